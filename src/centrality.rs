@@ -5,7 +5,7 @@ use crate::netview::NetviewGraph;
 use petgraph::algo::dijkstra;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize, Serialize, clap::ValueEnum)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, clap::ValueEnum)]
 pub enum NodeCentrality {
     Betweenness,
     Degree,
@@ -22,6 +22,15 @@ impl std::fmt::Display for NodeCentrality {
     }
 }
 
+// Standardized centrality of all nodes with the centrality metric used in label propagation
+pub fn node_centrality(graph: &NetviewGraph, metric: &NodeCentrality) -> HashMap<usize, f64> {
+    match metric {
+        NodeCentrality::Betweenness => betweenness_centrality(graph, true),
+        NodeCentrality::Degree => degree_centrality(graph, true),
+        NodeCentrality::Closeness => closeness_centrality(graph, true),
+    }
+}
+
 pub fn standardize_centrality(centrality: &mut HashMap<usize, f64>) {
     if centrality.is_empty() {
         return;
@@ -0,0 +1,392 @@
+// Columnar export of node, edge and matrix tables for Polars, DuckDB and Arrow
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use parquet::format::KeyValue;
+use petgraph::visit::EdgeRef;
+
+use crate::dist::make_symmetrical;
+use crate::error::NetviewError;
+use crate::label::format_rank_confidence;
+use crate::metadata::{attribute_columns, AttributeType, AttributeValue};
+use crate::mknn::GraphMetadata;
+use crate::netview::NetviewGraph;
+
+/// Key of the run metadata (`GraphMetadata` as JSON) in the file metadata
+pub const METADATA_KEY: &str = "netview";
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum ColumnarFormat {
+    Parquet,
+    Arrow
+}
+impl std::fmt::Display for ColumnarFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            ColumnarFormat::Parquet => "parquet",
+            ColumnarFormat::Arrow => "arrow",
+        };
+        write!(f, "{}", output)
+    }
+}
+
+/// Builds the node table with one typed column per `NodeLabel` field and
+/// one nullable column per node attribute with the common attribute type.
+pub fn node_record_batch(graph: &NetviewGraph) -> Result<RecordBatch, NetviewError> {
+
+    let nodes: Vec<_> = graph.node_weights().collect();
+
+    let mut fields = vec![
+        Field::new("index", DataType::UInt64, false),
+        Field::new("id", DataType::Utf8, true),
+        Field::new("label", DataType::Utf8, true),
+        Field::new("label_confidence", DataType::Float64, false),
+        Field::new("runner_up_label", DataType::Utf8, true),
+        Field::new("runner_up_confidence", DataType::Float64, true),
+        Field::new("rank_confidence", DataType::Utf8, true),
+        Field::new("abstention", DataType::Utf8, true),
+        Field::new("label_probabilities", DataType::Utf8, true),
+        Field::new("community", DataType::UInt64, true),
+        Field::new("layout_x", DataType::Float64, true),
+        Field::new("layout_y", DataType::Float64, true),
+    ];
+
+    // Label probabilities from label spreading as JSON objects
+    let label_probabilities = nodes.iter()
+        .map(|node| node.label_probabilities.as_ref().map(serde_json::to_string).transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(nodes.iter().map(|node| node.index as u64))),
+        Arc::new(StringArray::from_iter(nodes.iter().map(|node| node.id.as_deref()))),
+        Arc::new(StringArray::from_iter(nodes.iter().map(|node| node.label.as_deref()))),
+        Arc::new(Float64Array::from_iter_values(nodes.iter().map(|node| node.label_confidence))),
+        Arc::new(StringArray::from_iter(nodes.iter().map(|node| node.runner_up_label.as_deref()))),
+        Arc::new(Float64Array::from_iter(nodes.iter().map(|node| node.runner_up_label.as_ref().map(|_| node.runner_up_confidence)))),
+        Arc::new(StringArray::from_iter(nodes.iter().map(|node| node.rank_confidence.as_deref().map(format_rank_confidence)))),
+        Arc::new(StringArray::from_iter(nodes.iter().map(|node| node.abstention.as_deref()))),
+        Arc::new(StringArray::from_iter(label_probabilities)),
+        Arc::new(UInt64Array::from_iter(nodes.iter().map(|node| node.community.map(|community| community as u64)))),
+        Arc::new(Float64Array::from_iter(nodes.iter().map(|node| node.layout.map(|layout| layout[0])))),
+        Arc::new(Float64Array::from_iter(nodes.iter().map(|node| node.layout.map(|layout| layout[1])))),
+    ];
+
+    for (name, attribute_type) in attribute_columns(graph) {
+        if fields.iter().any(|field| field.name() == &name) {
+            log::warn!("Node attribute '{name}' is not written to the node table (reserved column)");
+            continue;
+        }
+        let values: Vec<Option<AttributeValue>> = nodes.iter()
+            .map(|node| node.attributes.get(&name).map(|value| value.cast(&attribute_type)))
+            .collect();
+
+        let (data_type, column): (DataType, ArrayRef) = match attribute_type {
+            AttributeType::Boolean => (DataType::Boolean, Arc::new(BooleanArray::from_iter(values.iter().map(|value| match value {
+                Some(AttributeValue::Boolean(value)) => Some(*value),
+                _ => None
+            })))),
+            AttributeType::Integer => (DataType::Int64, Arc::new(Int64Array::from_iter(values.iter().map(|value| match value {
+                Some(AttributeValue::Integer(value)) => Some(*value),
+                _ => None
+            })))),
+            AttributeType::Float => (DataType::Float64, Arc::new(Float64Array::from_iter(values.iter().map(|value| match value {
+                Some(AttributeValue::Float(value)) => Some(*value),
+                _ => None
+            })))),
+            AttributeType::Text => (DataType::Utf8, Arc::new(StringArray::from_iter(values.iter().map(|value| match value {
+                Some(AttributeValue::Text(value)) => Some(value.as_str()),
+                _ => None
+            })))),
+        };
+        fields.push(Field::new(name, data_type, true));
+        columns.push(column);
+    }
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?)
+}
+
+/// Builds the edge table with one typed column per `EdgeLabel` field and
+/// the identifiers of the source and target nodes.
+pub fn edge_record_batch(graph: &NetviewGraph) -> Result<RecordBatch, NetviewError> {
+
+    let edges: Vec<_> = graph.edge_references().collect();
+
+    let schema = Schema::new(vec![
+        Field::new("index", DataType::UInt64, false),
+        Field::new("source", DataType::UInt64, false),
+        Field::new("target", DataType::UInt64, false),
+        Field::new("source_id", DataType::Utf8, true),
+        Field::new("target_id", DataType::Utf8, true),
+        Field::new("weight", DataType::Float64, false),
+        Field::new("ani", DataType::Float64, true),
+        Field::new("aai", DataType::Float64, true),
+        Field::new("af", DataType::Float64, true),
+    ]);
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(edges.iter().map(|edge| edge.weight().index as u64))),
+        Arc::new(UInt64Array::from_iter_values(edges.iter().map(|edge| edge.weight().source as u64))),
+        Arc::new(UInt64Array::from_iter_values(edges.iter().map(|edge| edge.weight().target as u64))),
+        Arc::new(StringArray::from_iter(edges.iter().map(|edge| graph[edge.source()].id.as_deref()))),
+        Arc::new(StringArray::from_iter(edges.iter().map(|edge| graph[edge.target()].id.as_deref()))),
+        Arc::new(Float64Array::from_iter_values(edges.iter().map(|edge| edge.weight().weight))),
+        Arc::new(Float64Array::from_iter(edges.iter().map(|edge| edge.weight().ani))),
+        Arc::new(Float64Array::from_iter(edges.iter().map(|edge| edge.weight().aai))),
+        Arc::new(Float64Array::from_iter(edges.iter().map(|edge| edge.weight().af))),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Builds a long-form table of a symmetrical or lower triangular matrix.
+///
+/// Each pair `i < j` is written once as a row with the row and column indices,
+/// their identifiers (if provided) and the matrix value. The diagonal is
+/// included if `diagonal` is `true`.
+pub fn matrix_record_batch(
+    matrix: &Vec<Vec<f64>>,
+    ids: Option<&Vec<String>>,
+    diagonal: bool
+) -> Result<RecordBatch, NetviewError> {
+
+    let matrix = make_symmetrical(matrix)?;
+    let n = matrix.len();
+
+    if let Some(ids) = ids {
+        if ids.len() != n {
+            return Err(NetviewError::InvalidDimensions);
+        }
+    }
+
+    let pairs: Vec<(usize, usize)> = (0..n)
+        .flat_map(|i| (i..n).map(move |j| (i, j)))
+        .filter(|(i, j)| diagonal || i != j)
+        .collect();
+
+    let schema = Schema::new(vec![
+        Field::new("i", DataType::UInt64, false),
+        Field::new("j", DataType::UInt64, false),
+        Field::new("i_id", DataType::Utf8, true),
+        Field::new("j_id", DataType::Utf8, true),
+        Field::new("value", DataType::Float64, false),
+    ]);
+
+    let id = |index: usize| ids.map(|ids| ids[index].as_str());
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(pairs.iter().map(|(i, _)| *i as u64))),
+        Arc::new(UInt64Array::from_iter_values(pairs.iter().map(|(_, j)| *j as u64))),
+        Arc::new(StringArray::from_iter(pairs.iter().map(|(i, _)| id(*i)))),
+        Arc::new(StringArray::from_iter(pairs.iter().map(|(_, j)| id(*j)))),
+        Arc::new(Float64Array::from_iter_values(pairs.iter().map(|(i, j)| matrix[*i][*j]))),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Writes a record batch as Parquet (Snappy compressed) or Arrow IPC file.
+///
+/// Run metadata is stored as JSON under the `netview` key in the schema
+/// metadata and, for Parquet, in the file key-value metadata.
+pub fn write_record_batch(
+    batch: &RecordBatch,
+    path: &Path,
+    format: &ColumnarFormat,
+    metadata: Option<&GraphMetadata>
+) -> Result<(), NetviewError> {
+
+    let metadata = match metadata {
+        Some(metadata) => Some(serde_json::to_string(metadata)?),
+        None => None
+    };
+
+    let batch = match &metadata {
+        Some(json) => {
+            let schema = batch.schema().as_ref().clone().with_metadata(
+                HashMap::from([(METADATA_KEY.to_string(), json.clone())])
+            );
+            batch.clone().with_schema(Arc::new(schema))?
+        },
+        None => batch.clone()
+    };
+
+    let file = File::create(path)?;
+
+    match format {
+        ColumnarFormat::Parquet => {
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .set_key_value_metadata(metadata.map(|json| vec![KeyValue::new(METADATA_KEY.to_string(), json)]))
+                .build();
+            let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))?;
+            writer.write(&batch)?;
+            writer.close()?;
+        },
+        ColumnarFormat::Arrow => {
+            let mut writer = FileWriter::try_new(file, &batch.schema())?;
+            writer.write(&batch)?;
+            writer.finish()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the node and edge tables of a graph to two columnar files.
+pub fn write_graph_tables(
+    graph: &NetviewGraph,
+    nodes: &Path,
+    edges: &Path,
+    format: &ColumnarFormat,
+    metadata: Option<&GraphMetadata>
+) -> Result<(), NetviewError> {
+
+    log::info!("Writing node table to: {}", nodes.display());
+    write_record_batch(&node_record_batch(graph)?, nodes, format, metadata)?;
+
+    log::info!("Writing edge table to: {}", edges.display());
+    write_record_batch(&edge_record_batch(graph)?, edges, format, metadata)?;
+
+    Ok(())
+}
+
+/// Writes a matrix in long form to a columnar file, see `matrix_record_batch`.
+pub fn write_matrix_table(
+    matrix: &Vec<Vec<f64>>,
+    ids: Option<&Vec<String>>,
+    path: &Path,
+    format: &ColumnarFormat,
+    diagonal: bool
+) -> Result<(), NetviewError> {
+
+    log::info!("Writing long-form matrix table to: {}", path.display());
+    write_record_batch(&matrix_record_batch(matrix, ids, diagonal)?, path, format, None)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::mknn::convert_to_graph;
+    use arrow::array::Array;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn test_graph() -> NetviewGraph {
+        let mnn = vec![vec![1], vec![0, 2], vec![1]];
+        let dist = vec![vec![0.0, 1.0, 2.0], vec![1.0, 0.0, 3.0], vec![2.0, 3.0, 0.0]];
+        let ids = Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        convert_to_graph(&mnn, Some(&dist), None, ids, None).unwrap()
+    }
+
+    #[test]
+    fn test_node_and_edge_tables() {
+        let graph = test_graph();
+
+        let nodes = node_record_batch(&graph).unwrap();
+        assert_eq!(nodes.num_rows(), 3);
+        assert_eq!(nodes.schema().field_with_name("label_confidence").unwrap().data_type(), &DataType::Float64);
+        assert_eq!(nodes.column_by_name("label").unwrap().null_count(), 3);
+        assert_eq!(nodes.column_by_name("community").unwrap().null_count(), 3);
+
+        let edges = edge_record_batch(&graph).unwrap();
+        assert_eq!(edges.num_rows(), 2);
+        let target_id = edges.column_by_name("target_id").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(target_id.value(1), "c");
+        assert_eq!(edges.column_by_name("af").unwrap().null_count(), 2);
+    }
+
+    #[test]
+    fn test_node_attribute_columns() {
+        let mut graph = test_graph();
+        graph[petgraph::graph::NodeIndex::new(0)].attributes.insert("year".to_string(), AttributeValue::Integer(2019));
+        graph[petgraph::graph::NodeIndex::new(2)].attributes.insert("year".to_string(), AttributeValue::Integer(2021));
+        graph[petgraph::graph::NodeIndex::new(1)].attributes.insert("resistant".to_string(), AttributeValue::Boolean(true));
+
+        let nodes = node_record_batch(&graph).unwrap();
+        assert_eq!(nodes.schema().field_with_name("year").unwrap().data_type(), &DataType::Int64);
+        assert_eq!(nodes.schema().field_with_name("resistant").unwrap().data_type(), &DataType::Boolean);
+        let year = nodes.column_by_name("year").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(year.value(2), 2021);
+        assert_eq!(year.null_count(), 1);
+    }
+
+    #[test]
+    fn test_node_prediction_columns() {
+        let mut graph = test_graph();
+        let node = &mut graph[petgraph::graph::NodeIndex::new(1)];
+        node.runner_up_label = Some("B".to_string());
+        node.runner_up_confidence = 0.25;
+        node.rank_confidence = Some(vec![1.0, 0.75]);
+        node.abstention = Some("distance".to_string());
+        node.label_probabilities = Some([("A".to_string(), 0.75), ("B".to_string(), 0.25)].into());
+        node.community = Some(2);
+        node.layout = Some([0.5, -1.0]);
+
+        let nodes = node_record_batch(&graph).unwrap();
+        let text = |name: &str| nodes.column_by_name(name).unwrap().as_any().downcast_ref::<StringArray>().unwrap().clone();
+        let float = |name: &str| nodes.column_by_name(name).unwrap().as_any().downcast_ref::<Float64Array>().unwrap().clone();
+
+        assert_eq!(text("runner_up_label").value(1), "B");
+        assert_eq!(float("runner_up_confidence").value(1), 0.25);
+        assert_eq!(float("runner_up_confidence").null_count(), 2);
+        assert_eq!(text("rank_confidence").value(1), "1.0000;0.7500");
+        assert_eq!(text("abstention").value(1), "distance");
+        assert_eq!(text("label_probabilities").value(1), r#"{"A":0.75,"B":0.25}"#);
+        assert_eq!(float("layout_y").value(1), -1.0);
+        let community = nodes.column_by_name("community").unwrap().as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!((community.value(1), community.null_count()), (2, 2));
+    }
+
+    #[test]
+    fn test_matrix_long_form() {
+        let matrix = vec![vec![0.0], vec![1.0, 0.0], vec![2.0, 3.0, 0.0]];
+
+        let batch = matrix_record_batch(&matrix, None, false).unwrap();
+        let value = batch.column_by_name("value").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(value.values().to_vec(), vec![1.0, 2.0, 3.0]);
+
+        assert_eq!(matrix_record_batch(&matrix, None, true).unwrap().num_rows(), 6);
+        assert!(matrix_record_batch(&matrix, Some(&vec!["a".to_string()]), false).is_err());
+    }
+
+    #[test]
+    fn test_write_parquet_with_metadata() {
+        let graph = test_graph();
+        let dir = tempfile::tempdir().unwrap();
+        let (nodes, edges) = (dir.path().join("nodes.parquet"), dir.path().join("edges.parquet"));
+
+        let metadata = GraphMetadata::new().k(2);
+        write_graph_tables(&graph, &nodes, &edges, &ColumnarFormat::Parquet, Some(&metadata)).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&edges).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+
+        let key_values = reader.metadata().file_metadata().key_value_metadata().unwrap();
+        let json = key_values.iter().find(|kv| kv.key == METADATA_KEY).unwrap().value.clone().unwrap();
+        let read: GraphMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.k, Some(2));
+    }
+
+    #[test]
+    fn test_write_arrow_ipc() {
+        let graph = test_graph();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nodes.arrow");
+        write_record_batch(&node_record_batch(&graph).unwrap(), &path, &ColumnarFormat::Arrow, None).unwrap();
+
+        let reader = arrow::ipc::reader::FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches[0].num_rows(), 3);
+    }
+}
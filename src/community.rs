@@ -0,0 +1,1161 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+use petgraph::visit::EdgeRef;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::dist::{Tree, TreeNode};
+use crate::error::NetviewError;
+use crate::mknn::node_id_or_index;
+use crate::netview::NetviewGraph;
+use crate::utils::get_file_writer;
+
+/// Community detection methods
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, clap::ValueEnum)]
+pub enum CommunityMethod {
+    Louvain,    // Blondel et al. (2008) multi-level modularity optimization
+    Leiden,     // Traag et al. (2019) with refinement for well-connected communities
+    Walktrap,   // Pons & Latapy (2005) agglomeration by random walk distances
+    Infomap,    // Rosvall & Bergstrom (2008) two-level map equation optimization
+}
+impl std::fmt::Display for CommunityMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            CommunityMethod::Louvain => "louvain",
+            CommunityMethod::Leiden => "leiden",
+            CommunityMethod::Walktrap => "walktrap",
+            CommunityMethod::Infomap => "infomap",
+        };
+        write!(f, "{}", output)
+    }
+}
+
+/// Settings for community detection
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommunityConfig {
+    pub method: CommunityMethod,
+    pub resolution: f64,        // Resolution of the modularity, larger values give smaller communities
+    pub seed: u64,              // Seed for the node visiting order
+    pub max_levels: usize,      // Maximum number of aggregation levels
+    pub distance_percent: bool, // Edge similarity is 1 - distance / 100 instead of 1 - distance
+    pub steps: usize,           // Length of random walks for walktrap
+    pub communities: Option<usize>, // Walktrap dendrogram cut, otherwise cut at maximum modularity
+}
+
+impl Default for CommunityConfig {
+    fn default() -> Self {
+        Self {
+            method: CommunityMethod::Leiden,
+            resolution: 1.0,
+            seed: 42,
+            max_levels: 100,
+            distance_percent: true,
+            steps: 4,
+            communities: None
+        }
+    }
+}
+
+/// Weighted undirected network with symmetric adjacency lists and without self-loops;
+/// node strengths include the weight of nodes aggregated into this node
+#[derive(Clone, Debug)]
+pub(crate) struct Network {
+    pub(crate) neighbors: Vec<Vec<(usize, f64)>>,
+    pub(crate) strength: Vec<f64>,
+    pub(crate) total: f64,      // Sum of strengths, twice the total edge weight
+}
+
+impl Network {
+    /// Network with edge similarity weights derived from `EdgeLabel.weight`
+    pub(crate) fn from_graph(graph: &NetviewGraph, distance_percent: bool) -> Self {
+        let n = graph.node_count();
+        let mut neighbors = vec![Vec::new(); n];
+
+        for edge_ref in graph.edge_references() {
+            let (i, j) = (edge_ref.source().index(), edge_ref.target().index());
+            let distance = edge_ref.weight().weight;
+            let similarity = match distance_percent {
+                true => 1.0 - distance / 100.0,
+                false => 1.0 - distance
+            };
+            if i != j && similarity > 0.0 {
+                neighbors[i].push((j, similarity));
+                neighbors[j].push((i, similarity));
+            }
+        }
+        let strength: Vec<f64> = neighbors.iter().map(|row| row.iter().map(|(_, w)| w).sum()).collect();
+        let total = strength.iter().sum();
+
+        Self { neighbors, strength, total }
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+    // Collapse nodes into their communities, internal edges become part of the strength only
+    fn aggregate(&self, membership: &[usize], communities: usize) -> Self {
+        let mut weights: Vec<HashMap<usize, f64>> = vec![HashMap::new(); communities];
+        let mut strength = vec![0.0; communities];
+
+        for (i, row) in self.neighbors.iter().enumerate() {
+            let ci = membership[i];
+            strength[ci] += self.strength[i];
+            for &(j, w) in row {
+                let cj = membership[j];
+                if ci != cj {
+                    *weights[ci].entry(cj).or_insert(0.0) += w;
+                }
+            }
+        }
+        let neighbors = weights.into_iter()
+            .map(|row| {
+                let mut row: Vec<(usize, f64)> = row.into_iter().collect();
+                row.sort_by_key(|(j, _)| *j);
+                row
+            })
+            .collect();
+
+        Self { neighbors, strength, total: self.total }
+    }
+}
+
+/// Community membership of each node in graph order
+#[derive(Clone, Debug)]
+pub struct Communities {
+    pub membership: Vec<usize>,     // Community of each node, numbered by decreasing size
+    pub modularity: f64,            // Modularity of the partition at the configured resolution
+    pub codelength: Option<f64>,    // Map equation codelength in bits (infomap)
+    pub dendrogram: Option<Dendrogram>, // Merge dendrogram (walktrap)
+}
+
+impl Communities {
+    /// Number of communities.
+    pub fn count(&self) -> usize {
+        self.membership.iter().max().map_or(0, |c| c + 1)
+    }
+    /// Stores community membership on the nodes of the graph.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetviewError::NodeLabelLengthError` if the graph does not have one node per membership.
+    pub fn apply(&self, graph: &mut NetviewGraph) -> Result<(), NetviewError> {
+        if graph.node_count() != self.membership.len() {
+            return Err(NetviewError::NodeLabelLengthError(graph.node_count()))
+        }
+        for (node, community) in graph.node_weights_mut().zip(&self.membership) {
+            node.community = Some(*community);
+        }
+        Ok(())
+    }
+    /// Writes the membership table as TSV with columns `id` and `community`.
+    pub fn write_membership(&self, graph: &NetviewGraph, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        writeln!(writer, "id\tcommunity")?;
+        for (node, community) in graph.node_weights().zip(&self.membership) {
+            writeln!(writer, "{}\t{}", node_id_or_index(node), community)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Detects communities in a graph.
+///
+/// Louvain and Leiden optimize modularity, walktrap agglomerates communities by
+/// random walk distances and infomap minimizes the two-level map equation. Walktrap
+/// is cut at the configured number of communities or at maximum modularity.
+///
+/// Edges are weighted by similarity `1 - distance` (or `1 - distance / 100` for
+/// distances in percent), edges without positive similarity are ignored. Results
+/// are reproducible for a given seed. Community identifiers are ordered by
+/// decreasing community size, ties by the first node of the community.
+///
+/// # Example
+///
+/// ```
+/// let communities = detect_communities(&graph, &CommunityConfig::default()).unwrap();
+/// communities.apply(&mut graph).unwrap();
+/// ```
+pub fn detect_communities(graph: &NetviewGraph, config: &CommunityConfig) -> Result<Communities, NetviewError> {
+
+    if config.resolution < 0.0 || !config.resolution.is_finite() {
+        return Err(NetviewError::CommunityConfigError(format!("invalid resolution: {}", config.resolution)))
+    }
+    if config.method == CommunityMethod::Walktrap && config.steps == 0 {
+        return Err(NetviewError::CommunityConfigError("walktrap requires at least one step".to_string()))
+    }
+
+    let network = Network::from_graph(graph, config.distance_percent);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let mut codelength = None;
+    let mut dendrogram = None;
+
+    let membership = match config.method {
+        CommunityMethod::Louvain => louvain(&network, config, &mut rng),
+        CommunityMethod::Leiden => leiden(&network, config, &mut rng),
+        CommunityMethod::Walktrap => {
+            let merges = walktrap(&network, config.steps, config.resolution);
+            let membership = match config.communities {
+                Some(communities) => merges.cut(communities)?,
+                None => merges.cut_at_step(merges.best_step())
+            };
+            dendrogram = Some(merges);
+            membership
+        },
+        CommunityMethod::Infomap => {
+            let membership = infomap(&network, config, &mut rng);
+            codelength = Some(map_equation(&network, &membership));
+            membership
+        }
+    };
+    let membership = order_by_size(&membership);
+    let modularity = modularity(&network, &membership, config.resolution);
+
+    Ok(Communities { membership, modularity, codelength, dendrogram })
+}
+
+/// Modularity of a partition with resolution `γ`: `Q = Σ_c [ w_c / m - γ (K_c / 2m)² ]`
+/// where `w_c` is the internal edge weight and `K_c` the total strength of community `c`.
+pub(crate) fn modularity(network: &Network, membership: &[usize], resolution: f64) -> f64 {
+    if network.total <= 0.0 {
+        return 0.0
+    }
+    let communities = membership.iter().max().map_or(0, |c| c + 1);
+    let mut internal = vec![0.0; communities];
+    let mut strength = vec![0.0; communities];
+
+    for (i, row) in network.neighbors.iter().enumerate() {
+        strength[membership[i]] += network.strength[i];
+        for &(j, w) in row {
+            if membership[i] == membership[j] {
+                internal[membership[i]] += w;  // each edge is visited from both ends
+            }
+        }
+    }
+    internal.iter().zip(&strength)
+        .map(|(w, k)| w / network.total - resolution * (k / network.total).powi(2))
+        .sum()
+}
+
+fn louvain(network: &Network, config: &CommunityConfig, rng: &mut StdRng) -> Vec<usize> {
+    let mut node_membership: Vec<usize> = (0..network.len()).collect();
+    let mut level = network.clone();
+
+    for _ in 0..config.max_levels {
+        let mut membership: Vec<usize> = (0..level.len()).collect();
+        if !move_nodes(&level, &mut membership, config.resolution, rng) {
+            break
+        }
+        let communities = renumber(&mut membership);
+        node_membership.iter_mut().for_each(|c| *c = membership[*c]);
+        level = level.aggregate(&membership, communities);
+    }
+    node_membership
+}
+
+fn leiden(network: &Network, config: &CommunityConfig, rng: &mut StdRng) -> Vec<usize> {
+    let mut node_membership: Vec<usize> = (0..network.len()).collect();
+    let mut level = network.clone();
+    let mut membership: Vec<usize> = (0..level.len()).collect();
+
+    for _ in 0..config.max_levels {
+        move_nodes(&level, &mut membership, config.resolution, rng);
+        let communities = renumber(&mut membership);
+        if communities == level.len() {
+            break
+        }
+
+        // Aggregate the refined partition, aggregate nodes start in their unrefined community
+        let mut refined = refine(&level, &membership, config.resolution, rng);
+        let subcommunities = renumber(&mut refined);
+        if subcommunities == level.len() {
+            break
+        }
+
+        let mut aggregate_membership = vec![0; subcommunities];
+        for (i, s) in refined.iter().enumerate() {
+            aggregate_membership[*s] = membership[i];
+        }
+        node_membership.iter_mut().for_each(|c| *c = refined[*c]);
+        level = level.aggregate(&refined, subcommunities);
+        membership = aggregate_membership;
+    }
+    node_membership.iter().map(|c| membership[*c]).collect()
+}
+
+// Local moving of nodes between communities until no move improves the quality,
+// returns whether any node changed its community
+fn move_nodes(network: &Network, membership: &mut [usize], resolution: f64, rng: &mut StdRng) -> bool {
+    let n = network.len();
+    if network.total <= 0.0 {
+        return false
+    }
+
+    let mut community_strength = vec![0.0; n];
+    for (i, c) in membership.iter().enumerate() {
+        community_strength[*c] += network.strength[i];
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    let mut improved = false;
+    let mut weights: HashMap<usize, f64> = HashMap::new();
+
+    loop {
+        order.shuffle(rng);
+        let mut moved = false;
+
+        for &i in &order {
+            let current = membership[i];
+            let strength = network.strength[i];
+
+            weights.clear();
+            for &(j, w) in &network.neighbors[i] {
+                *weights.entry(membership[j]).or_insert(0.0) += w;
+            }
+
+            community_strength[current] -= strength;
+            let gain = |community: usize, weight: f64| {
+                weight - resolution * strength * community_strength[community] / network.total
+            };
+
+            let mut best = current;
+            let mut best_gain = gain(current, weights.get(&current).copied().unwrap_or(0.0));
+            let mut candidates: Vec<(usize, f64)> = weights.iter().map(|(c, w)| (*c, *w)).collect();
+            candidates.sort_by_key(|(c, _)| *c);  // deterministic for a given seed
+
+            for (community, weight) in candidates {
+                let g = gain(community, weight);
+                if g > best_gain + 1e-12 {
+                    (best, best_gain) = (community, g);
+                }
+            }
+
+            community_strength[best] += strength;
+            if best != current {
+                membership[i] = best;
+                moved = true;
+                improved = true;
+            }
+        }
+        if !moved {
+            break
+        }
+    }
+    improved
+}
+
+// Leiden refinement: merge singletons within each community into well-connected subcommunities
+fn refine(network: &Network, membership: &[usize], resolution: f64, rng: &mut StdRng) -> Vec<usize> {
+    let n = network.len();
+    let mut refined: Vec<usize> = (0..n).collect();
+    let mut size = vec![1usize; n];
+    let mut sub_strength = network.strength.clone();
+
+    let mut community_strength: HashMap<usize, f64> = HashMap::new();
+    for (i, c) in membership.iter().enumerate() {
+        *community_strength.entry(*c).or_insert(0.0) += network.strength[i];
+    }
+
+    // Edge weight of each subcommunity to the rest of its community
+    let mut external: Vec<f64> = (0..n)
+        .map(|i| network.neighbors[i].iter().filter(|(j, _)| membership[*j] == membership[i]).map(|(_, w)| w).sum())
+        .collect();
+
+    let well_connected = |external: f64, strength: f64, community: f64| {
+        external >= resolution * strength * (community - strength) / network.total
+    };
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.shuffle(rng);
+
+    for i in order {
+        let own = refined[i];
+        let community = community_strength[&membership[i]];
+        if size[own] > 1 || !well_connected(external[i], network.strength[i], community) {
+            continue
+        }
+
+        let mut weights: HashMap<usize, f64> = HashMap::new();
+        for &(j, w) in &network.neighbors[i] {
+            if membership[j] == membership[i] && refined[j] != own {
+                *weights.entry(refined[j]).or_insert(0.0) += w;
+            }
+        }
+        let mut candidates: Vec<(usize, f64)> = weights.into_iter().collect();
+        candidates.sort_by_key(|(s, _)| *s);
+
+        let mut best: Option<(usize, f64, f64)> = None;
+        for (s, weight) in candidates {
+            if !well_connected(external[s], sub_strength[s], community) {
+                continue
+            }
+            let gain = weight - resolution * network.strength[i] * sub_strength[s] / network.total;
+            if gain >= 0.0 && best.map_or(true, |(_, best_gain, _)| gain > best_gain) {
+                best = Some((s, gain, weight));
+            }
+        }
+
+        if let Some((s, _, weight)) = best {
+            refined[i] = s;
+            size[s] += 1;
+            size[own] = 0;
+            sub_strength[s] += network.strength[i];
+            external[s] += external[i] - 2.0 * weight;
+        }
+    }
+    refined
+}
+
+/// Merge of two clusters in a dendrogram, leaves are `0..n` and the cluster
+/// created by merge `i` is `n + i` as in `hclust` and `igraph`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    pub left: usize,
+    pub right: usize,
+    pub height: f64,        // Cumulative increase of the walktrap distance, monotone in merge order
+    pub size: usize,        // Number of leaves in the merged cluster
+    pub modularity: f64,    // Modularity of the partition after this merge
+}
+
+/// Agglomerative merge dendrogram over the nodes of a graph.
+///
+/// Disconnected components are never merged, so the dendrogram has `n - c`
+/// merges for a graph with `c` connected components.
+#[derive(Clone, Debug)]
+pub struct Dendrogram {
+    pub leaves: usize,
+    pub initial_modularity: f64,    // Modularity of the partition into singletons
+    pub merges: Vec<Merge>,
+}
+
+impl Dendrogram {
+    /// Number of merges with the highest modularity, zero for the partition into singletons.
+    pub fn best_step(&self) -> usize {
+        let mut best = (0, self.initial_modularity);
+        for (i, merge) in self.merges.iter().enumerate() {
+            if merge.modularity > best.1 {
+                best = (i + 1, merge.modularity);
+            }
+        }
+        best.0
+    }
+    /// Membership after the first `steps` merges.
+    pub fn cut_at_step(&self, steps: usize) -> Vec<usize> {
+        let mut cluster: Vec<usize> = (0..self.leaves).collect();
+        let mut parent: Vec<usize> = (0..self.leaves + self.merges.len()).collect();
+        for (i, merge) in self.merges.iter().take(steps).enumerate() {
+            parent[merge.left] = self.leaves + i;
+            parent[merge.right] = self.leaves + i;
+        }
+        for c in cluster.iter_mut() {
+            while parent[*c] != *c {
+                *c = parent[*c];
+            }
+        }
+        order_by_size(&cluster)
+    }
+    /// Membership with the given number of communities.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetviewError::CommunityConfigError` if the number of communities is zero,
+    /// larger than the number of nodes or smaller than the number of connected components.
+    pub fn cut(&self, communities: usize) -> Result<Vec<usize>, NetviewError> {
+        let min = self.leaves - self.merges.len();
+        if communities == 0 || communities > self.leaves || communities < min {
+            return Err(NetviewError::CommunityConfigError(format!(
+                "cannot cut dendrogram into {communities} communities (min: {min}, max: {})", self.leaves
+            )))
+        }
+        Ok(self.cut_at_step(self.leaves - communities))
+    }
+    /// Writes the merges as TSV with columns `step`, `left`, `right`, `height`, `size` and `modularity`.
+    pub fn write_merges(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        writeln!(writer, "step\tleft\tright\theight\tsize\tmodularity")?;
+        for (i, merge) in self.merges.iter().enumerate() {
+            writeln!(writer, "{}\t{}\t{}\t{}\t{}\t{}", i + 1, merge.left, merge.right, merge.height, merge.size, merge.modularity)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    /// Converts the dendrogram into a tree with branch lengths from merge heights,
+    /// which can be written as Newick with `dist::write_newick_to_file`. Roots of
+    /// disconnected components are joined at the height of the last merge.
+    pub fn to_tree(&self) -> Tree {
+        let mut nodes: Vec<TreeNode> = (0..self.leaves).map(|i| TreeNode { tip: Some(i), children: Vec::new() }).collect();
+        let mut heights = vec![0.0; self.leaves];
+        let mut is_root = vec![true; self.leaves + self.merges.len()];
+
+        for merge in &self.merges {
+            nodes.push(TreeNode {
+                tip: None,
+                children: vec![
+                    (merge.left, merge.height - heights[merge.left]),
+                    (merge.right, merge.height - heights[merge.right])
+                ]
+            });
+            heights.push(merge.height);
+            is_root[merge.left] = false;
+            is_root[merge.right] = false;
+        }
+
+        let roots: Vec<usize> = (0..nodes.len()).filter(|i| is_root[*i]).collect();
+        let root = match roots.as_slice() {
+            [root] => *root,
+            _ => {
+                let height = heights.iter().cloned().fold(0.0, f64::max);
+                nodes.push(TreeNode { tip: None, children: roots.iter().map(|r| (*r, height - heights[*r])).collect() });
+                nodes.len() - 1
+            }
+        };
+        Tree { nodes, root }
+    }
+}
+
+// Sparse probability vector sorted by node
+type SparseVector = Vec<(usize, f64)>;
+
+#[derive(PartialEq)]
+struct Candidate {
+    delta: f64,
+    left: usize,
+    right: usize,
+}
+impl Eq for Candidate {}
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.delta.total_cmp(&other.delta)
+            .then((self.left, self.right).cmp(&(other.left, other.right)))
+    }
+}
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Walktrap agglomeration, every node has a self-loop with the mean weight of its edges
+fn walktrap(network: &Network, steps: usize, resolution: f64) -> Dendrogram {
+    let n = network.len();
+
+    let loops: Vec<f64> = network.neighbors.iter()
+        .map(|row| if row.is_empty() { 1.0 } else { row.iter().map(|(_, w)| w).sum::<f64>() / row.len() as f64 })
+        .collect();
+    let degree: Vec<f64> = network.strength.iter().zip(&loops).map(|(s, l)| s + l).collect();
+
+    // Random walk of length t from each node: P^t_i = e_i (D^-1 A)^t
+    let walk = |start: usize| -> SparseVector {
+        let mut probabilities: HashMap<usize, f64> = HashMap::from([(start, 1.0)]);
+        for _ in 0..steps {
+            let mut next: HashMap<usize, f64> = HashMap::new();
+            for (&j, &p) in &probabilities {
+                *next.entry(j).or_insert(0.0) += p * loops[j] / degree[j];
+                for &(k, w) in &network.neighbors[j] {
+                    *next.entry(k).or_insert(0.0) += p * w / degree[j];
+                }
+            }
+            probabilities = next;
+        }
+        let mut vector: SparseVector = probabilities.into_iter().collect();
+        vector.sort_by_key(|(k, _)| *k);
+        vector
+    };
+
+    let mut vectors: Vec<Option<SparseVector>> = (0..n).map(|i| Some(walk(i))).collect();
+    let mut sizes: Vec<usize> = vec![1; n];
+    let mut strength: Vec<f64> = network.strength.clone();
+    let mut adjacency: Vec<HashMap<usize, f64>> = network.neighbors.iter()
+        .map(|row| row.iter().cloned().collect())
+        .collect();
+
+    // Squared random walk distance between communities weighted by their sizes (Δσ)
+    let delta_sigma = |a: &SparseVector, b: &SparseVector, size_a: usize, size_b: usize| -> f64 {
+        let (mut i, mut j, mut r) = (0, 0, 0.0);
+        while i < a.len() || j < b.len() {
+            let (k, d) = match (a.get(i), b.get(j)) {
+                (Some(&(ka, pa)), Some(&(kb, pb))) if ka == kb => { i += 1; j += 1; (ka, pa - pb) },
+                (Some(&(ka, pa)), Some(&(kb, _))) if ka < kb => { i += 1; (ka, pa) },
+                (Some(_), Some(&(kb, pb))) => { j += 1; (kb, -pb) },
+                (Some(&(ka, pa)), None) => { i += 1; (ka, pa) },
+                (None, Some(&(kb, pb))) => { j += 1; (kb, -pb) },
+                (None, None) => unreachable!()
+            };
+            r += d * d / degree[k];
+        }
+        (size_a * size_b) as f64 / (size_a + size_b) as f64 * r / n as f64
+    };
+
+    let mut heap: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+    for (i, row) in network.neighbors.iter().enumerate() {
+        for &(j, _) in row {
+            if i < j {
+                let delta = delta_sigma(vectors[i].as_ref().unwrap(), vectors[j].as_ref().unwrap(), 1, 1);
+                heap.push(Reverse(Candidate { delta, left: i, right: j }));
+            }
+        }
+    }
+
+    let total = network.total;
+    let mut modularity_value = match total > 0.0 {
+        true => -resolution * strength.iter().map(|s| (s / total).powi(2)).sum::<f64>(),
+        false => 0.0
+    };
+    let initial_modularity = modularity_value;
+
+    let mut merges = Vec::new();
+    let mut height = 0.0;
+
+    while let Some(Reverse(Candidate { delta, left, right })) = heap.pop() {
+        if vectors[left].is_none() || vectors[right].is_none() {
+            continue  // one of the communities was merged already
+        }
+        let id = n + merges.len();
+        let (a, b) = (vectors[left].take().unwrap(), vectors[right].take().unwrap());
+        let (size_a, size_b) = (sizes[left], sizes[right]);
+
+        // Merged probability vector is the size-weighted mean
+        let mut merged: HashMap<usize, f64> = HashMap::new();
+        for (k, p) in a {
+            *merged.entry(k).or_insert(0.0) += p * size_a as f64;
+        }
+        for (k, p) in b {
+            *merged.entry(k).or_insert(0.0) += p * size_b as f64;
+        }
+        let size = size_a + size_b;
+        let mut vector: SparseVector = merged.into_iter().map(|(k, p)| (k, p / size as f64)).collect();
+        vector.sort_by_key(|(k, _)| *k);
+
+        // Community adjacency with edge weights for the modularity of each cut
+        let mut neighbors = std::mem::take(&mut adjacency[left]);
+        for (k, w) in std::mem::take(&mut adjacency[right]) {
+            *neighbors.entry(k).or_insert(0.0) += w;
+        }
+        let between = neighbors.remove(&left).unwrap_or(0.0) + neighbors.remove(&right).unwrap_or(0.0);
+        let between = between / 2.0;  // counted from both communities
+        if total > 0.0 {
+            modularity_value += 2.0 * between / total - 2.0 * resolution * strength[left] * strength[right] / (total * total);
+        }
+
+        for (&k, &w) in &neighbors {
+            let row = &mut adjacency[k];
+            row.remove(&left);
+            row.remove(&right);
+            row.insert(id, w);
+        }
+
+        height += delta;
+        merges.push(Merge { left, right, height, size, modularity: modularity_value });
+
+        vectors.push(Some(vector));
+        sizes.push(size);
+        strength.push(strength[left] + strength[right]);
+
+        let mut candidates: Vec<usize> = neighbors.keys().copied().collect();
+        candidates.sort_unstable();
+        adjacency.push(neighbors);
+
+        for k in candidates {
+            let delta = delta_sigma(vectors[k].as_ref().unwrap(), vectors[id].as_ref().unwrap(), sizes[k], size);
+            heap.push(Reverse(Candidate { delta, left: k, right: id }));
+        }
+    }
+
+    Dendrogram { leaves: n, initial_modularity, merges }
+}
+
+fn plogp(p: f64) -> f64 {
+    if p > 0.0 { p * p.log2() } else { 0.0 }
+}
+
+/// Two-level map equation codelength in bits for an undirected network where node
+/// visit rates are proportional to strength and flow on an edge is `w / total`.
+pub(crate) fn map_equation(network: &Network, membership: &[usize]) -> f64 {
+    if network.total <= 0.0 {
+        return 0.0
+    }
+    let modules = membership.iter().max().map_or(0, |c| c + 1);
+    let mut exit = vec![0.0; modules];
+    let mut flow = vec![0.0; modules];
+    let mut node_entropy = 0.0;
+
+    for (i, row) in network.neighbors.iter().enumerate() {
+        let p = network.strength[i] / network.total;
+        flow[membership[i]] += p;
+        node_entropy += plogp(p);
+        for &(j, w) in row {
+            if membership[i] != membership[j] {
+                exit[membership[i]] += w / network.total;
+            }
+        }
+    }
+    codelength(&exit, &flow, node_entropy)
+}
+
+fn codelength(exit: &[f64], flow: &[f64], node_entropy: f64) -> f64 {
+    let total_exit: f64 = exit.iter().sum();
+    plogp(total_exit)
+        - 2.0 * exit.iter().map(|q| plogp(*q)).sum::<f64>()
+        - node_entropy
+        + exit.iter().zip(flow).map(|(q, p)| plogp(q + p)).sum::<f64>()
+}
+
+// Multi-level greedy minimization of the map equation (core algorithm of Infomap)
+fn infomap(network: &Network, config: &CommunityConfig, rng: &mut StdRng) -> Vec<usize> {
+    let mut node_membership: Vec<usize> = (0..network.len()).collect();
+    let mut level = network.clone();
+
+    // Visit rates of original nodes are constant across levels
+    let node_entropy: f64 = network.strength.iter().map(|s| plogp(s / network.total.max(f64::MIN_POSITIVE))).sum();
+
+    for _ in 0..config.max_levels {
+        let mut membership: Vec<usize> = (0..level.len()).collect();
+        if !move_nodes_map_equation(&level, &mut membership, node_entropy, rng) {
+            break
+        }
+        let communities = renumber(&mut membership);
+        node_membership.iter_mut().for_each(|c| *c = membership[*c]);
+        level = level.aggregate(&membership, communities);
+    }
+    node_membership
+}
+
+fn move_nodes_map_equation(network: &Network, membership: &mut [usize], node_entropy: f64, rng: &mut StdRng) -> bool {
+    let n = network.len();
+    if network.total <= 0.0 {
+        return false
+    }
+
+    // Exit flow of each node is the weight of its edges to other nodes
+    let out: Vec<f64> = network.neighbors.iter().map(|row| row.iter().map(|(_, w)| w).sum::<f64>() / network.total).collect();
+    let visit: Vec<f64> = network.strength.iter().map(|s| s / network.total).collect();
+
+    let mut exit = vec![0.0; n];
+    let mut flow = vec![0.0; n];
+    for i in 0..n {
+        flow[membership[i]] += visit[i];
+        for &(j, w) in &network.neighbors[i] {
+            if membership[i] != membership[j] {
+                exit[membership[i]] += w / network.total;
+            }
+        }
+    }
+    let mut total_exit: f64 = exit.iter().sum();
+    let mut sum_plogp_exit: f64 = exit.iter().map(|q| plogp(*q)).sum();
+    let mut sum_plogp_module: f64 = exit.iter().zip(&flow).map(|(q, p)| plogp(q + p)).sum();
+
+    let current_codelength = |total_exit: f64, sum_exit: f64, sum_module: f64| {
+        plogp(total_exit) - 2.0 * sum_exit - node_entropy + sum_module
+    };
+
+    let mut order: Vec<usize> = (0..n).collect();
+    let mut improved = false;
+    let mut weights: HashMap<usize, f64> = HashMap::new();
+
+    loop {
+        order.shuffle(rng);
+        let mut moved = false;
+
+        for &i in &order {
+            let current = membership[i];
+
+            weights.clear();
+            for &(j, w) in &network.neighbors[i] {
+                *weights.entry(membership[j]).or_insert(0.0) += w / network.total;
+            }
+            let to_current = weights.get(&current).copied().unwrap_or(0.0);
+
+            // Remove node from its module
+            let exit_old = exit[current];
+            let exit_removed = exit_old - out[i] + 2.0 * to_current;
+            let flow_removed = flow[current] - visit[i];
+
+            let base_total = total_exit - exit_old + exit_removed;
+            let base_exit = sum_plogp_exit - plogp(exit_old) + plogp(exit_removed);
+            let base_module = sum_plogp_module - plogp(exit_old + flow[current]) + plogp(exit_removed + flow_removed);
+
+            let evaluate = |module_exit: f64, module_flow: f64, to_module: f64| {
+                let new_exit = module_exit + out[i] - 2.0 * to_module;
+                let total = base_total - module_exit + new_exit;
+                let sum_exit = base_exit - plogp(module_exit) + plogp(new_exit);
+                let sum_module = base_module - plogp(module_exit + module_flow) + plogp(new_exit + module_flow + visit[i]);
+                (current_codelength(total, sum_exit, sum_module), total, sum_exit, sum_module, new_exit)
+            };
+
+            let mut best = (current, evaluate(exit_removed, flow_removed, to_current));
+            let mut candidates: Vec<(usize, f64)> = weights.iter().map(|(c, w)| (*c, *w)).collect();
+            candidates.sort_by_key(|(c, _)| *c);
+
+            for (module, to_module) in candidates {
+                if module == current {
+                    continue
+                }
+                let result = evaluate(exit[module], flow[module], to_module);
+                if result.0 < best.1.0 - 1e-12 {
+                    best = (module, result);
+                }
+            }
+
+            let (module, (_, total, sum_exit, sum_module, new_exit)) = best;
+            if module != current {
+                exit[current] = exit_removed;
+                flow[current] = flow_removed;
+                exit[module] = new_exit;
+                flow[module] += visit[i];
+                membership[i] = module;
+                (total_exit, sum_plogp_exit, sum_plogp_module) = (total, sum_exit, sum_module);
+                moved = true;
+                improved = true;
+            }
+        }
+        if !moved {
+            break
+        }
+    }
+    improved
+}
+
+/// Settings for consensus clustering of repeated community detection runs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConsensusConfig {
+    pub seeds: Vec<u64>,        // Seeds of the community detection runs on each graph
+    pub threshold: f64,         // Minimum co-assignment of node pairs in the consensus network
+    pub min_stability: f64,     // Nodes with lower stability are flagged as unstable
+}
+
+impl Default for ConsensusConfig {
+    fn default() -> Self {
+        Self {
+            seeds: (0..10).collect(),
+            threshold: 0.5,
+            min_stability: 0.75
+        }
+    }
+}
+
+/// Consensus communities with per-node stability across partitions
+#[derive(Clone, Debug)]
+pub struct Consensus {
+    pub membership: Vec<usize>,                     // Consensus community of each node
+    pub stability: Vec<f64>,                        // Mean Jaccard similarity of the node's community in each partition with its consensus community
+    pub unstable: Vec<bool>,                        // Stability below the configured minimum
+    pub partitions: usize,                          // Number of partitions in the consensus
+    pub coassignment: Vec<(usize, usize, f64)>,     // Fraction of partitions with both nodes in the same community
+}
+
+impl Consensus {
+    /// Number of consensus communities.
+    pub fn count(&self) -> usize {
+        self.membership.iter().max().map_or(0, |c| c + 1)
+    }
+    /// Stores consensus community membership on the nodes of the graph.
+    pub fn apply(&self, graph: &mut NetviewGraph) -> Result<(), NetviewError> {
+        if graph.node_count() != self.membership.len() {
+            return Err(NetviewError::NodeLabelLengthError(graph.node_count()))
+        }
+        for (node, community) in graph.node_weights_mut().zip(&self.membership) {
+            node.community = Some(*community);
+        }
+        Ok(())
+    }
+    /// Writes the consensus table as TSV with columns `id`, `community`, `stability` and `unstable`.
+    pub fn write_membership(&self, graph: &NetviewGraph, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        writeln!(writer, "id\tcommunity\tstability\tunstable")?;
+        for (i, node) in graph.node_weights().enumerate() {
+            writeln!(writer, "{}\t{}\t{}\t{}", node_id_or_index(node), self.membership[i], self.stability[i], self.unstable[i])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    /// Writes the sparse co-assignment matrix as TSV with columns `source`, `target` and `coassignment`.
+    pub fn write_coassignment(&self, graph: &NetviewGraph, path: &Path) -> Result<(), NetviewError> {
+        let ids: Vec<String> = graph.node_weights().map(node_id_or_index).collect();
+        let mut writer = get_file_writer(path)?;
+        writeln!(writer, "source\ttarget\tcoassignment")?;
+        for (i, j, c) in &self.coassignment {
+            writeln!(writer, "{}\t{}\t{}", ids[*i], ids[*j], c)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Consensus communities across graphs of the same nodes (e.g. different k) and seeds.
+///
+/// Communities are detected on each graph with each seed. Co-assignment is the fraction
+/// of partitions in which two nodes share a community; it is computed for node pairs
+/// connected in any of the graphs, which keeps the matrix sparse. Pairs with co-assignment
+/// of at least `threshold` form a consensus network weighted by co-assignment, which is
+/// clustered with Leiden at the configured resolution.
+///
+/// # Errors
+///
+/// Returns `NetviewError::CommunityConfigError` if no graphs or seeds are given, or if
+/// the graphs do not have the same node identifiers in the same order.
+pub fn consensus_communities(
+    graphs: &[NetviewGraph],
+    config: &CommunityConfig,
+    consensus: &ConsensusConfig
+) -> Result<Consensus, NetviewError> {
+
+    if graphs.is_empty() || consensus.seeds.is_empty() {
+        return Err(NetviewError::CommunityConfigError("consensus requires at least one graph and seed".to_string()))
+    }
+    // Partitions are compared by node index, which requires the same node order in all graphs
+    let ids: Vec<String> = graphs[0].node_weights().map(node_id_or_index).collect();
+    for graph in &graphs[1..] {
+        if graph.node_count() != ids.len() || !graph.node_weights().map(node_id_or_index).eq(ids.iter().cloned()) {
+            return Err(NetviewError::CommunityConfigError("consensus graphs must have the same nodes in the same order".to_string()))
+        }
+    }
+
+    let mut partitions = Vec::new();
+    for graph in graphs {
+        for seed in &consensus.seeds {
+            let run = CommunityConfig { seed: *seed, ..config.clone() };
+            partitions.push(detect_communities(graph, &run)?.membership);
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = graphs.iter()
+        .flat_map(|graph| graph.edge_references().map(|e| {
+            let (i, j) = (e.source().index(), e.target().index());
+            (i.min(j), i.max(j))
+        }))
+        .filter(|(i, j)| i != j)
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    Ok(consensus_from_partitions(&partitions, &pairs, config.resolution, consensus, &mut rng))
+}
+
+fn consensus_from_partitions(
+    partitions: &[Vec<usize>],
+    pairs: &[(usize, usize)],
+    resolution: f64,
+    consensus: &ConsensusConfig,
+    rng: &mut StdRng
+) -> Consensus {
+
+    let n = partitions[0].len();
+    let runs = partitions.len() as f64;
+
+    let coassignment: Vec<(usize, usize, f64)> = pairs.iter()
+        .map(|&(i, j)| (i, j, partitions.iter().filter(|p| p[i] == p[j]).count() as f64 / runs))
+        .collect();
+
+    let mut neighbors = vec![Vec::new(); n];
+    for &(i, j, c) in &coassignment {
+        if c >= consensus.threshold && c > 0.0 {
+            neighbors[i].push((j, c));
+            neighbors[j].push((i, c));
+        }
+    }
+    let strength: Vec<f64> = neighbors.iter().map(|row| row.iter().map(|(_, w)| w).sum()).collect();
+    let total = strength.iter().sum();
+    let network = Network { neighbors, strength, total };
+
+    let config = CommunityConfig { resolution, ..Default::default() };
+    let membership = order_by_size(&leiden(&network, &config, rng));
+
+    // Jaccard similarity of each node's community in a partition with its consensus community
+    let consensus_sizes = community_sizes(&membership);
+    let mut stability = vec![0.0; n];
+    for partition in partitions {
+        let sizes = community_sizes(partition);
+        let mut overlap: HashMap<(usize, usize), usize> = HashMap::new();
+        for (a, k) in partition.iter().zip(&membership) {
+            *overlap.entry((*a, *k)).or_insert(0) += 1;
+        }
+        for i in 0..n {
+            let (a, k) = (partition[i], membership[i]);
+            let shared = overlap[&(a, k)];
+            stability[i] += shared as f64 / (sizes[a] + consensus_sizes[k] - shared) as f64;
+        }
+    }
+    stability.iter_mut().for_each(|s| *s /= runs);
+    let unstable = stability.iter().map(|s| *s < consensus.min_stability).collect();
+
+    Consensus { membership, stability, unstable, partitions: partitions.len(), coassignment }
+}
+
+fn community_sizes(membership: &[usize]) -> Vec<usize> {
+    let mut sizes = vec![0; membership.iter().max().map_or(0, |c| c + 1)];
+    membership.iter().for_each(|c| sizes[*c] += 1);
+    sizes
+}
+
+// Renumber communities to 0..k in order of first appearance, returns k
+fn renumber(membership: &mut [usize]) -> usize {
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    for c in membership.iter_mut() {
+        let next = ids.len();
+        *c = *ids.entry(*c).or_insert(next);
+    }
+    ids.len()
+}
+
+// Renumber communities by decreasing size, ties by first appearance
+pub(crate) fn order_by_size(membership: &[usize]) -> Vec<usize> {
+    let mut membership = membership.to_vec();
+    let communities = renumber(&mut membership);
+
+    let mut sizes = vec![0usize; communities];
+    membership.iter().for_each(|c| sizes[*c] += 1);
+
+    let mut order: Vec<usize> = (0..communities).collect();
+    order.sort_by_key(|c| std::cmp::Reverse(sizes[*c]));  // stable sort keeps first appearance on ties
+
+    let mut rank = vec![0; communities];
+    for (r, c) in order.into_iter().enumerate() {
+        rank[c] = r;
+    }
+    membership.iter().map(|c| rank[*c]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mknn::convert_to_graph;
+    use crate::netview::NodeLabel;
+
+    // Two dense groups of four nodes joined by a single edge (3 - 4) and a trailing pair
+    fn clustered_graph() -> NetviewGraph {
+        let mnn = vec![
+            vec![1, 2, 3], vec![0, 2, 3], vec![0, 1, 3], vec![0, 1, 2, 4],
+            vec![3, 5, 6, 7], vec![4, 6, 7], vec![4, 5, 7], vec![4, 5, 6],
+            vec![9], vec![8]
+        ];
+        let dist = vec![vec![10.0; 10]; 10];
+        convert_to_graph(&mnn, Some(&dist), None, None, None).unwrap()
+    }
+
+    #[test]
+    fn louvain_and_leiden_find_groups() {
+        let graph = clustered_graph();
+
+        for method in [CommunityMethod::Louvain, CommunityMethod::Leiden] {
+            let config = CommunityConfig { method: method.clone(), ..Default::default() };
+            let communities = detect_communities(&graph, &config).unwrap();
+
+            assert_eq!(communities.membership, vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2], "{method}");
+            assert_eq!(communities.count(), 3);
+            assert!((communities.modularity - 0.4923).abs() < 1e-3, "{}", communities.modularity);
+        }
+    }
+
+    #[test]
+    fn communities_are_stored_on_nodes() {
+        let mut graph = clustered_graph();
+        let communities = detect_communities(&graph, &CommunityConfig::default()).unwrap();
+        communities.apply(&mut graph).unwrap();
+
+        assert_eq!(graph.node_weights().map(|n| n.community.unwrap()).collect::<Vec<_>>(), communities.membership);
+
+        let config = CommunityConfig { resolution: -1.0, ..Default::default() };
+        assert!(matches!(detect_communities(&graph, &config), Err(NetviewError::CommunityConfigError(_))));
+    }
+
+    #[test]
+    fn walktrap_and_infomap_find_groups() {
+        let graph = clustered_graph();
+
+        for method in [CommunityMethod::Walktrap, CommunityMethod::Infomap] {
+            let config = CommunityConfig { method: method.clone(), ..Default::default() };
+            let communities = detect_communities(&graph, &config).unwrap();
+            assert_eq!(communities.membership, vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2], "{method}");
+        }
+
+        let config = CommunityConfig { method: CommunityMethod::Infomap, ..Default::default() };
+        let communities = detect_communities(&graph, &config).unwrap();
+        let network = Network::from_graph(&graph, true);
+        let single = map_equation(&network, &[0; 10]);
+        assert!(communities.codelength.unwrap() < single);
+    }
+
+    #[test]
+    fn walktrap_dendrogram_cuts() {
+        let graph = clustered_graph();
+        let config = CommunityConfig { method: CommunityMethod::Walktrap, communities: Some(2), ..Default::default() };
+        let communities = detect_communities(&graph, &config).unwrap();
+        assert_eq!(communities.membership, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+
+        // Two components: eight merges and the last one joins the groups
+        let dendrogram = communities.dendrogram.unwrap();
+        assert_eq!(dendrogram.merges.len(), 8);
+        assert_eq!(dendrogram.merges.last().unwrap().size, 8);
+        assert!(dendrogram.merges.windows(2).all(|m| m[0].height <= m[1].height));
+        assert_eq!(dendrogram.cut(10).unwrap(), (0..10).collect::<Vec<_>>());
+        assert!(matches!(dendrogram.cut(1), Err(NetviewError::CommunityConfigError(_))));
+
+        let best = dendrogram.best_step();
+        let network = Network::from_graph(&graph, true);
+        let expected = modularity(&network, &dendrogram.cut_at_step(best), 1.0);
+        assert!((dendrogram.merges[best - 1].modularity - expected).abs() < 1e-9);
+
+        let tips: Vec<NodeLabel> = graph.node_weights().cloned().collect();
+        let newick = dendrogram.to_tree().to_newick(&tips, false).unwrap();
+        assert_eq!(newick.matches('(').count(), 9);  // eight merges and a root joining the components
+    }
+
+    #[test]
+    fn consensus_of_partitions() {
+        // Node 2 switches between the first and second community
+        let partitions = vec![
+            vec![0, 0, 0, 1, 1, 1],
+            vec![0, 0, 1, 1, 1, 1],
+            vec![0, 0, 0, 1, 1, 1],
+            vec![0, 0, 1, 1, 1, 1],
+            vec![0, 0, 0, 1, 1, 1],
+        ];
+        let pairs = vec![(0, 1), (0, 2), (1, 2), (2, 3), (3, 4), (3, 5), (4, 5)];
+        let mut rng = StdRng::seed_from_u64(42);
+        let consensus = consensus_from_partitions(&partitions, &pairs, 1.0, &ConsensusConfig::default(), &mut rng);
+
+        assert_eq!(consensus.membership, vec![0, 0, 0, 1, 1, 1]);
+        assert_eq!(consensus.coassignment[1], (0, 2, 0.6));
+        assert_eq!(consensus.coassignment[3], (2, 3, 0.4));
+        assert!((consensus.stability[0] - (0.6 + 0.4 * 2.0 / 3.0)).abs() < 1e-9);
+        assert!((consensus.stability[2] - (0.6 + 0.4 / 6.0)).abs() < 1e-9);
+        assert_eq!(consensus.unstable, vec![false, false, true, false, false, false]);
+    }
+
+    #[test]
+    fn consensus_across_graphs() {
+        let graphs = vec![clustered_graph(), clustered_graph()];
+        let config = CommunityConfig { method: CommunityMethod::Louvain, ..Default::default() };
+        let consensus = consensus_communities(&graphs, &config, &ConsensusConfig::default()).unwrap();
+
+        assert_eq!(consensus.partitions, 20);
+        assert_eq!(consensus.membership, vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2]);
+        assert!(consensus.stability.iter().all(|s| (s - 1.0).abs() < 1e-9));
+
+        let config = ConsensusConfig { seeds: vec![], ..Default::default() };
+        assert!(matches!(consensus_communities(&graphs, &CommunityConfig::default(), &config), Err(NetviewError::CommunityConfigError(_))));
+
+        // Graphs of the same size with different node identifiers are not comparable
+        let mut renamed = clustered_graph();
+        if let Some(node) = renamed.node_weight_mut(petgraph::graph::NodeIndex::new(0)) {
+            node.id = Some("other".to_string());
+        }
+        let graphs = vec![clustered_graph(), renamed];
+        assert!(matches!(consensus_communities(&graphs, &CommunityConfig::default(), &ConsensusConfig::default()), Err(NetviewError::CommunityConfigError(_))));
+    }
+
+    #[test]
+    fn resolution_zero_merges_components() {
+        // At zero resolution each connected component is a single community
+        let graph = clustered_graph();
+        let config = CommunityConfig { method: CommunityMethod::Louvain, resolution: 0.0, ..Default::default() };
+        let communities = detect_communities(&graph, &config).unwrap();
+        assert_eq!(communities.membership, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+    }
+}
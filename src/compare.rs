@@ -0,0 +1,306 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::error::NetviewError;
+use crate::mknn::{node_id_or_index, GraphJson};
+use crate::utils::{get_file_reader, get_file_writer, has_extension};
+
+/// Partition of nodes into classes keyed by node identifier, nodes
+/// without a class (e.g. unlabelled nodes) have `None`
+pub type Partition = HashMap<String, Option<String>>;
+
+/// Reads a partition from a graph (.json) or a delimited table with header.
+///
+/// For graphs, `column` selects the node field `label` (default) or `community`.
+/// For tables, `column` selects the class column by name (default: second column)
+/// and the `id` column is used for node identifiers (default: first column).
+/// Tables ending in `.csv` (optionally compressed) are comma-separated, otherwise
+/// tab-separated. Empty values are treated as missing.
+pub fn read_partition(path: &Path, column: Option<&str>) -> Result<Partition, NetviewError> {
+
+    if has_extension(path, &[".json"]) {
+        let graph = GraphJson::read(path)?;
+        return graph.nodes.iter()
+            .map(|node| {
+                let class = match column.unwrap_or("label") {
+                    "label" => node.label.clone(),
+                    "community" => node.community.map(|c| c.to_string()),
+                    other => return Err(NetviewError::PartitionColumnError(other.to_string()))
+                };
+                Ok((node_id_or_index(node), class))
+            })
+            .collect()
+    }
+
+    let reader = get_file_reader(path)?;
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(if has_extension(path, &[".csv"]) { b',' } else { b'\t' })
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let headers = rdr.headers()?.clone();
+    let id_index = headers.iter().position(|h| h == "id").unwrap_or(0);
+    let class_index = match column {
+        Some(column) => headers.iter().position(|h| h == column)
+            .ok_or_else(|| NetviewError::PartitionColumnError(column.to_string()))?,
+        None => match id_index {
+            0 => 1,
+            _ => 0
+        }
+    };
+    if class_index >= headers.len() {
+        return Err(NetviewError::PartitionColumnError(format!("column {} of {}", class_index + 1, path.display())))
+    }
+
+    let mut partition = Partition::new();
+    for record in rdr.records() {
+        let record = record?;
+        let id = record.get(id_index).unwrap_or_default().to_string();
+        let class = record.get(class_index).filter(|c| !c.is_empty()).map(String::from);
+        partition.insert(id, class);
+    }
+    Ok(partition)
+}
+
+/// Contingency table of reference classes (rows) and predicted classes (columns)
+#[derive(Serialize, Clone, Debug)]
+pub struct ContingencyTable {
+    pub reference: Vec<String>,
+    pub predicted: Vec<String>,
+    pub counts: Vec<Vec<usize>>,
+}
+
+impl ContingencyTable {
+    fn row_sums(&self) -> Vec<usize> {
+        self.counts.iter().map(|row| row.iter().sum()).collect()
+    }
+    fn column_sums(&self) -> Vec<usize> {
+        (0..self.predicted.len()).map(|j| self.counts.iter().map(|row| row[j]).sum()).collect()
+    }
+    /// Writes the table as TSV with reference classes as rows and predicted classes as columns.
+    pub fn write(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        writeln!(writer, "reference\t{}", self.predicted.join("\t"))?;
+        for (class, row) in self.reference.iter().zip(&self.counts) {
+            let counts: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+            writeln!(writer, "{class}\t{}", counts.join("\t"))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Agreement of a predicted partition with a reference partition
+#[derive(Serialize, Clone, Debug)]
+pub struct Comparison {
+    pub nodes: usize,                   // Nodes with a class in both partitions
+    pub missing_reference: usize,       // Nodes without a reference class or absent from the reference
+    pub missing_predicted: usize,       // Nodes without a predicted class or absent from the prediction
+    pub adjusted_rand_index: f64,
+    pub normalized_mutual_information: f64,  // Arithmetic mean normalization
+    pub purity: f64,                    // Fraction of nodes in the majority reference class of their predicted class
+    pub homogeneity: f64,
+    pub completeness: f64,
+    pub v_measure: f64,
+    pub contingency: ContingencyTable,
+}
+
+impl Comparison {
+    /// Writes the metrics as JSON (`.json`) or as TSV with columns `metric` and `value`.
+    pub fn write(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        if has_extension(path, &[".json"]) {
+            serde_json::to_writer_pretty(&mut writer, self)?;
+            writeln!(writer)?;
+        } else {
+            writeln!(writer, "metric\tvalue")?;
+            writeln!(writer, "nodes\t{}", self.nodes)?;
+            writeln!(writer, "missing_reference\t{}", self.missing_reference)?;
+            writeln!(writer, "missing_predicted\t{}", self.missing_predicted)?;
+            writeln!(writer, "adjusted_rand_index\t{}", self.adjusted_rand_index)?;
+            writeln!(writer, "normalized_mutual_information\t{}", self.normalized_mutual_information)?;
+            writeln!(writer, "purity\t{}", self.purity)?;
+            writeln!(writer, "homogeneity\t{}", self.homogeneity)?;
+            writeln!(writer, "completeness\t{}", self.completeness)?;
+            writeln!(writer, "v_measure\t{}", self.v_measure)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Compares a predicted partition with a reference partition over shared node identifiers.
+///
+/// Only nodes with a class in both partitions are compared, the number of nodes missing
+/// from either partition is reported. Classes are matched by node identifier, class names
+/// of the two partitions do not need to correspond (e.g. labels against community indices).
+///
+/// # Errors
+///
+/// Returns `NetviewError::PartitionComparisonError` if no node has a class in both partitions.
+pub fn compare_partitions(reference: &Partition, predicted: &Partition) -> Result<Comparison, NetviewError> {
+
+    let mut pairs: Vec<(&String, &String)> = Vec::new();
+    let mut missing_reference = 0;
+    let mut missing_predicted = 0;
+
+    let ids: BTreeSet<&String> = reference.keys().chain(predicted.keys()).collect();
+    for id in ids {
+        match (reference.get(id).and_then(|c| c.as_ref()), predicted.get(id).and_then(|c| c.as_ref())) {
+            (Some(r), Some(p)) => pairs.push((r, p)),
+            (None, Some(_)) => missing_reference += 1,
+            (Some(_), None) => missing_predicted += 1,
+            (None, None) => {
+                missing_reference += 1;
+                missing_predicted += 1;
+            }
+        }
+    }
+    if pairs.is_empty() {
+        return Err(NetviewError::PartitionComparisonError("no node has a class in both partitions".to_string()))
+    }
+
+    let contingency = contingency_table(&pairs);
+    let n = pairs.len() as f64;
+    let rows = contingency.row_sums();
+    let columns = contingency.column_sums();
+
+    // Adjusted Rand index (Hubert & Arabie, 1985)
+    let choose2 = |x: usize| (x * x.saturating_sub(1)) as f64 / 2.0;
+    let index: f64 = contingency.counts.iter().flatten().map(|c| choose2(*c)).sum();
+    let sum_rows: f64 = rows.iter().map(|c| choose2(*c)).sum();
+    let sum_columns: f64 = columns.iter().map(|c| choose2(*c)).sum();
+    let expected = if pairs.len() > 1 { sum_rows * sum_columns / choose2(pairs.len()) } else { 0.0 };
+    let maximum = 0.5 * (sum_rows + sum_columns);
+    let adjusted_rand_index = if (maximum - expected).abs() < f64::EPSILON {
+        1.0  // both partitions are trivial (all singletons or a single class)
+    } else {
+        (index - expected) / (maximum - expected)
+    };
+
+    // Entropies and mutual information in nats
+    let entropy = |sums: &[usize]| -> f64 {
+        sums.iter().filter(|c| **c > 0).map(|c| { let p = *c as f64 / n; -p * p.ln() }).sum()
+    };
+    let h_reference = entropy(&rows);
+    let h_predicted = entropy(&columns);
+    let mutual_information: f64 = contingency.counts.iter().enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, c)| (i, j, *c)))
+        .filter(|(_, _, c)| *c > 0)
+        .map(|(i, j, c)| {
+            let p = c as f64 / n;
+            p * (c as f64 * n / (rows[i] as f64 * columns[j] as f64)).ln()
+        })
+        .sum();
+
+    let normalized_mutual_information = match h_reference + h_predicted {
+        h if h > 0.0 => (2.0 * mutual_information / h).min(1.0),
+        _ => 1.0
+    };
+
+    let purity = (0..contingency.predicted.len())
+        .map(|j| contingency.counts.iter().map(|row| row[j]).max().unwrap_or(0))
+        .sum::<usize>() as f64 / n;
+
+    // V-measure (Rosenberg & Hirschberg, 2007): H(C|K) = H(C) - I(C, K)
+    let homogeneity = if h_reference > 0.0 { (mutual_information / h_reference).min(1.0) } else { 1.0 };
+    let completeness = if h_predicted > 0.0 { (mutual_information / h_predicted).min(1.0) } else { 1.0 };
+    let v_measure = if homogeneity + completeness > 0.0 {
+        2.0 * homogeneity * completeness / (homogeneity + completeness)
+    } else {
+        0.0
+    };
+
+    Ok(Comparison {
+        nodes: pairs.len(),
+        missing_reference,
+        missing_predicted,
+        adjusted_rand_index,
+        normalized_mutual_information,
+        purity,
+        homogeneity,
+        completeness,
+        v_measure,
+        contingency
+    })
+}
+
+// Contingency table with classes in sorted order
+fn contingency_table(pairs: &[(&String, &String)]) -> ContingencyTable {
+    let reference: Vec<String> = pairs.iter().map(|(r, _)| (*r).clone()).collect::<BTreeSet<_>>().into_iter().collect();
+    let predicted: Vec<String> = pairs.iter().map(|(_, p)| (*p).clone()).collect::<BTreeSet<_>>().into_iter().collect();
+
+    let row: HashMap<&String, usize> = reference.iter().enumerate().map(|(i, c)| (c, i)).collect();
+    let column: HashMap<&String, usize> = predicted.iter().enumerate().map(|(j, c)| (c, j)).collect();
+
+    let mut counts = vec![vec![0; predicted.len()]; reference.len()];
+    for (r, p) in pairs {
+        counts[row[r]][column[p]] += 1;
+    }
+    ContingencyTable { reference, predicted, counts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partition(classes: &[Option<&str>]) -> Partition {
+        classes.iter().enumerate().map(|(i, c)| (format!("n{i}"), c.map(String::from))).collect()
+    }
+
+    #[test]
+    fn identical_partitions_with_renamed_classes() {
+        let reference = partition(&[Some("A"), Some("A"), Some("B"), Some("B"), Some("C")]);
+        let predicted = partition(&[Some("0"), Some("0"), Some("1"), Some("1"), Some("2")]);
+        let comparison = compare_partitions(&reference, &predicted).unwrap();
+
+        assert_eq!(comparison.nodes, 5);
+        for metric in [comparison.adjusted_rand_index, comparison.normalized_mutual_information, comparison.purity, comparison.v_measure] {
+            assert!((metric - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn known_metric_values() {
+        // Contingency table [[2, 1, 0], [0, 1, 2]], values as in scikit-learn (arithmetic NMI)
+        let reference = partition(&[Some("a"), Some("a"), Some("a"), Some("b"), Some("b"), Some("b"), None]);
+        let predicted = partition(&[Some("x"), Some("x"), Some("y"), Some("y"), Some("z"), Some("z"), Some("z")]);
+        let comparison = compare_partitions(&reference, &predicted).unwrap();
+
+        assert_eq!((comparison.nodes, comparison.missing_reference, comparison.missing_predicted), (6, 1, 0));
+        assert!((comparison.adjusted_rand_index - 0.24242424242424246).abs() < 1e-12);
+        assert!((comparison.normalized_mutual_information - 0.5158037429793889).abs() < 1e-12);
+        assert!((comparison.homogeneity - 0.6666666666666669).abs() < 1e-12);
+        assert!((comparison.completeness - 0.420619835714305).abs() < 1e-12);
+        assert!((comparison.v_measure - 0.5158037429793889).abs() < 1e-12);
+        assert!((comparison.purity - 5.0 / 6.0).abs() < 1e-12);
+
+        assert_eq!(comparison.contingency.reference, vec!["a", "b"]);
+        assert_eq!(comparison.contingency.counts, vec![vec![2, 1, 0], vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn partitions_from_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("labels.csv");
+        std::fs::write(&path, "label,id\nA,n0\n,n1\n").unwrap();
+
+        let partition = read_partition(&path, None).unwrap();
+        assert_eq!(partition["n0"], Some("A".to_string()));
+        assert_eq!(partition["n1"], None);
+
+        assert!(matches!(read_partition(&path, Some("missing")), Err(NetviewError::PartitionColumnError(_))));
+        assert!(matches!(compare_partitions(&partition, &Partition::new()), Err(NetviewError::PartitionComparisonError(_))));
+
+        // Output format from the file extension of the comparison
+        let comparison = compare_partitions(&partition, &partition).unwrap();
+        for (name, json) in [("comparison.json", true), ("comparison.jsonish.tsv", false)] {
+            let output = dir.path().join(name);
+            comparison.write(&output).unwrap();
+            assert_eq!(std::fs::read_to_string(&output).unwrap().starts_with('{'), json, "{name}");
+        }
+    }
+}
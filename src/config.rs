@@ -4,10 +4,10 @@ use std::{
     path::PathBuf,
 };
 
-use crate::{centrality::NodeCentrality, error::NetviewError, label::VoteWeights};
+use crate::{centrality::NodeCentrality, error::NetviewError, label::{AbstentionConfig, HierarchyConfig, PropagationUpdate, SpreadingConfig, VoteWeights}};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetviewConfig {
     pub skani: SkaniConfig,
     pub graph: GraphConfig,
@@ -64,7 +64,7 @@ impl NetviewConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkaniConfig {
     pub marker_compression_factor: usize,
     pub compression_factor: usize,
@@ -87,7 +87,7 @@ impl Default for SkaniConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GraphConfig {
     pub k: usize,
 }
@@ -103,13 +103,27 @@ impl GraphConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LabelConfig {
     pub centrality_metric: NodeCentrality,
     pub max_iterations: usize,
     pub vote_weights: VoteWeights,
     pub neighbor_centrality_vote: bool,
     pub distance_percent: bool,
+    #[serde(default)]
+    pub update: PropagationUpdate,
+    #[serde(default = "default_seed")]
+    pub seed: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spreading: Option<SpreadingConfig>,  // Label spreading instead of label propagation by votes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hierarchy: Option<HierarchyConfig>,  // Hierarchical labels (e.g. taxonomic lineages) assigned rank by rank
+    #[serde(default)]
+    pub abstention: AbstentionConfig,        // Novel label for queries below the abstention thresholds
+}
+
+fn default_seed() -> u64 {
+    42
 }
 
 impl Default for LabelConfig {
@@ -120,6 +134,11 @@ impl Default for LabelConfig {
             vote_weights: VoteWeights::default(),
             neighbor_centrality_vote: false,
             distance_percent: true,
+            update: PropagationUpdate::Synchronous,
+            seed: default_seed(),
+            spreading: None,
+            hierarchy: None,
+            abstention: AbstentionConfig::default(),
         }
     }
 }
@@ -4,21 +4,23 @@ use std::io::BufWriter;
 use std::path::PathBuf;
 use needletail::parse_fastx_file;
 use crate::error::NetviewError;
-use crate::label::{read_labels_from_file, write_labels_to_file, Label};
+use crate::label::{read_label_table, Label, LabelColumns};
 use crate::utils::write_fasta;
 
 pub struct Dereplicator<'a> {
     fasta_path: &'a PathBuf,
     label_path: &'a PathBuf,
+    label_columns: &'a LabelColumns,
     max_per_label: usize,
 }
 
 impl<'a> Dereplicator<'a> {
     // Constructor
-    pub fn new(fasta_path: &'a PathBuf, label_path: &'a PathBuf, max_per_label: usize) -> Self {
+    pub fn new(fasta_path: &'a PathBuf, label_path: &'a PathBuf, label_columns: &'a LabelColumns, max_per_label: usize) -> Self {
         Dereplicator {
             fasta_path,
             label_path,
+            label_columns,
             max_per_label,
         }
     }
@@ -28,22 +30,23 @@ impl<'a> Dereplicator<'a> {
         // Load sequences from FASTA
         let sequences = self.load_fasta_sequences(self.fasta_path, min_length)?;
 
-        // Load labels from CSV/TSV
-        let labels = read_labels_from_file(self.label_path, false)?; // Assuming CSV here, adjust as needed
+        // Load labels from CSV/TSV, multiple label columns are dereplicated by their combination
+        let table = read_label_table(self.label_path, self.label_columns)?;
+        let labels = table.strata();
 
         // Group sequences by label and dereplicate
         let selected_sequences = self.group_and_select_sequences(&sequences, &labels, exclude);
 
         // Write the dereplicated sequences to the output FASTA file
         let mut fasta_writer = BufWriter::new(File::create(output_fasta)?);
-        let mut fasta_labels = Vec::new();
+        let mut fasta_ids = Vec::new();
 
         for (label, seq) in selected_sequences {
             write_fasta(label.id.as_bytes(), &seq, &mut fasta_writer, needletail::parser::LineEnding::Unix)?;
-            fasta_labels.push(label)
+            fasta_ids.push(label.id)
         }
 
-        write_labels_to_file(&fasta_labels, output_labels, false)?;
+        table.write(output_labels, Some(&fasta_ids), false)?;
 
         Ok(())
     }
@@ -4,12 +4,11 @@ use csv::{ReaderBuilder, Trim};
 use itertools::Itertools;
 use needletail::parse_fastx_file;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
-use std::io::BufWriter;
 use std::io::Write;
 use std::path::Path;
 use std::process::Command;
@@ -18,6 +17,8 @@ use rayon::prelude::*;
 use rayon::ThreadPoolBuilder;
 
 use crate::error::NetviewError;
+use crate::netview::NodeLabel;
+use crate::utils::{get_file_reader, get_file_writer};
 
 pub fn extract_fasta_ids(fasta_path: &Path) -> Result<Vec<String>, NetviewError> {
     // Open the FASTA file using needletail
@@ -43,7 +44,7 @@ pub fn extract_fasta_ids(fasta_path: &Path) -> Result<Vec<String>, NetviewError>
 }
 
 pub fn parse_identifiers(id_path: &Path) -> Result<Vec<String>, NetviewError> {
-    let reader = BufReader::new(File::open(id_path)?);
+    let reader = BufReader::new(get_file_reader(id_path)?);
 
     // Create a vector to store identifiers
     let mut ids: Vec<String> = Vec::new();
@@ -57,12 +58,13 @@ pub fn parse_identifiers(id_path: &Path) -> Result<Vec<String>, NetviewError> {
 }
 
 pub fn write_ids(ids: &Vec<String>, file: &Path) -> Result<(), NetviewError> {
-    let mut writer = BufWriter::new(File::create(file)?);
+    let mut writer = get_file_writer(file)?;
 
     for id in ids {
         writeln!(writer, "{}", &id)?
     }
 
+    writer.flush()?;
     Ok(())
 }
 
@@ -222,7 +224,8 @@ fn find_missing_ids(ids1: Vec<String>, ids2: Vec<String>) -> Vec<String> {
 ///   value to the file.
 /// * `file_path` - A string slice that holds the path to the file where the matrix
 ///   should be written. The function will create the file if it does not exist, or
-///   overwrite the file if it already exists.
+///   overwrite the file if it already exists. Output is compressed by file extension
+///   (`.gz`, `.bz2`, `.xz`) and written to stdout if the path is `-`.
 ///
 /// # Errors
 ///
@@ -243,8 +246,8 @@ fn find_missing_ids(ids1: Vec<String>, ids2: Vec<String>) -> Vec<String> {
 /// }
 /// ```
 pub fn write_matrix_to_file(matrix: &Vec<Vec<f64>>, file_path: &Path) -> Result<(), NetviewError> {
-    // Open the file for writing (or create it if it doesn't exist)
-    let mut file = File::create(file_path)?;
+    // Open the file for writing (or create it if it doesn't exist), compressed by extension
+    let mut file = get_file_writer(file_path)?;
 
     // Iterate through the rows of the matrix
     for row in matrix {
@@ -259,6 +262,7 @@ pub fn write_matrix_to_file(matrix: &Vec<Vec<f64>>, file_path: &Path) -> Result<
         writeln!(file, "{}", row_str)?
     }
 
+    file.flush()?;
     Ok(())
 }
 
@@ -269,7 +273,7 @@ struct MatrixRow(Vec<f64>);
 /// Parses a distance matrix from a CSV/TSV file.
 ///
 /// The function can handle both symmetrical and lower triangular matrices.
-/// It automatically detects whether the file is CSV or TSV based on the extension.
+/// Compressed files are decompressed transparently and `-` reads from stdin.
 ///
 /// # Arguments
 ///
@@ -299,7 +303,7 @@ pub fn parse_input_matrix<P: AsRef<Path>>(
     file_path: P,
     is_csv: bool,
 ) -> Result<Vec<Vec<f64>>, NetviewError> {
-    let file = File::open(file_path.as_ref()).map_err(|_| NetviewError::FileReadError)?;
+    let file = get_file_reader(file_path.as_ref()).map_err(|_| NetviewError::FileReadError)?;
 
     let mut rdr = ReaderBuilder::new()
         .delimiter(if is_csv { b',' } else { b'\t' })
@@ -522,12 +526,342 @@ pub fn euclidean_distance_of_distances(
     Ok(result_matrix)
 }
 
+/// Tree construction methods for distance matrices
+#[derive(Serialize, Deserialize, Clone, Debug, clap::ValueEnum)]
+pub enum TreeMethod {
+    Nj,     // Neighbor-joining, unrooted tree with a basal trifurcation
+    Upgma,  // Average linkage, rooted ultrametric tree
+}
+impl std::fmt::Display for TreeMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            TreeMethod::Nj => "nj",
+            TreeMethod::Upgma => "upgma",
+        };
+        write!(f, "{}", output)
+    }
+}
+
+/// Node of a tree, tips carry the row index of the distance matrix
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeNode {
+    pub tip: Option<usize>,
+    pub children: Vec<(usize, f64)>,  // Child node and branch length
+}
+
+/// Tree constructed from a distance matrix with nodes stored in an arena
+#[derive(Clone, Debug)]
+pub struct Tree {
+    pub nodes: Vec<TreeNode>,
+    pub root: usize,
+}
+
+impl Tree {
+    fn with_tips(n: usize) -> Self {
+        Self {
+            nodes: (0..n).map(|i| TreeNode { tip: Some(i), children: Vec::new() }).collect(),
+            root: 0
+        }
+    }
+    fn join(&mut self, children: Vec<(usize, f64)>) -> usize {
+        self.nodes.push(TreeNode { tip: None, children });
+        self.nodes.len() - 1
+    }
+    /// Number of tips in the tree.
+    pub fn tip_count(&self) -> usize {
+        self.nodes.iter().filter(|node| node.tip.is_some()).count()
+    }
+    /// Formats the tree in Newick format.
+    ///
+    /// Tips are named by `NodeLabel.id` (or the node index if no identifier is set)
+    /// in the same order as the rows of the distance matrix. If `annotate_labels` is
+    /// set, tips with a label carry an extended Newick comment as read by FigTree
+    /// and `treeio`, e.g. `GCF_1:0.1[&label="ST93",label_confidence=0.9]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetviewError::NodeLabelLengthError` if the number of nodes does not
+    /// match the number of tips.
+    pub fn to_newick(&self, tips: &[NodeLabel], annotate_labels: bool) -> Result<String, NetviewError> {
+
+        let n = self.tip_count();
+        if tips.len() != n {
+            return Err(NetviewError::NodeLabelLengthError(n))
+        }
+
+        // Iterative traversal, trees from large matrices can be deep (e.g. UPGMA caterpillars)
+        let mut newick = String::new();
+        let mut stack = vec![(self.root, None, 0)];
+
+        while let Some((node, length, child)) = stack.pop() {
+            let tree_node = &self.nodes[node];
+            if let Some(tip) = tree_node.tip {
+                let label = &tips[tip];
+                newick.push_str(&newick_quote(&label.id.clone().unwrap_or(label.index.to_string())));
+                push_branch_length(&mut newick, length);
+                if annotate_labels {
+                    if let Some(l) = &label.label {
+                        newick.push_str(&format!(
+                            "[&label=\"{}\",label_confidence={}]", l.replace('"', "'"), label.label_confidence
+                        ));
+                    }
+                }
+            } else if child < tree_node.children.len() {
+                newick.push(if child == 0 { '(' } else { ',' });
+                stack.push((node, length, child + 1));
+                let (next, next_length) = tree_node.children[child];
+                stack.push((next, Some(next_length), 0));
+            } else {
+                newick.push(')');
+                push_branch_length(&mut newick, length);
+            }
+        }
+        newick.push(';');
+
+        Ok(newick)
+    }
+}
+
+fn push_branch_length(newick: &mut String, length: Option<f64>) {
+    if let Some(length) = length {
+        newick.push_str(&format!(":{length}"));
+    }
+}
+
+// Quote Newick names with reserved characters, single quotes are escaped by doubling
+fn newick_quote(name: &str) -> String {
+    if name.chars().any(|c| c.is_whitespace() || "()[]':;,".contains(c)) {
+        format!("'{}'", name.replace('\'', "''"))
+    } else {
+        name.to_string()
+    }
+}
+
+/// Builds a tree from a symmetrical or lower triangular distance matrix.
+///
+/// # Example
+///
+/// ```
+/// use netview::dist::{build_tree, TreeMethod};
+///
+/// let matrix = vec![vec![0.0], vec![2.0, 0.0], vec![4.0, 4.0, 0.0]];
+/// let tree = build_tree(&matrix, &TreeMethod::Upgma).unwrap();
+///
+/// assert_eq!(tree.tip_count(), 3);
+/// ```
+pub fn build_tree(distance_matrix: &Vec<Vec<f64>>, method: &TreeMethod) -> Result<Tree, NetviewError> {
+    let matrix = make_symmetrical(distance_matrix)?;
+    if matrix.iter().flatten().any(|d| !d.is_finite()) {
+        return Err(NetviewError::InvalidMatrix)
+    }
+    match method {
+        TreeMethod::Nj => neighbor_joining(&matrix),
+        TreeMethod::Upgma => upgma(&matrix),
+    }
+}
+
+/// Neighbor-joining tree (Saitou & Nei, 1987) from a symmetrical distance matrix.
+///
+/// Runs in O(n³) time. Negative branch lengths are set to zero. Ties are broken
+/// by the first pair in matrix order so that trees are reproducible.
+pub fn neighbor_joining(matrix: &[Vec<f64>]) -> Result<Tree, NetviewError> {
+
+    let n = matrix.len();
+    if n == 0 {
+        return Err(NetviewError::EmptyMatrix)
+    }
+    if matrix.iter().any(|row| row.len() != n) {
+        return Err(NetviewError::NonSquareMatrix)
+    }
+
+    let mut tree = Tree::with_tips(n);
+    if n == 1 {
+        return Ok(tree)
+    }
+
+    let mut active: Vec<usize> = (0..n).collect();
+    let mut d = matrix.to_vec();
+
+    while active.len() > 3 {
+        let m = active.len();
+        let r: Vec<f64> = d.iter().map(|row| row.iter().sum()).collect();
+
+        let (mut min_i, mut min_j, mut min_q) = (0, 1, f64::INFINITY);
+        for (i, row) in d.iter().enumerate() {
+            for (j, dij) in row.iter().enumerate().skip(i + 1) {
+                let q = (m as f64 - 2.0) * dij - r[i] - r[j];
+                if q < min_q {
+                    (min_i, min_j, min_q) = (i, j, q);
+                }
+            }
+        }
+
+        let dij = d[min_i][min_j];
+        let li = (0.5 * dij + (r[min_i] - r[min_j]) / (2.0 * (m as f64 - 2.0))).max(0.0);
+        let lj = (dij - li).max(0.0);
+        let node = tree.join(vec![(active[min_i], li), (active[min_j], lj)]);
+
+        // Replace row i with the new node and remove row j
+        let merged: Vec<f64> = (0..m).map(|k| 0.5 * (d[min_i][k] + d[min_j][k] - dij)).collect();
+        for (k, dk) in merged.into_iter().enumerate() {
+            d[min_i][k] = dk;
+            d[k][min_i] = dk;
+        }
+        d[min_i][min_i] = 0.0;
+        active[min_i] = node;
+
+        d.remove(min_j);
+        for row in d.iter_mut() {
+            row.remove(min_j);
+        }
+        active.remove(min_j);
+    }
+
+    tree.root = match active.len() {
+        2 => {
+            let half = (0.5 * d[0][1]).max(0.0);
+            tree.join(vec![(active[0], half), (active[1], half)])
+        },
+        _ => {
+            let la = (0.5 * (d[0][1] + d[0][2] - d[1][2])).max(0.0);
+            let lb = (0.5 * (d[0][1] + d[1][2] - d[0][2])).max(0.0);
+            let lc = (0.5 * (d[0][2] + d[1][2] - d[0][1])).max(0.0);
+            tree.join(vec![(active[0], la), (active[1], lb), (active[2], lc)])
+        }
+    };
+
+    Ok(tree)
+}
+
+/// UPGMA tree (average linkage) from a symmetrical distance matrix.
+///
+/// Runs in O(n³) time. Node heights are half the average distance between the
+/// joined clusters, branch lengths are the difference in height to the children.
+pub fn upgma(matrix: &[Vec<f64>]) -> Result<Tree, NetviewError> {
+
+    let n = matrix.len();
+    if n == 0 {
+        return Err(NetviewError::EmptyMatrix)
+    }
+    if matrix.iter().any(|row| row.len() != n) {
+        return Err(NetviewError::NonSquareMatrix)
+    }
+
+    let mut tree = Tree::with_tips(n);
+    let mut active: Vec<usize> = (0..n).collect();
+    let mut sizes = vec![1.0; n];
+    let mut heights = vec![0.0; n];
+    let mut d = matrix.to_vec();
+
+    while active.len() > 1 {
+        let m = active.len();
+
+        let (mut min_i, mut min_j, mut min_d) = (0, 1, f64::INFINITY);
+        for (i, row) in d.iter().enumerate() {
+            for (j, &dij) in row.iter().enumerate().skip(i + 1) {
+                if dij < min_d {
+                    (min_i, min_j, min_d) = (i, j, dij);
+                }
+            }
+        }
+
+        let height = 0.5 * min_d;
+        let node = tree.join(vec![
+            (active[min_i], (height - heights[min_i]).max(0.0)),
+            (active[min_j], (height - heights[min_j]).max(0.0))
+        ]);
+
+        let (si, sj) = (sizes[min_i], sizes[min_j]);
+        let merged: Vec<f64> = (0..m).map(|k| (si * d[min_i][k] + sj * d[min_j][k]) / (si + sj)).collect();
+        for (k, dk) in merged.into_iter().enumerate() {
+            d[min_i][k] = dk;
+            d[k][min_i] = dk;
+        }
+        d[min_i][min_i] = 0.0;
+        active[min_i] = node;
+        sizes[min_i] = si + sj;
+        heights[min_i] = height.max(heights[min_i]).max(heights[min_j]);
+
+        d.remove(min_j);
+        for row in d.iter_mut() {
+            row.remove(min_j);
+        }
+        active.remove(min_j);
+        sizes.remove(min_j);
+        heights.remove(min_j);
+    }
+
+    tree.root = active[0];
+    Ok(tree)
+}
+
+/// Writes a tree in Newick format, see `Tree::to_newick`.
+///
+/// Compressed outputs are written by extension and `-` writes to stdout.
+pub fn write_newick_to_file(tree: &Tree, tips: &[NodeLabel], path: &Path, annotate_labels: bool) -> Result<(), NetviewError> {
+    let newick = tree.to_newick(tips, annotate_labels)?;
+    let mut writer = get_file_writer(path)?;
+    writeln!(writer, "{newick}")?;
+    writer.flush()?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     // Tests for compute_euclidean_distance_of_distances
 
+    // Tests for tree construction
+
+    fn tips(ids: &[&str]) -> Vec<NodeLabel> {
+        ids.iter().enumerate().map(|(i, id)| NodeLabel::new(i, Some(id.to_string()))).collect()
+    }
+
+    #[test]
+    fn upgma_newick() {
+        let matrix = vec![vec![0.0], vec![2.0, 0.0], vec![6.0, 6.0, 0.0]];
+        let tree = build_tree(&matrix, &TreeMethod::Upgma).unwrap();
+        let newick = tree.to_newick(&tips(&["a", "b", "c"]), false).unwrap();
+        assert_eq!(newick, "((a:1,b:1):2,c:3);");
+    }
+
+    #[test]
+    fn neighbor_joining_additive_tree() {
+        // Additive distances of the tree ((a:1,b:2):1,c:3,(d:2,e:1):2)
+        let matrix = vec![
+            vec![0.0, 3.0, 5.0, 6.0, 5.0],
+            vec![3.0, 0.0, 6.0, 7.0, 6.0],
+            vec![5.0, 6.0, 0.0, 7.0, 6.0],
+            vec![6.0, 7.0, 7.0, 0.0, 3.0],
+            vec![5.0, 6.0, 6.0, 3.0, 0.0],
+        ];
+        let tree = build_tree(&matrix, &TreeMethod::Nj).unwrap();
+        assert_eq!(tree.tip_count(), 5);
+
+        let newick = tree.to_newick(&tips(&["a", "b", "c", "d", "e"]), false).unwrap();
+        assert!(newick.contains("(a:1,b:2)"));
+        assert!(newick.contains("(d:2,e:1)"));
+        assert!(newick.contains("c:3"));
+    }
+
+    #[test]
+    fn newick_labels_and_quoting() {
+        let matrix = vec![vec![0.0, 1.0], vec![1.0, 0.0]];
+        let tree = build_tree(&matrix, &TreeMethod::Nj).unwrap();
+
+        let mut nodes = tips(&["sample one", "b"]);
+        nodes[1].label = Some("ST93".to_string());
+        nodes[1].label_confidence = 1.0;
+
+        let newick = tree.to_newick(&nodes, true).unwrap();
+        assert_eq!(newick, "('sample one':0.5,b:0.5[&label=\"ST93\",label_confidence=1]);");
+        assert!(matches!(tree.to_newick(&nodes[..1], true), Err(NetviewError::NodeLabelLengthError(2))));
+
+        let single = build_tree(&vec![vec![0.0]], &TreeMethod::Nj).unwrap();
+        assert_eq!(single.to_newick(&nodes[..1], false).unwrap(), "'sample one';");
+    }
+
 
     // Tests for make_symmetrical
 
@@ -696,6 +1030,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn write_and_parse_compressed_matrix() {
+        let dir = tempdir().unwrap();
+        let matrix = vec![vec![0.0, 1.5], vec![1.5, 0.0]];
+        for name in ["matrix.tsv.gz", "matrix.tsv.bz2", "matrix.tsv.xz", "matrix.tsv"] {
+            let path = dir.path().join(name);
+            write_matrix_to_file(&matrix, &path).unwrap();
+            assert_eq!(parse_input_matrix(&path, false).unwrap(), matrix);
+        }
+        let gz = std::fs::read(dir.path().join("matrix.tsv.gz")).unwrap();
+        assert_eq!(&gz[..2], &[0x1f, 0x8b]);
+    }
+
+    #[test]
+    fn parse_matrix_shorter_than_magic_bytes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("matrix.tsv");
+        std::fs::write(&path, "0\n").unwrap();
+        assert_eq!(parse_input_matrix(&path, false).unwrap(), vec![vec![0.0]]);
+    }
+
     #[test]
     fn valid_tsv_with_mixed_delimiters_error() {
         // Uses both comma and tab as delimiters, which should result in an error
@@ -37,6 +37,36 @@ pub enum NetviewError {
     GraphSerializationError(String),
     #[error("Error deserializing the graph: {0}")]
     GraphDeserializationError(String),
+    #[error("Failed to parse edge list at line {0}: {1}")]
+    EdgeListParseError(usize, String),
+    #[error("Failed to parse Matrix Market file at line {0}: {1}")]
+    MatrixMarketParseError(usize, String),
+    #[error("Graph schema version {0} is not supported by this version of netview (<= {1})")]
+    GraphVersionError(u32, u32),
+    #[error("Edge {0} references node {1} but the graph has {2} nodes")]
+    GraphEdgeEndpointError(usize, usize, usize),
+    #[error("Duplicate edge between nodes {0} and {1}")]
+    GraphDuplicateEdgeError(usize, usize),
+    #[error("Duplicate node identifier '{0}'")]
+    GraphDuplicateIdError(String),
+    #[error("Eigensolver converged for {0} of {1} eigenpairs after {2} iterations, increase the maximum iterations or tolerance")]
+    EigenConvergenceError(usize, usize, usize),
+    #[error("Invalid community detection settings: {0}")]
+    CommunityConfigError(String),
+    #[error("Invalid label spreading settings: {0}")]
+    LabelSpreadingConfigError(String),
+    #[error("Partition column not found: {0}")]
+    PartitionColumnError(String),
+    #[error("Metadata column not found: {0}")]
+    MetadataColumnError(String),
+    #[error("Label column not found: {0}")]
+    LabelColumnError(String),
+    #[error("Invalid attribute filter '{0}': expected <attribute><operator><value> with one of =, !=, <, <=, >, >=")]
+    AttributeFilterError(String),
+    #[error("Failed to evaluate label predictions: {0}")]
+    EvaluationError(String),
+    #[error("Failed to compare partitions: {0}")]
+    PartitionComparisonError(String),
     #[error("Error writing to file: {0}")]
     WriteError(String),
     #[error("CSV error: {0}")]
@@ -64,6 +94,16 @@ pub enum NetviewError {
     #[error("Failed to serialize TOML: {0}")]
     TomlSerialize(#[from] toml::ser::Error),
     
+    #[cfg(feature = "columnar")]
+    #[error(transparent)]
+    ArrowError(#[from] arrow::error::ArrowError),
+    #[cfg(feature = "columnar")]
+    #[error(transparent)]
+    ParquetError(#[from] parquet::errors::ParquetError),
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    SqliteError(#[from] rusqlite::Error),
+
     #[cfg(feature = "plot")]
     #[error(transparent)]
     PlottersBitmapError(#[from] plotters_bitmap::BitMapBackendError),
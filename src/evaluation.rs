@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+use petgraph::graph::NodeIndex;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Serialize;
+
+use crate::compare::ContingencyTable;
+use crate::config::LabelConfig;
+use crate::error::NetviewError;
+use crate::label::{abstain_novel, label_nodes, label_propagation_with_centrality, propagate_node_label};
+use crate::mknn::node_id_or_index;
+use crate::netview::NetviewGraph;
+use crate::utils::{get_file_writer, has_extension};
+use crate::validation::Fold;
+
+/// Predicted label of a labelled node with its label masked
+#[derive(Serialize, Clone, Debug)]
+pub struct MaskedPrediction {
+    pub id: String,
+    pub label: String,
+    pub predicted: Option<String>,     // No prediction without labelled neighbors
+    pub confidence: f64,
+}
+
+/// Precision, recall and F1 of a reference class
+#[derive(Serialize, Clone, Debug)]
+pub struct ClassMetrics {
+    pub label: String,
+    pub support: usize,                // Evaluated nodes of the class
+    pub predicted: usize,              // Evaluated nodes predicted as the class
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+/// Accuracy, confusion matrix and per-class metrics of masked label predictions
+#[derive(Serialize, Clone, Debug)]
+pub struct Evaluation {
+    pub nodes: usize,                  // Evaluated (masked) nodes
+    pub unpredicted: usize,            // Evaluated nodes without a prediction, counted as incorrect
+    pub accuracy: f64,
+    pub macro_precision: f64,
+    pub macro_recall: f64,
+    pub macro_f1: f64,
+    pub classes: Vec<ClassMetrics>,
+    pub confusion: ContingencyTable,   // Reference and predicted classes in the same order
+    #[serde(skip)]
+    pub predictions: Vec<MaskedPrediction>,
+}
+
+impl Evaluation {
+    /// Computes the metrics from masked predictions.
+    ///
+    /// Nodes without a prediction count against accuracy and recall of their class and
+    /// are not part of the confusion matrix. Predicted classes without reference nodes
+    /// (e.g. the novel label of abstention) are columns of the confusion matrix but have
+    /// no class metrics. Macro averages are over the reference classes.
+    pub fn from_predictions(predictions: Vec<MaskedPrediction>) -> Self {
+
+        let labels: Vec<String> = predictions.iter()
+            .flat_map(|prediction| std::iter::once(&prediction.label).chain(prediction.predicted.as_ref()))
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let index: HashMap<&String, usize> = labels.iter().enumerate().map(|(i, label)| (label, i)).collect();
+
+        let mut counts = vec![vec![0; labels.len()]; labels.len()];
+        let mut support = vec![0; labels.len()];
+        for prediction in &predictions {
+            support[index[&prediction.label]] += 1;
+            if let Some(predicted) = &prediction.predicted {
+                counts[index[&prediction.label]][index[predicted]] += 1;
+            }
+        }
+
+        let ratio = |a: usize, b: usize| if b > 0 { a as f64 / b as f64 } else { 0.0 };
+        let classes: Vec<ClassMetrics> = labels.iter().enumerate()
+            .filter(|(i, _)| support[*i] > 0)
+            .map(|(i, label)| {
+                let predicted: usize = counts.iter().map(|row| row[i]).sum();
+                let precision = ratio(counts[i][i], predicted);
+                let recall = ratio(counts[i][i], support[i]);
+                let f1 = if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 };
+                ClassMetrics { label: label.clone(), support: support[i], predicted, precision, recall, f1 }
+            })
+            .collect();
+
+        let mean = |metric: fn(&ClassMetrics) -> f64| {
+            if classes.is_empty() { 0.0 } else { classes.iter().map(metric).sum::<f64>() / classes.len() as f64 }
+        };
+        let correct: usize = (0..labels.len()).map(|i| counts[i][i]).sum();
+
+        Evaluation {
+            nodes: predictions.len(),
+            unpredicted: predictions.iter().filter(|prediction| prediction.predicted.is_none()).count(),
+            accuracy: ratio(correct, predictions.len()),
+            macro_precision: mean(|class| class.precision),
+            macro_recall: mean(|class| class.recall),
+            macro_f1: mean(|class| class.f1),
+            confusion: ContingencyTable { reference: labels.clone(), predicted: labels, counts },
+            classes,
+            predictions,
+        }
+    }
+    /// Writes the metrics as JSON (`.json`, including class metrics and confusion matrix)
+    /// or as TSV with columns `metric` and `value`.
+    pub fn write(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        if has_extension(path, &[".json"]) {
+            serde_json::to_writer_pretty(&mut writer, self)?;
+            writeln!(writer)?;
+        } else {
+            writeln!(writer, "metric\tvalue")?;
+            writeln!(writer, "nodes\t{}", self.nodes)?;
+            writeln!(writer, "unpredicted\t{}", self.unpredicted)?;
+            writeln!(writer, "accuracy\t{}", self.accuracy)?;
+            writeln!(writer, "macro_precision\t{}", self.macro_precision)?;
+            writeln!(writer, "macro_recall\t{}", self.macro_recall)?;
+            writeln!(writer, "macro_f1\t{}", self.macro_f1)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    /// Writes the per-class metrics as TSV.
+    pub fn write_classes(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        writeln!(writer, "label\tsupport\tpredicted\tprecision\trecall\tf1")?;
+        for class in &self.classes {
+            writeln!(
+                writer, "{}\t{}\t{}\t{}\t{}\t{}",
+                class.label, class.support, class.predicted, class.precision, class.recall, class.f1
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    /// Writes the masked predictions as TSV, nodes without a prediction have an empty label.
+    pub fn write_predictions(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+        writeln!(writer, "id\tlabel\tpredicted\tconfidence")?;
+        for prediction in &self.predictions {
+            writeln!(
+                writer, "{}\t{}\t{}\t{}",
+                prediction.id, prediction.label, prediction.predicted.as_deref().unwrap_or_default(), prediction.confidence
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Leave-one-out evaluation of label propagation on a labelled graph.
+///
+/// Each evaluated node has its label masked and is the only target of label propagation
+/// with the vote settings, hierarchy and abstention of the label configuration, so that
+/// accuracy is measured on the graph without recomputing distances. The node centrality
+/// of the configured metric (see `node_centrality`) is computed once by the caller, since
+/// masking a label does not change the graph topology. With `max_per_label`, a stratified
+/// sample of at most this many nodes per label is evaluated (seeded). Label spreading is
+/// not evaluated, the vote settings are used instead.
+///
+/// # Errors
+///
+/// Returns `NetviewError::EvaluationError` if the graph has no labelled nodes.
+pub fn leave_one_out(
+    graph: &NetviewGraph,
+    config: &LabelConfig,
+    centrality: &HashMap<usize, f64>,
+    max_per_label: Option<usize>,
+    seed: u64
+) -> Result<Evaluation, NetviewError> {
+
+    if config.spreading.is_some() {
+        log::warn!("Leave-one-out evaluation uses label propagation by votes, label spreading is not evaluated");
+    }
+
+    // Labelled nodes grouped by label for stratified sampling
+    let mut strata: BTreeMap<&String, Vec<NodeIndex>> = BTreeMap::new();
+    for node in graph.node_indices() {
+        if let Some(label) = &graph[node].label {
+            strata.entry(label).or_default().push(node);
+        }
+    }
+    if strata.is_empty() {
+        return Err(NetviewError::EvaluationError("graph has no labelled nodes".to_string()))
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut nodes: Vec<NodeIndex> = Vec::new();
+    for mut stratum in strata.into_values() {
+        if let Some(max) = max_per_label {
+            stratum.shuffle(&mut rng);
+            stratum.truncate(max);
+        }
+        nodes.extend(stratum);
+    }
+    nodes.sort();
+
+    log::info!("Evaluating {} labelled nodes with leave-one-out label propagation", nodes.len());
+    let mut graph = graph.clone();
+    let mut predictions = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let original = graph[node].clone();
+        let id = node_id_or_index(&original);
+
+        graph[node].label = None;
+        let predicted = propagate_node_label(&mut graph, node, centrality, config);
+        if predicted && config.abstention.is_enabled() {
+            abstain_novel(&mut graph, std::slice::from_ref(&id), &config.abstention);
+        }
+
+        predictions.push(MaskedPrediction {
+            id,
+            label: original.label.clone().unwrap_or_default(),
+            predicted: graph[node].label.clone(),
+            confidence: if predicted { graph[node].label_confidence } else { 0.0 },
+        });
+        graph[node] = original;
+    }
+
+    let evaluation = Evaluation::from_predictions(predictions);
+    log::info!(
+        "Leave-one-out evaluation of {} nodes: accuracy = {:.4}, macro F1 = {:.4}, unpredicted = {}",
+        evaluation.nodes,
+        evaluation.accuracy,
+        evaluation.macro_f1,
+        evaluation.unpredicted
+    );
+    Ok(evaluation)
+}
+
+/// Evaluation of label propagation with the known labels of cross-validation folds.
+///
+/// For each fold, only the training nodes are labelled and label propagation targets
+/// the test nodes with the label configuration, predictions of all folds are pooled.
+/// Nodes are matched by identifier, test nodes that are not in the graph are skipped.
+/// The node centrality of the configured metric is shared by all folds of the graph.
+///
+/// # Errors
+///
+/// Returns `NetviewError::EvaluationError` if no test node of any fold is in the graph.
+pub fn evaluate_folds(
+    graph: &NetviewGraph,
+    config: &LabelConfig,
+    centrality: &HashMap<usize, f64>,
+    folds: &[Fold]
+) -> Result<Evaluation, NetviewError> {
+
+    if config.spreading.is_some() {
+        log::warn!("Cross-validation uses label propagation by votes, label spreading is not evaluated");
+    }
+
+    let mut predictions = Vec::new();
+    for (i, fold) in folds.iter().enumerate() {
+
+        let mut fold_graph = graph.clone();
+        let train_labels = graph.node_weights().map(|node| fold.train.get(&node_id_or_index(node)).cloned()).collect();
+        label_nodes(&mut fold_graph, train_labels)?;
+
+        let test_nodes: Vec<(NodeIndex, String)> = graph.node_indices()
+            .map(|node| (node, node_id_or_index(&graph[node])))
+            .filter(|(_, id)| fold.test.contains_key(id))
+            .collect();
+        if test_nodes.len() < fold.test.len() {
+            log::warn!("Fold {i}: {} of {} test nodes are not in the graph", fold.test.len() - test_nodes.len(), fold.test.len());
+        }
+        let test_ids: Vec<String> = test_nodes.iter().map(|(_, id)| id.clone()).collect();
+
+        label_propagation_with_centrality(&mut fold_graph, config, centrality, Some(test_ids.clone()), false, None);
+        if config.abstention.is_enabled() {
+            abstain_novel(&mut fold_graph, &test_ids, &config.abstention);
+        }
+
+        for (node, id) in test_nodes {
+            let predicted = fold_graph[node].label.clone();
+            predictions.push(MaskedPrediction {
+                label: fold.test[&id].clone(),
+                confidence: if predicted.is_some() { fold_graph[node].label_confidence } else { 0.0 },
+                predicted,
+                id,
+            });
+        }
+    }
+    if predictions.is_empty() {
+        return Err(NetviewError::EvaluationError("no test node of the folds is in the graph".to_string()))
+    }
+
+    let evaluation = Evaluation::from_predictions(predictions);
+    log::info!(
+        "Evaluation of {} test nodes in {} folds: accuracy = {:.4}, macro F1 = {:.4}, unpredicted = {}",
+        evaluation.nodes,
+        folds.len(),
+        evaluation.accuracy,
+        evaluation.macro_f1,
+        evaluation.unpredicted
+    );
+    Ok(evaluation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::centrality::node_centrality;
+    use crate::label::label_nodes;
+    use crate::mknn::convert_to_graph;
+
+    fn prediction(label: &str, predicted: Option<&str>) -> MaskedPrediction {
+        MaskedPrediction { id: String::new(), label: label.to_string(), predicted: predicted.map(String::from), confidence: 1.0 }
+    }
+
+    #[test]
+    fn metrics_from_predictions() {
+        let evaluation = Evaluation::from_predictions(vec![
+            prediction("A", Some("A")),
+            prediction("A", Some("B")),
+            prediction("A", None),
+            prediction("B", Some("B")),
+            prediction("B", Some("novel")),
+        ]);
+
+        assert_eq!((evaluation.nodes, evaluation.unpredicted), (5, 1));
+        assert!((evaluation.accuracy - 0.4).abs() < 1e-12);
+        assert_eq!(evaluation.confusion.reference, vec!["A", "B", "novel"]);
+        assert_eq!(evaluation.confusion.counts, vec![vec![1, 1, 0], vec![0, 1, 1], vec![0, 0, 0]]);
+
+        // Classes without reference nodes have no metrics
+        assert_eq!(evaluation.classes.len(), 2);
+        let a = &evaluation.classes[0];
+        assert_eq!((a.support, a.predicted), (3, 1));
+        assert!((a.precision - 1.0).abs() < 1e-12);
+        assert!((a.recall - 1.0 / 3.0).abs() < 1e-12);
+        assert!((a.f1 - 0.5).abs() < 1e-12);
+        let b = &evaluation.classes[1];
+        assert!((b.precision - 0.5).abs() < 1e-12 && (b.recall - 0.5).abs() < 1e-12);
+        assert!((evaluation.macro_f1 - 0.5).abs() < 1e-12);
+
+        // Output format from the file extension without compression suffix
+        let dir = tempfile::tempdir().unwrap();
+        for (name, json) in [("evaluation.JSON", true), ("evaluation.jsonish.tsv", false)] {
+            let output = dir.path().join(name);
+            evaluation.write(&output).unwrap();
+            assert_eq!(std::fs::read_to_string(&output).unwrap().starts_with('{'), json, "{name}");
+        }
+    }
+
+    #[test]
+    fn leave_one_out_masks_each_node() {
+        // Two triangles labelled 'A' and 'B' connected by a bridge between a2 and b0
+        let mnn = vec![vec![1, 2], vec![0, 2], vec![0, 1, 3], vec![2, 4, 5], vec![3, 5], vec![3, 4]];
+        let dist = vec![vec![10.0; 6]; 6];
+        let ids = Some(["a0", "a1", "a2", "b0", "b1", "b2"].iter().map(|id| id.to_string()).collect());
+        let mut graph = convert_to_graph(&mnn, Some(&dist), None, ids, None).unwrap();
+        let labels = ["A", "A", "A", "B", "B", "B"].iter().map(|label| Some(label.to_string())).collect();
+        label_nodes(&mut graph, labels).unwrap();
+
+        let config = LabelConfig::default();
+        let centrality = node_centrality(&graph, &config.centrality_metric);
+        let evaluation = leave_one_out(&graph, &config, &centrality, None, 42).unwrap();
+        assert_eq!(evaluation.nodes, 6);
+        assert!((evaluation.accuracy - 1.0).abs() < 1e-12);
+        assert_eq!(evaluation.predictions[2].predicted, Some("A".to_string()));
+        assert!(evaluation.predictions[2].confidence < 1.0);
+
+        // Labels of the input graph are not changed by masking
+        assert!(graph.node_weights().all(|node| node.label.is_some()));
+
+        let sample = leave_one_out(&graph, &config, &centrality, Some(1), 42).unwrap();
+        assert_eq!(sample.nodes, 2);
+
+        let unlabelled = convert_to_graph(&mnn, Some(&dist), None, None, None).unwrap();
+        assert!(matches!(leave_one_out(&unlabelled, &config, &centrality, None, 42), Err(NetviewError::EvaluationError(_))));
+    }
+}
@@ -1,19 +1,25 @@
 
 use csv::WriterBuilder;
 use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::collections::HashMap;
 
 use std::collections::HashSet;
-use std::fs::File; 
-use std::path::Path;
-use crate::centrality::betweenness_centrality;
-use crate::centrality::closeness_centrality;
-use crate::centrality::degree_centrality;
-use crate::centrality::NodeCentrality;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use crate::centrality::node_centrality;
+use crate::config::LabelConfig;
 use crate::error::NetviewError;
+use crate::metadata::AttributeValue;
+use crate::mknn::node_id_or_index;
 use crate::netview::NetviewGraph;
+use crate::utils::{get_file_reader, get_file_writer, has_extension};
 
 #[derive(Eq, Hash, PartialEq)]
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -22,12 +28,24 @@ pub struct Label {
     pub label: Option<String>,
 }
 
+// Label of a graph node with the vote shares of the label and the runner-up label
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LabelPrediction {
+    pub id: String,
+    pub label: Option<String>,
+    pub label_confidence: f64,
+    pub runner_up_label: Option<String>,
+    pub runner_up_confidence: f64,
+    pub rank_confidence: Option<String>,
+    pub abstention: Option<String>,
+}
+
 
 pub fn read_labels_from_file<P: AsRef<Path>>(
     file_path: P,
     tsv: bool,
 ) -> Result<Vec<Label>, NetviewError> {
-    let file = File::open(file_path)?;
+    let file = get_file_reader(file_path.as_ref())?;
     let mut rdr = if tsv {
         csv::ReaderBuilder::new().delimiter(b'\t').trim(csv::Trim::All).from_reader(file)
     } else {
@@ -40,6 +58,225 @@ pub fn read_labels_from_file<P: AsRef<Path>>(
 }
 
 
+// Delimiter of label and metadata tables
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum LabelDelimiter {
+    #[default]
+    Auto,
+    Comma,
+    Tab,
+    Semicolon,
+}
+impl std::fmt::Display for LabelDelimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            LabelDelimiter::Auto => "auto",
+            LabelDelimiter::Comma => "comma",
+            LabelDelimiter::Tab => "tab",
+            LabelDelimiter::Semicolon => "semicolon",
+        };
+        write!(f, "{}", output)
+    }
+}
+impl LabelDelimiter {
+    // Delimiter byte, detected from the file extension (.tsv, .tab) without compression 
+    // suffix (.gz, .bz2, .xz) or the most frequent delimiter in the header
+    pub fn resolve(&self, path: &Path, content: &str) -> u8 {
+        match self {
+            LabelDelimiter::Comma => b',',
+            LabelDelimiter::Tab => b'\t',
+            LabelDelimiter::Semicolon => b';',
+            LabelDelimiter::Auto => {
+                if has_extension(path, &[".tsv", ".tab"]) {
+                    return b'\t'
+                }
+                let header = content.lines().next().unwrap_or_default();
+                [b',', b'\t', b';'].into_iter()
+                    .max_by_key(|delimiter| header.bytes().filter(|b| b == delimiter).count())
+                    .filter(|delimiter| header.as_bytes().contains(delimiter))
+                    .unwrap_or(b',')
+            }
+        }
+    }
+}
+
+// Identifier and label columns selected from a label table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelColumns {
+    pub delimiter: LabelDelimiter,
+    pub id: String,
+    pub labels: Vec<String>,
+}
+impl Default for LabelColumns {
+    fn default() -> Self {
+        Self {
+            delimiter: LabelDelimiter::Auto,
+            id: "id".to_string(),
+            labels: vec!["label".to_string()],
+        }
+    }
+}
+
+// Output path for a label column when labels are predicted for multiple columns,
+// the column is inserted before the extensions e.g. votes.tsv.gz -> votes.serotype.tsv.gz
+pub fn label_column_path(path: &Path, column: &str) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let name = match name.split_once('.') {
+        Some((stem, extensions)) => format!("{stem}.{column}.{extensions}"),
+        None => format!("{name}.{column}")
+    };
+    path.with_file_name(name)
+}
+
+// Read the header and records of a (compressed) table with its delimiter, tables are read from stdin with '-'
+pub(crate) fn read_table<P: AsRef<Path>>(path: P, delimiter: &LabelDelimiter) -> Result<(csv::StringRecord, Vec<csv::StringRecord>), NetviewError> {
+    let mut content = String::new();
+    get_file_reader(path.as_ref())?.read_to_string(&mut content)?;
+    let delimiter = delimiter.resolve(path.as_ref(), &content);
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let header = reader.headers()?.clone();
+    let records = reader.records().collect::<Result<Vec<_>, csv::Error>>()?;
+    Ok((header, records))
+}
+
+// Identifiers and the values of the selected label columns of a label table
+#[derive(Debug, Clone)]
+pub struct LabelTable {
+    pub id_column: String,
+    pub ids: Vec<String>,
+    pub columns: Vec<String>,
+    pub values: Vec<Vec<Option<String>>>,  // One vector per label column in order of the identifiers
+}
+impl LabelTable {
+    // Identifier and label columns of the records of a table (see `read_table`)
+    pub(crate) fn from_records(header: &csv::StringRecord, records: &[csv::StringRecord], columns: &LabelColumns) -> Result<Self, NetviewError> {
+        let position = |column: &str| header.iter().position(|h| h == column)
+            .ok_or_else(|| NetviewError::LabelColumnError(column.to_string()));
+        let id_column = position(&columns.id)?;
+        let label_columns = columns.labels.iter().map(|column| position(column)).collect::<Result<Vec<_>, _>>()?;
+
+        let mut ids = Vec::new();
+        let mut values = vec![Vec::new(); label_columns.len()];
+        for record in records {
+            ids.push(record.get(id_column).unwrap_or_default().to_string());
+            for (column, &i) in values.iter_mut().zip(&label_columns) {
+                column.push(record.get(i).filter(|value| !value.is_empty()).map(String::from));
+            }
+        }
+
+        Ok(LabelTable { id_column: columns.id.clone(), ids, columns: columns.labels.clone(), values })
+    }
+    // Labels of a single label column
+    pub fn labels(&self, column: usize) -> Vec<Label> {
+        self.ids.iter().zip(&self.values[column])
+            .map(|(id, label)| Label { id: id.clone(), label: label.clone() })
+            .collect()
+    }
+    // Labels combining all label columns (e.g. for stratification), unlabelled if any column is empty
+    pub fn strata(&self) -> Vec<Label> {
+        (0..self.ids.len()).map(|i| {
+            let values: Option<Vec<&str>> = self.values.iter().map(|column| column[i].as_deref()).collect();
+            Label { id: self.ids[i].clone(), label: values.map(|values| values.join("|")) }
+        }).collect()
+    }
+    // Write the identifier and label columns, optionally for a subset of identifiers in the given order
+    pub fn write<P: AsRef<Path>>(&self, path: P, ids: Option<&[String]>, tsv: bool) -> Result<(), NetviewError> {
+        let writer = get_file_writer(path.as_ref())?;
+        let mut wtr = WriterBuilder::new().delimiter(if tsv { b'\t' } else { b',' }).from_writer(writer);
+
+        let mut header = vec![self.id_column.as_str()];
+        header.extend(self.columns.iter().map(String::as_str));
+        wtr.write_record(&header)?;
+
+        let rows: HashMap<&String, usize> = self.ids.iter().enumerate().map(|(i, id)| (id, i)).collect();
+        let selected: Vec<usize> = match ids {
+            Some(ids) => ids.iter().filter_map(|id| rows.get(id).copied()).collect(),
+            None => (0..self.ids.len()).collect()
+        };
+        for i in selected {
+            let mut record = vec![self.ids[i].as_str()];
+            record.extend(self.values.iter().map(|column| column[i].as_deref().unwrap_or_default()));
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+// Read the identifier and label columns from a label table with header, empty labels are unlabelled
+pub fn read_label_table<P: AsRef<Path>>(path: P, columns: &LabelColumns) -> Result<LabelTable, NetviewError> {
+    let (header, records) = read_table(path.as_ref(), &columns.delimiter)?;
+    LabelTable::from_records(&header, &records, columns)
+}
+
+// Write the propagated labels of each label column (one graph per column) as columns '{column}', 
+// '{column}_confidence', '{column}_runner_up_label', '{column}_runner_up_confidence', 
+// '{column}_rank_confidence' and '{column}_abstention' as in `write_graph_labels_to_file`
+pub fn write_label_columns_to_file<P: AsRef<Path>>(
+    predictions: &[(String, NetviewGraph)],
+    output_file: P,
+    ids: Option<Vec<String>>,
+    tsv: bool
+) -> Result<(), NetviewError> {
+
+    let writer = get_file_writer(output_file.as_ref())?;
+    let mut wtr = WriterBuilder::new().delimiter(if tsv { b'\t' } else { b',' }).from_writer(writer);
+
+    let mut header = vec!["id".to_string()];
+    for (column, _) in predictions {
+        header.extend([
+            column.clone(), 
+            format!("{column}_confidence"), 
+            format!("{column}_runner_up_label"), 
+            format!("{column}_runner_up_confidence"),
+            format!("{column}_rank_confidence"),
+            format!("{column}_abstention")
+        ]);
+    }
+    wtr.write_record(&header)?;
+
+    let Some((_, first)) = predictions.first() else { return Ok(()) };
+    for node in first.node_indices() {
+        let id = node_id_or_index(&first[node]);
+        if ids.as_ref().is_some_and(|ids| !ids.contains(&id)) {
+            continue;
+        }
+        let mut record = vec![id];
+        for (_, graph) in predictions {
+            let node_label = &graph[node];
+            record.extend([
+                node_label.label.clone().unwrap_or_default(),
+                node_label.label_confidence.to_string(),
+                node_label.runner_up_label.clone().unwrap_or_default(),
+                node_label.runner_up_confidence.to_string(),
+                node_label.rank_confidence.as_deref().map(format_rank_confidence).unwrap_or_default(),
+                node_label.abstention.clone().unwrap_or_default(),
+            ]);
+        }
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Confidence of each rank of a hierarchical label separated by ';'
+pub(crate) fn format_rank_confidence(confidence: &[f64]) -> String {
+    confidence.iter().map(|c| format!("{c:.4}")).collect::<Vec<_>>().join(";")
+}
+
+// Add the propagated label and confidence of a label column as node attributes
+pub fn add_label_column_attributes(graph: &mut NetviewGraph, column: &str, prediction: &NetviewGraph) {
+    for (node, predicted) in graph.node_weights_mut().zip(prediction.node_weights()) {
+        if let Some(label) = &predicted.label {
+            node.attributes.insert(column.to_string(), AttributeValue::Text(label.clone()));
+            node.attributes.insert(format!("{column}_confidence"), AttributeValue::Float(predicted.label_confidence));
+        }
+    }
+}
+
 // Function to write the labels from a graph to a file
 pub fn write_graph_labels_to_file<P: AsRef<Path>>(
     graph: &NetviewGraph,        
@@ -48,8 +285,8 @@ pub fn write_graph_labels_to_file<P: AsRef<Path>>(
     tsv: bool,                
 ) -> Result<(), NetviewError> {
 
-    // Open the output file for writing
-    let file = File::create(output_file)?;
+    // Open the output file for writing, compressed by extension or stdout with '-'
+    let file = get_file_writer(output_file.as_ref())?;
     
     // Use the csv::WriterBuilder to set the delimiter (tab for TSV, comma for CSV)
     let mut wtr = if tsv {
@@ -70,10 +307,15 @@ pub fn write_graph_labels_to_file<P: AsRef<Path>>(
                 }
             }
 
-            // Construct a Label struct
-            let label = Label {
+            // Construct a LabelPrediction struct with vote shares from label propagation
+            let label = LabelPrediction {
                 id: node_label.id.clone().unwrap_or_else(|| node.index().to_string()),
                 label: node_label.label.clone(),
+                label_confidence: node_label.label_confidence,
+                runner_up_label: node_label.runner_up_label.clone(),
+                runner_up_confidence: node_label.runner_up_confidence,
+                rank_confidence: node_label.rank_confidence.as_deref().map(format_rank_confidence),
+                abstention: node_label.abstention.clone(),
             };
 
             // Write the label to the file
@@ -95,8 +337,8 @@ pub fn write_labels_to_file<P: AsRef<Path>>(
     tsv: bool                      
 ) -> Result<(), NetviewError> {
 
-    // Open the output file for writing
-    let file = File::create(output_file)?;
+    // Open the output file for writing, compressed by extension or stdout with '-'
+    let file = get_file_writer(output_file.as_ref())?;
     
     // Use the csv::WriterBuilder to set the delimiter (tab for TSV, comma for CSV)
     let mut wtr = if tsv {
@@ -135,6 +377,13 @@ pub fn label_nodes(graph: &mut NetviewGraph, labels: Vec<Option<String>>) -> Res
         // Mutably borrow the node's weight (NodeLabel) and update the label
         if let Some(node_weight) = graph.node_weight_mut(node) {
             node_weight.label = label.clone();
+            // Known labels are certain until they are replaced by label propagation
+            node_weight.label_confidence = if label.is_some() { 1.0 } else { 0.0 };
+            node_weight.runner_up_label = None;
+            node_weight.runner_up_confidence = 0.0;
+            node_weight.label_probabilities = None;
+            node_weight.abstention = None;
+            node_weight.rank_confidence = None;
             
             log::debug!(
                 "Node {} labeled with '{}'.",
@@ -152,11 +401,11 @@ pub fn label_nodes(graph: &mut NetviewGraph, labels: Vec<Option<String>>) -> Res
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VoteWeights {
-    centrality: f64,      // centrality metric in label propagation vote
-    weight: f64,          // input distance -> similarity
-    af: f64,              // alignment fraction from skani
-    ani: f64,             // blast ani from vircov 
-    aai: f64,             // blast aai from vircov
+    pub centrality: f64,      // centrality metric in label propagation vote
+    pub weight: f64,          // input distance -> similarity
+    pub af: f64,              // alignment fraction from skani
+    pub ani: f64,             // blast ani from vircov 
+    pub aai: f64,             // blast aai from vircov
 }
 
 impl Default for VoteWeights {
@@ -171,28 +420,161 @@ impl Default for VoteWeights {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, clap::ValueEnum)]
+pub enum VoteReportFormat {
+    Tsv,
+    Jsonl,
+}
+impl std::fmt::Display for VoteReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            VoteReportFormat::Tsv => "tsv",
+            VoteReportFormat::Jsonl => "jsonl",
+        };
+        write!(f, "{}", output)
+    }
+}
+impl VoteReportFormat {
+    // JSON-lines if the file extension is '.jsonl' or '.json' without compression suffix
+    // (e.g. votes.jsonl.gz), TSV otherwise
+    pub fn from_path(path: &Path) -> Self {
+        if has_extension(path, &[".jsonl", ".json"]) {
+            VoteReportFormat::Jsonl
+        } else {
+            VoteReportFormat::Tsv
+        }
+    }
+}
+
+// Vote of a single labelled neighbor for a target node
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NeighborVote {
+    pub id: String,
+    pub label: String,
+    pub distance: f64,
+    pub af: Option<f64>,
+    pub centrality: f64,
+    pub neighbor_centrality: Option<f64>,  // Only if neighbor centrality contributes to the vote
+    pub vote: f64,
+}
+
+// Summed vote weight of a label for a target node
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LabelVote {
+    pub label: String,
+    pub vote: f64,
+    pub share: f64,
+}
+
+// Vote breakdown of a target node in the last iteration in which it was evaluated
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NodeVotes {
+    pub id: String,
+    pub label: Option<String>,
+    pub iteration: usize,
+    pub totals: Vec<LabelVote>,  // Descending by vote weight
+    pub neighbors: Vec<NeighborVote>,
+}
+
+// Per-node vote breakdown collected during label propagation
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct VoteReport {
+    pub nodes: BTreeMap<usize, NodeVotes>,  // Keyed by node index
+}
+impl VoteReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Write one row per neighbor vote (TSV) or one object per target node (JSON-lines)
+    pub fn write<P: AsRef<Path>>(&self, path: P, format: &VoteReportFormat) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path.as_ref())?;
+        match format {
+            VoteReportFormat::Jsonl => {
+                for votes in self.nodes.values() {
+                    serde_json::to_writer(&mut writer, votes)?;
+                    writer.write_all(b"\n")?;
+                }
+                writer.flush()?;
+            },
+            VoteReportFormat::Tsv => {
+                let mut wtr = WriterBuilder::new().delimiter(b'\t').from_writer(writer);
+                wtr.write_record([
+                    "id", "label", "iteration", "neighbor_id", "neighbor_label", "distance", "af", 
+                    "centrality", "neighbor_centrality", "vote", "label_vote", "label_share"
+                ])?;
+                let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+                for votes in self.nodes.values() {
+                    for neighbor in &votes.neighbors {
+                        let total = votes.totals.iter().find(|t| t.label == neighbor.label);
+                        wtr.write_record([
+                            votes.id.clone(),
+                            votes.label.clone().unwrap_or_default(),
+                            votes.iteration.to_string(),
+                            neighbor.id.clone(),
+                            neighbor.label.clone(),
+                            neighbor.distance.to_string(),
+                            optional(neighbor.af),
+                            neighbor.centrality.to_string(),
+                            optional(neighbor.neighbor_centrality),
+                            neighbor.vote.to_string(),
+                            optional(total.map(|t| t.vote)),
+                            optional(total.map(|t| t.share)),
+                        ])?;
+                    }
+                }
+                wtr.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Label updates are applied after each iteration (synchronous) or immediately
+// for each node in a random order every iteration (asynchronous)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum PropagationUpdate {
+    #[default]
+    Synchronous,
+    Asynchronous,
+}
+impl std::fmt::Display for PropagationUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            PropagationUpdate::Synchronous => "synchronous",
+            PropagationUpdate::Asynchronous => "asynchronous",
+        };
+        write!(f, "{}", output)
+    }
+}
+
 // Function to propagate labels based on weighted voting using the node labels in the graph
 pub fn label_propagation(
     graph: &mut NetviewGraph, 
-    centrality_metric: NodeCentrality,
-    max_iterations: usize,
-    vote_weights: VoteWeights,
-    neighbor_centrality_vote: bool,
-    distance_percent: bool,            // If distance weight in percent e.g. from skani, standardize to 0 - 1
+    config: &LabelConfig,              // Centrality, vote weights, iterations and updates of label propagation
     query_nodes: Option<Vec<String>>,  // Optional subset of nodes by identifiers
-    propagate_on_unlabeled: bool       // Whether to propagate only on nodes without a label (None)
+    propagate_on_unlabeled: bool,      // Whether to propagate only on nodes without a label (None)
+    vote_report: Option<&mut VoteReport>  // Optional vote breakdown of the target nodes
 ) -> NetviewGraph {
-    // Compute centrality using the previously defined function
+    log::info!("Computing node centrality ({})", config.centrality_metric);
+    let centrality = node_centrality(graph, &config.centrality_metric);
 
-    log::info!("Starting label propagation (max iter = {})", max_iterations);
-    log::info!("ANI: {}, AAI: {}, AF: {}, 1-DIST: {}, CENTR: {}", vote_weights.ani, vote_weights.aai, vote_weights.af, vote_weights.weight, vote_weights.centrality);
+    label_propagation_with_centrality(graph, config, &centrality, query_nodes, propagate_on_unlabeled, vote_report)
+}
 
-    log::info!("Computing node centrality ({centrality_metric})");
-    let centrality: HashMap<usize, f64> = match centrality_metric {
-        NodeCentrality::Betweenness => betweenness_centrality(graph, true),
-        NodeCentrality::Degree => degree_centrality(graph, true),
-        NodeCentrality::Closeness => closeness_centrality(graph, true),
-    };
+// Label propagation with precomputed node centrality of the configured metric, which depends
+// only on the graph topology and can be reused when propagating labels on the same graph
+pub fn label_propagation_with_centrality(
+    graph: &mut NetviewGraph, 
+    config: &LabelConfig,
+    centrality: &HashMap<usize, f64>,
+    query_nodes: Option<Vec<String>>,
+    propagate_on_unlabeled: bool,
+    mut vote_report: Option<&mut VoteReport>
+) -> NetviewGraph {
+
+    let (max_iterations, update, seed, vote_weights) = (config.max_iterations, config.update, config.seed, &config.vote_weights);
+    log::info!("Starting label propagation (max iter = {}, update = {}, seed = {})", max_iterations, update, seed);
+    log::info!("ANI: {}, AAI: {}, AF: {}, 1-DIST: {}, CENTR: {}", vote_weights.ani, vote_weights.aai, vote_weights.af, vote_weights.weight, vote_weights.centrality);
 
     // Generate the subset of nodes based on the input options
     let target_nodes: Vec<NodeIndex> = if propagate_on_unlabeled {
@@ -219,109 +601,890 @@ pub fn label_propagation(
 
     log::info!("Targeting {} nodes with label propagation", target_nodes.len());
 
+    // Random but reproducible node order for asynchronous updates
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut node_order = target_nodes.clone();
+    let mut converged = false;
+
     for iter in 0..max_iterations {
-        log::debug!("Starting iteration {} of label propagation.", iter + 1);
-        let mut new_labels = HashMap::new();
-        let mut label_changed = false;  // Track if any label changes
+        log::debug!("Starting iteration {} of label propagation ({update}).", iter + 1);
+        let mut new_labels = Vec::new();
+        let mut changed = 0;
+
+        if update == PropagationUpdate::Asynchronous {
+            node_order.shuffle(&mut rng);
+        }
 
         // Loop through target (all, query) nodes in the graph
-        for node in &target_nodes {
+        for node in &node_order {
 
-            let mut label_votes: HashMap<String, f64> = HashMap::new();
             let node_index = node.index();
-            let node_centrality = centrality[&node_index];
-
-
             log::debug!(
                 "Processing node with index {} and centrality score {:.4}.",
                 node_index,
-                node_centrality
+                centrality[&node_index]
             );
 
-            // Loop through the neighbors of the current node
-            for neighbor in graph.neighbors(*node) {
-
-                let neighbor_label = graph.node_weight(neighbor).unwrap(); // Get the neighbor's NodeLabel
-
-                if let Some(ref neighbor_label_value) = neighbor_label.label {
-
-                    // Find the edge between the current node and the neighbor
-                    let edge = graph.edge_weight(
-                        graph.find_edge(*node, neighbor).expect("Failed to find edge between nodes - it should exist?")
-                    ).unwrap();
-
-                    let ani = edge.ani.unwrap_or(0.0) / 100.0;  // percent -> 0 - 1
-                    let aai = edge.aai.unwrap_or(0.0) / 100.0;  // percent -> 0 - 1
-                    let af = edge.af.unwrap_or(0.0) / 100.0;    // percent -> 0 - 1
-
-
-                    let weight = if distance_percent {
-                        1.0 - (edge.weight / 100.0)  // distance is percent -> similarity
-                    } else {
-                        1.0 - edge.weight            // distance -> similarity
-                    };
-                    
-                    log::debug!(
-                        "Neighbor (index: {}) has label '{}'. Edge weights: ANI = {:.4}, AAI = {:.4}, AF = {:.4}, 1-DIST = {:.4}, CENTR = {:.4}",
-                        neighbor.index(),
-                        neighbor_label_value,
-                        weight,
-                        ani,
-                        aai,
-                        af,
-                        node_centrality
-                    );
-
-                    // Calculate the vote weight for the neighbor's label
-                    let mut vote_weight = (weight * vote_weights.weight)             
-                        + (vote_weights.ani * ani)                   
-                        + (vote_weights.aai * aai)                        
-                        + (vote_weights.af * af)                         
-                        + (vote_weights.centrality * node_centrality);
-                    
-                    // Optionally include neighbor centrality in the vote
-                    if neighbor_centrality_vote {
-                        let neighbor_centrality = centrality[&neighbor.index()]; // Get neighbor's centrality
-                        vote_weight += neighbor_centrality;
-
-                        log::debug!(
-                            "Including neighbor centrality in vote: neighbor_centrality = {:.4}, updated vote weight = {:.4}.",
-                            neighbor_centrality,
-                            vote_weight
-                        );
-                    }
+            let (ranked_votes, neighbor_votes) = node_votes(
+                graph,
+                *node,
+                centrality,
+                vote_weights,
+                config.neighbor_centrality_vote,
+                config.distance_percent,
+                vote_report.is_some()
+            );
 
-                    *label_votes.entry(neighbor_label_value.clone()).or_insert(0.0) += vote_weight;
-                }
+            // Vote shares of the labels in the total vote weight for the report
+            let total_vote: f64 = ranked_votes.iter().map(|(_, vote)| vote).sum();
+            let share = |vote: f64| if total_vote > 0.0 { vote / total_vote } else { 0.0 };
+
+            if let Some(report) = vote_report.as_deref_mut() {
+                report.nodes.insert(node_index, NodeVotes {
+                    id: node_id_or_index(&graph[*node]),
+                    label: ranked_votes.first().map(|(label, _)| label.clone()),
+                    iteration: iter + 1,
+                    totals: ranked_votes.iter().map(|(label, vote)| LabelVote {
+                        label: label.clone(), vote: *vote, share: share(*vote)
+                    }).collect(),
+                    neighbors: neighbor_votes,
+                });
             }
 
-            // Select the label with the highest vote
-            if let Some((best_label, highest_vote)) = label_votes.into_iter().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap()) {
+            let assignment = match &config.hierarchy {
+                Some(hierarchy) => hierarchy.assign(&ranked_votes),
+                None => LabelAssignment::from_votes(&ranked_votes),
+            };
+
+            if let Some(assignment) = assignment {
                 log::debug!(
-                    "Node {} will adopt label '{}' with vote weight {:.4}.",
+                    "Node {} will adopt label '{}' ({:.4} of total vote).",
                     node_index,
-                    best_label,
-                    highest_vote
+                    assignment.label,
+                    assignment.confidence
                 );
-                new_labels.insert(node, best_label);
+
+                match update {
+                    // Asynchronous updates are visible to the next node in this iteration
+                    PropagationUpdate::Asynchronous => {
+                        if update_node_label(graph, *node, assignment) { changed += 1 }
+                    },
+                    PropagationUpdate::Synchronous => new_labels.push((*node, assignment)),
+                }
             }
         }
 
-        // Update the labels for the next iteration directly on the graph
+        // Synchronous updates of the labels for the next iteration directly on the graph
         for (node, new_label) in new_labels {
-            log::debug!("Updating node {} with new label '{}'.", node.index(), new_label);
-            if let Some(node_weight) = graph.node_weight_mut(*node) {
-                node_weight.label = Some(new_label);  // Update the label directly in the graph's NodeLabel
-                label_changed = true;                 // Track if a label changes
-            }
+            if update_node_label(graph, node, new_label) { changed += 1 }
         }
 
-        if !label_changed {
+        log::info!("Label propagation iteration {}: {} labels changed", iter + 1, changed);
+
+        if changed == 0 {
             log::info!("Label propagation converged at iteration {}.", iter + 1);
+            converged = true;
             break;  // Early exit if no label changed
         }
     }
 
+    if !converged {
+        log::warn!("Label propagation did not converge within {} iterations", max_iterations);
+    }
+
     log::info!("Returning graph with updated node labels");
     graph.clone()
-}
\ No newline at end of file
+}
+
+// Label of a single target node from the votes of its labelled neighbors with precomputed 
+// node centrality, as label propagation targeting only this node (one iteration assigns the
+// label, the next iteration does not change it); returns whether a label was assigned
+pub fn propagate_node_label(
+    graph: &mut NetviewGraph, 
+    node: NodeIndex, 
+    centrality: &HashMap<usize, f64>, 
+    config: &LabelConfig
+) -> bool {
+    let (ranked_votes, _) = node_votes(
+        graph, 
+        node, 
+        centrality, 
+        &config.vote_weights, 
+        config.neighbor_centrality_vote, 
+        config.distance_percent, 
+        false
+    );
+    let assignment = match &config.hierarchy {
+        Some(hierarchy) => hierarchy.assign(&ranked_votes),
+        None => LabelAssignment::from_votes(&ranked_votes),
+    };
+    match assignment {
+        Some(assignment) => {
+            update_node_label(graph, node, assignment);
+            true
+        },
+        None => false
+    }
+}
+
+// Summed votes per label (descending) and optionally the individual neighbor votes for a node
+fn node_votes(
+    graph: &NetviewGraph,
+    node: NodeIndex,
+    centrality: &HashMap<usize, f64>,
+    vote_weights: &VoteWeights,
+    neighbor_centrality_vote: bool,
+    distance_percent: bool,
+    collect_neighbors: bool
+) -> (Vec<(String, f64)>, Vec<NeighborVote>) {
+
+    let mut label_votes: HashMap<String, f64> = HashMap::new();
+    let mut neighbor_votes: Vec<NeighborVote> = Vec::new();
+    let node_centrality = centrality[&node.index()];
+
+    // Loop through the neighbors of the current node
+    for neighbor in graph.neighbors(node) {
+
+        let neighbor_label = graph.node_weight(neighbor).unwrap(); // Get the neighbor's NodeLabel
+
+        if let Some(ref neighbor_label_value) = neighbor_label.label {
+
+            // Find the edge between the current node and the neighbor
+            let edge = graph.edge_weight(
+                graph.find_edge(node, neighbor).expect("Failed to find edge between nodes - it should exist?")
+            ).unwrap();
+
+            let ani = edge.ani.unwrap_or(0.0) / 100.0;  // percent -> 0 - 1
+            let aai = edge.aai.unwrap_or(0.0) / 100.0;  // percent -> 0 - 1
+            let af = edge.af.unwrap_or(0.0) / 100.0;    // percent -> 0 - 1
+
+            let weight = if distance_percent {
+                1.0 - (edge.weight / 100.0)  // distance is percent -> similarity
+            } else {
+                1.0 - edge.weight            // distance -> similarity
+            };
+            
+            log::debug!(
+                "Neighbor (index: {}) has label '{}'. Edge weights: 1-DIST = {:.4}, ANI = {:.4}, AAI = {:.4}, AF = {:.4}, CENTR = {:.4}",
+                neighbor.index(),
+                neighbor_label_value,
+                weight,
+                ani,
+                aai,
+                af,
+                node_centrality
+            );
+
+            // Calculate the vote weight for the neighbor's label
+            let mut vote_weight = (weight * vote_weights.weight)             
+                + (vote_weights.ani * ani)                   
+                + (vote_weights.aai * aai)                        
+                + (vote_weights.af * af)                         
+                + (vote_weights.centrality * node_centrality);
+            
+            // Optionally include neighbor centrality in the vote
+            let mut included_neighbor_centrality = None;
+            if neighbor_centrality_vote {
+                let neighbor_centrality = centrality[&neighbor.index()]; // Get neighbor's centrality
+                vote_weight += neighbor_centrality;
+                included_neighbor_centrality = Some(neighbor_centrality);
+
+                log::debug!(
+                    "Including neighbor centrality in vote: neighbor_centrality = {:.4}, updated vote weight = {:.4}.",
+                    neighbor_centrality,
+                    vote_weight
+                );
+            }
+
+            *label_votes.entry(neighbor_label_value.clone()).or_insert(0.0) += vote_weight;
+
+            if collect_neighbors {
+                neighbor_votes.push(NeighborVote {
+                    id: node_id_or_index(neighbor_label),
+                    label: neighbor_label_value.clone(),
+                    distance: edge.weight,
+                    af: edge.af,
+                    centrality: node_centrality,
+                    neighbor_centrality: included_neighbor_centrality,
+                    vote: vote_weight,
+                });
+            }
+        }
+    }
+
+    let mut ranked_votes: Vec<(String, f64)> = label_votes.into_iter().collect();
+    ranked_votes.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    neighbor_votes.sort_by(|a, b| b.vote.total_cmp(&a.vote));
+
+    (ranked_votes, neighbor_votes)
+}
+
+// Label selected from the votes of a node with its share of the total vote
+struct LabelAssignment {
+    label: String,
+    confidence: f64,
+    runner_up: Option<(String, f64)>,
+    rank_confidence: Option<Vec<f64>>,  // Vote share of each rank of a hierarchical label
+}
+impl LabelAssignment {
+    // Label with the highest vote, votes are sorted in descending order
+    fn from_votes(ranked_votes: &[(String, f64)]) -> Option<Self> {
+        let total_vote: f64 = ranked_votes.iter().map(|(_, vote)| vote).sum();
+        let share = |vote: f64| if total_vote > 0.0 { vote / total_vote } else { 0.0 };
+
+        ranked_votes.first().map(|(label, vote)| LabelAssignment {
+            label: label.clone(),
+            confidence: share(*vote),
+            runner_up: ranked_votes.get(1).map(|(label, vote)| (label.clone(), share(*vote))),
+            rank_confidence: None,
+        })
+    }
+}
+
+// Write a propagated label with its vote shares to a node, returns whether the label changed
+fn update_node_label(graph: &mut NetviewGraph, node: NodeIndex, assignment: LabelAssignment) -> bool {
+    log::debug!("Updating node {} with new label '{}'.", node.index(), assignment.label);
+    match graph.node_weight_mut(node) {
+        Some(node_weight) => {
+            let changed = node_weight.label.as_ref() != Some(&assignment.label);
+            node_weight.label = Some(assignment.label);  // Update the label directly in the graph's NodeLabel
+            node_weight.label_confidence = assignment.confidence;
+            (node_weight.runner_up_label, node_weight.runner_up_confidence) = match assignment.runner_up {
+                Some((label, confidence)) => (Some(label), confidence),
+                None => (None, 0.0)
+            };
+            node_weight.rank_confidence = assignment.rank_confidence;
+            changed
+        },
+        None => false
+    }
+}
+
+// Hierarchical labels such as taxonomic lineages (d__Bacteria;p__...;s__...) or HierBAPS levels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HierarchyConfig {
+    pub separator: String,   // Separator of ranks in the label
+    pub min_support: f64,    // Minimum share of the total vote at a rank to assign the rank
+}
+impl Default for HierarchyConfig {
+    fn default() -> Self {
+        Self {
+            separator: ";".to_string(),
+            min_support: 0.5,
+        }
+    }
+}
+impl HierarchyConfig {
+    pub fn ranks<'a>(&self, label: &'a str) -> Vec<&'a str> {
+        label.split(self.separator.as_str())
+            .map(str::trim)
+            .filter(|rank| !rank.is_empty())
+            .collect()
+    }
+    // Assign the lineage rank by rank, at each rank votes are summed over the labels 
+    // descending from the lineage assigned so far and the lineage is truncated at the 
+    // first rank where the best taxon has insufficient support
+    fn assign(&self, ranked_votes: &[(String, f64)]) -> Option<LabelAssignment> {
+        let total_vote: f64 = ranked_votes.iter().map(|(_, vote)| vote).sum();
+        if total_vote <= 0.0 {
+            return None
+        }
+        let lineages: Vec<(Vec<&str>, f64)> = ranked_votes.iter()
+            .map(|(label, vote)| (self.ranks(label), *vote))
+            .collect();
+
+        let mut lineage: Vec<&str> = Vec::new();
+        let mut rank_confidence = Vec::new();
+        let mut runner_up = None;
+
+        loop {
+            let rank = lineage.len();
+            let mut taxon_votes: BTreeMap<&str, f64> = BTreeMap::new();
+            for (ranks, vote) in &lineages {
+                if ranks.len() > rank && ranks[..rank] == lineage[..] {
+                    *taxon_votes.entry(ranks[rank]).or_insert(0.0) += vote;
+                }
+            }
+            let mut taxa: Vec<(&str, f64)> = taxon_votes.into_iter().collect();
+            taxa.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            let Some((taxon, vote)) = taxa.first().copied() else { break };
+            let support = vote / total_vote;
+            if support < self.min_support {
+                log::debug!("Rank {} taxon '{}' has insufficient support ({:.4})", rank + 1, taxon, support);
+                break
+            }
+            // Runner-up is the best alternative at the deepest rank with alternatives
+            if let Some((alternative, vote)) = taxa.get(1) {
+                let mut alternative_lineage = lineage.clone();
+                alternative_lineage.push(alternative);
+                runner_up = Some((alternative_lineage.join(&self.separator), vote / total_vote));
+            }
+            lineage.push(taxon);
+            rank_confidence.push(support);
+        }
+
+        (!lineage.is_empty()).then(|| LabelAssignment {
+            label: lineage.join(&self.separator),
+            confidence: *rank_confidence.last().unwrap(),
+            runner_up,
+            rank_confidence: Some(rank_confidence),
+        })
+    }
+}
+
+// Label spreading (Zhou et al. 2004) on the normalized similarity graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadingConfig {
+    pub alpha: f64,             // Weight of the neighbor information against the initial labels (0 - 1)
+    pub clamp: bool,            // Reset labelled nodes to their labels in each iteration (hard clamping)
+    pub max_iterations: usize,
+    pub tolerance: f64,         // Convergence when the maximum change of a label score is below this value
+    pub distance_percent: bool, // If distance weight in percent e.g. from skani, standardize to 0 - 1
+}
+impl Default for SpreadingConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 0.9,
+            clamp: true,
+            max_iterations: 1000,
+            tolerance: 1e-6,
+            distance_percent: true,
+        }
+    }
+}
+
+// Probability distributions over labels for all nodes from label spreading
+#[derive(Debug, Clone)]
+pub struct LabelDistribution {
+    pub ids: Vec<String>,
+    pub labels: Vec<String>,               // Sorted label names, columns of the probabilities
+    pub probabilities: Vec<Vec<f64>>,      // Rows sum to one, or zero if no label reaches the node
+    pub iterations: usize,
+}
+impl LabelDistribution {
+    // Most probable label and its probability, if any label reached the node
+    pub fn best(&self, node: usize) -> Option<(&str, f64)> {
+        self.ranked(node).first().copied()
+    }
+    // Labels with non-zero probability in descending order
+    pub fn ranked(&self, node: usize) -> Vec<(&str, f64)> {
+        let mut ranked: Vec<(&str, f64)> = self.labels.iter()
+            .zip(&self.probabilities[node])
+            .filter(|(_, p)| **p > 0.0)
+            .map(|(label, p)| (label.as_str(), *p))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        ranked
+    }
+    // Set the most probable label, its probability as confidence, the runner-up and the distribution on the graph
+    pub fn apply(&self, graph: &mut NetviewGraph) {
+        for (i, node) in graph.node_weights_mut().enumerate() {
+            let ranked = self.ranked(i);
+            node.label = ranked.first().map(|(label, _)| label.to_string());
+            node.label_confidence = ranked.first().map_or(0.0, |(_, p)| *p);
+            node.runner_up_label = ranked.get(1).map(|(label, _)| label.to_string());
+            node.runner_up_confidence = ranked.get(1).map_or(0.0, |(_, p)| *p);
+            node.label_probabilities = Some(
+                ranked.into_iter().map(|(label, p)| (label.to_string(), p)).collect()
+            );
+        }
+    }
+    // Write a table with columns id, label, label_confidence and one column per label
+    pub fn write<P: AsRef<Path>>(&self, path: P, tsv: bool) -> Result<(), NetviewError> {
+        let writer = get_file_writer(path.as_ref())?;
+        let mut wtr = WriterBuilder::new().delimiter(if tsv { b'\t' } else { b',' }).from_writer(writer);
+
+        let mut header = vec!["id".to_string(), "label".to_string(), "label_confidence".to_string()];
+        header.extend(self.labels.iter().cloned());
+        wtr.write_record(&header)?;
+
+        for (i, id) in self.ids.iter().enumerate() {
+            let (label, confidence) = self.best(i).map_or((String::new(), 0.0), |(l, p)| (l.to_string(), p));
+            let mut record = vec![id.clone(), label, confidence.to_string()];
+            record.extend(self.probabilities[i].iter().map(|p| p.to_string()));
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+// Spread the labels of labelled nodes over the graph with F <- alpha * S * F + (1 - alpha) * Y,
+// where S = D^-1/2 W D^-1/2 is the symmetrically normalized similarity matrix and Y the initial labels
+pub fn label_spreading(graph: &NetviewGraph, config: &SpreadingConfig) -> Result<LabelDistribution, NetviewError> {
+
+    if !(0.0..1.0).contains(&config.alpha) {
+        return Err(NetviewError::LabelSpreadingConfigError(format!("alpha must be in [0, 1), got {}", config.alpha)));
+    }
+
+    let n = graph.node_count();
+    let labels: Vec<String> = graph.node_weights()
+        .filter_map(|node| node.label.clone())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect();
+    let label_index: HashMap<&str, usize> = labels.iter().enumerate().map(|(i, l)| (l.as_str(), i)).collect();
+
+    log::info!("Label spreading of {} labels on {} nodes (alpha = {}, clamp = {})", labels.len(), n, config.alpha, config.clamp);
+
+    // Initial one-hot label matrix
+    let initial: Vec<Option<usize>> = graph.node_weights()
+        .map(|node| node.label.as_deref().map(|label| label_index[label]))
+        .collect();
+
+    // Similarity weighted adjacency normalized by node strength
+    let similarity = |distance: f64| {
+        let similarity = if config.distance_percent { 1.0 - distance / 100.0 } else { 1.0 - distance };
+        similarity.max(0.0)
+    };
+    let mut neighbors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    for edge in graph.edge_indices() {
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        let w = similarity(graph[edge].weight);
+        if a != b && w > 0.0 {
+            neighbors[a.index()].push((b.index(), w));
+            neighbors[b.index()].push((a.index(), w));
+        }
+    }
+    let strength: Vec<f64> = neighbors.iter().map(|nb| nb.iter().map(|(_, w)| w).sum()).collect();
+    for (i, nb) in neighbors.iter_mut().enumerate() {
+        for (j, w) in nb.iter_mut() {
+            *w /= (strength[i] * strength[*j]).sqrt();
+        }
+    }
+
+    let one_hot = |i: usize| -> Vec<f64> {
+        let mut row = vec![0.0; labels.len()];
+        if let Some(l) = initial[i] { row[l] = 1.0 }
+        row
+    };
+    let y: Vec<Vec<f64>> = (0..n).map(one_hot).collect();
+    let mut f = y.clone();
+    let mut iterations = 0;
+
+    for iter in 0..config.max_iterations {
+        iterations = iter + 1;
+        let mut max_change: f64 = 0.0;
+        let mut next = vec![vec![0.0; labels.len()]; n];
+
+        for (i, row) in next.iter_mut().enumerate() {
+            if config.clamp && initial[i].is_some() {
+                row.clone_from(&y[i]);
+                continue;
+            }
+            for (j, w) in &neighbors[i] {
+                for (value, neighbor_value) in row.iter_mut().zip(&f[*j]) {
+                    *value += config.alpha * w * neighbor_value;
+                }
+            }
+            for (value, initial_value) in row.iter_mut().zip(&y[i]) {
+                *value += (1.0 - config.alpha) * initial_value;
+            }
+            for (value, previous) in row.iter().zip(&f[i]) {
+                max_change = max_change.max((value - previous).abs());
+            }
+        }
+        f = next;
+
+        log::debug!("Label spreading iteration {}: maximum change {:.3e}", iterations, max_change);
+        if max_change < config.tolerance {
+            log::info!("Label spreading converged at iteration {}", iterations);
+            break;
+        }
+    }
+
+    // Normalize label scores of each node to probabilities
+    let probabilities = f.into_iter().map(|row| {
+        let total: f64 = row.iter().sum();
+        if total > 0.0 { row.into_iter().map(|v| v / total).collect() } else { row }
+    }).collect();
+
+    Ok(LabelDistribution {
+        ids: graph.node_weights().map(node_id_or_index).collect(),
+        labels,
+        probabilities,
+        iterations
+    })
+}
+
+// Thresholds below which a query is assigned the novel label instead of the predicted label
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbstentionConfig {
+    pub label: String,                 // Label assigned to novel or unassigned queries
+    pub min_confidence: Option<f64>,   // Minimum vote share (or probability) of the predicted label
+    pub max_distance: Option<f64>,     // Maximum distance to the nearest labelled neighbor (units of the distance matrix)
+    pub min_af: Option<f64>,           // Minimum alignment fraction (percent) with the nearest labelled neighbor
+}
+impl Default for AbstentionConfig {
+    fn default() -> Self {
+        Self {
+            label: "novel".to_string(),
+            min_confidence: None,
+            max_distance: None,
+            min_af: None,
+        }
+    }
+}
+impl AbstentionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.min_confidence.is_some() || self.max_distance.is_some() || self.min_af.is_some()
+    }
+}
+
+// Assign the novel label to query nodes whose prediction falls below the abstention thresholds, 
+// the nearest labelled neighbor is the closest neighbor with a label that is not itself a query
+pub fn abstain_novel(graph: &mut NetviewGraph, query_ids: &[String], config: &AbstentionConfig) -> usize {
+
+    let query_ids: HashSet<&String> = query_ids.iter().collect();
+    let queries: HashSet<NodeIndex> = graph.node_indices()
+        .filter(|node| query_ids.contains(&node_id_or_index(&graph[*node])))
+        .collect();
+
+    let mut abstentions: Vec<(NodeIndex, String)> = Vec::new();
+    for node in graph.node_indices().filter(|node| queries.contains(node)) {
+
+        let node_label = &graph[node];
+        let mut reasons = Vec::new();
+
+        if let Some(min_confidence) = config.min_confidence {
+            if node_label.label.is_some() && node_label.label_confidence < min_confidence {
+                reasons.push(format!("label_confidence {:.4} < {}", node_label.label_confidence, min_confidence));
+            }
+        }
+
+        if config.max_distance.is_some() || config.min_af.is_some() {
+            let nearest = graph.edges(node)
+                .filter_map(|edge| {
+                    let neighbor = if edge.source() == node { edge.target() } else { edge.source() };
+                    (!queries.contains(&neighbor) && graph[neighbor].label.is_some()).then_some(edge.weight())
+                })
+                .min_by(|a, b| a.weight.total_cmp(&b.weight));
+
+            match nearest {
+                None => reasons.push("no labelled neighbor".to_string()),
+                Some(edge) => {
+                    if let Some(max_distance) = config.max_distance {
+                        if edge.weight > max_distance {
+                            reasons.push(format!("nearest labelled distance {:.4} > {}", edge.weight, max_distance));
+                        }
+                    }
+                    if let (Some(min_af), Some(af)) = (config.min_af, edge.af) {
+                        if af < min_af {
+                            reasons.push(format!("nearest labelled AF {:.4} < {}", af, min_af));
+                        }
+                    }
+                }
+            }
+        }
+
+        if !reasons.is_empty() {
+            let predicted = node_label.label.as_deref().unwrap_or("None");
+            abstentions.push((node, format!("{} (predicted '{}')", reasons.join("; "), predicted)));
+        }
+    }
+
+    log::info!("Abstaining from label prediction for {} of {} queries", abstentions.len(), queries.len());
+
+    let abstained = abstentions.len();
+    for (node, reason) in abstentions {
+        log::debug!("Node {} is labelled as '{}': {}", node.index(), config.label, reason);
+        let node_label = &mut graph[node];
+        node_label.label = Some(config.label.clone());
+        node_label.abstention = Some(reason);
+    }
+    abstained
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mknn::convert_to_graph;
+
+    // Node 0 is unlabelled with two neighbors labelled 'A' and one neighbor labelled 'B'
+    fn star_graph() -> NetviewGraph {
+        let mnn = vec![vec![1, 2, 3], vec![0], vec![0], vec![0]];
+        let dist = vec![vec![10.0; 4]; 4];
+        let ids = Some(vec!["q".to_string(), "a1".to_string(), "a2".to_string(), "b1".to_string()]);
+        let mut graph = convert_to_graph(&mnn, Some(&dist), None, ids, None).unwrap();
+        label_nodes(&mut graph, vec![None, Some("A".to_string()), Some("A".to_string()), Some("B".to_string())]).unwrap();
+        graph
+    }
+
+    #[test]
+    fn propagation_sets_confidence_and_runner_up() {
+        let mut graph = star_graph();
+        let weights = VoteWeights { centrality: 0.0, weight: 1.0, af: 0.0, ani: 0.0, aai: 0.0 };
+        label_propagation(&mut graph, &LabelConfig { max_iterations: 1, vote_weights: weights, ..Default::default() }, None, true, None);
+
+        let node = &graph[NodeIndex::new(0)];
+        assert_eq!(node.label.as_deref(), Some("A"));
+        assert!((node.label_confidence - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(node.runner_up_label.as_deref(), Some("B"));
+        assert!((node.runner_up_confidence - 1.0 / 3.0).abs() < 1e-12);
+
+        // Known labels are certain
+        assert_eq!(graph[NodeIndex::new(1)].label_confidence, 1.0);
+        assert_eq!(graph[NodeIndex::new(1)].runner_up_label, None);
+    }
+
+    #[test]
+    fn graph_labels_include_confidence() {
+        let mut graph = star_graph();
+        label_propagation(&mut graph, &LabelConfig { max_iterations: 1, ..Default::default() }, None, true, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("labels.csv");
+        write_graph_labels_to_file(&graph, &path, Some(vec!["q".to_string()]), false).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut lines = content.lines();
+        assert_eq!(lines.next(), Some("id,label,label_confidence,runner_up_label,runner_up_confidence,rank_confidence,abstention"));
+        assert!(lines.next().unwrap().starts_with("q,A,0."));
+
+        // Prediction tables can be read back as labels
+        assert_eq!(read_labels_from_file(&path, false).unwrap()[0].label.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn vote_report_lists_neighbor_votes() {
+        let mut graph = star_graph();
+        let weights = VoteWeights { centrality: 0.0, weight: 1.0, af: 0.0, ani: 0.0, aai: 0.0 };
+        let mut report = VoteReport::new();
+        label_propagation(&mut graph, &LabelConfig { max_iterations: 1, vote_weights: weights, ..Default::default() }, None, true, Some(&mut report));
+
+        assert_eq!(report.nodes.len(), 1);
+        let votes = &report.nodes[&0];
+        assert_eq!(votes.id, "q");
+        assert_eq!(votes.label.as_deref(), Some("A"));
+        assert_eq!(votes.neighbors.len(), 3);
+        assert_eq!(votes.totals[0].label, "A");
+        assert!((votes.totals[0].vote - 1.8).abs() < 1e-12);
+        assert!((votes.totals[1].share - 1.0 / 3.0).abs() < 1e-12);
+
+        let dir = tempfile::tempdir().unwrap();
+        let tsv = dir.path().join("votes.tsv");
+        report.write(&tsv, &VoteReportFormat::Tsv).unwrap();
+        let content = std::fs::read_to_string(&tsv).unwrap();
+        assert_eq!(content.lines().count(), 4);
+        assert!(content.lines().skip(1).all(|line| line.starts_with("q\tA\t1\t")));
+        assert!(content.lines().any(|line| line.contains("\tb1\tB\t10\t")));
+
+        let jsonl = dir.path().join("votes.jsonl");
+        report.write(&jsonl, &VoteReportFormat::Jsonl).unwrap();
+        let line = std::fs::read_to_string(&jsonl).unwrap();
+        let parsed: NodeVotes = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(parsed.neighbors.len(), 3);
+
+        // Format from the file extension without compression suffix
+        assert!(matches!(VoteReportFormat::from_path(Path::new("votes.JSONL.gz")), VoteReportFormat::Jsonl));
+        assert!(matches!(VoteReportFormat::from_path(Path::new("votes.jsonish.tsv")), VoteReportFormat::Tsv));
+        assert!(matches!(VoteReportFormat::from_path(Path::new("run.json/votes.tsv")), VoteReportFormat::Tsv));
+    }
+
+    // Chain of unlabelled nodes between a node labelled 'A' and a node labelled 'B'
+    fn chain_graph(n: usize) -> NetviewGraph {
+        let mnn: Vec<Vec<usize>> = (0..n).map(|i| {
+            let mut neighbors = Vec::new();
+            if i > 0 { neighbors.push(i - 1) }
+            if i + 1 < n { neighbors.push(i + 1) }
+            neighbors
+        }).collect();
+        let dist = vec![vec![10.0; n]; n];
+        let mut graph = convert_to_graph(&mnn, Some(&dist), None, None, None).unwrap();
+        let mut labels = vec![None; n];
+        labels[0] = Some("A".to_string());
+        labels[n - 1] = Some("B".to_string());
+        label_nodes(&mut graph, labels).unwrap();
+        graph
+    }
+
+    #[test]
+    fn propagation_converges_when_labels_are_stable() {
+        let mut graph = star_graph();
+        let mut report = VoteReport::new();
+        label_propagation(&mut graph, &LabelConfig { max_iterations: 20, ..Default::default() }, None, true, Some(&mut report));
+        
+        // Label is assigned in the first iteration and unchanged in the second
+        assert_eq!(report.nodes[&0].iteration, 2);
+    }
+
+    #[test]
+    fn asynchronous_propagation_is_seeded() {
+        let run = |seed: u64| {
+            let mut graph = chain_graph(12);
+            let mut report = VoteReport::new();
+            label_propagation(&mut graph, &LabelConfig { max_iterations: 50, update: PropagationUpdate::Asynchronous, seed, ..Default::default() }, None, true, Some(&mut report));
+            let labels: Vec<Option<String>> = graph.node_weights().map(|n| n.label.clone()).collect();
+            let iterations = report.nodes.values().map(|v| v.iteration).max().unwrap();
+            (labels, iterations)
+        };
+        let (labels, iterations) = run(7);
+        assert_eq!(run(7), (labels.clone(), iterations));
+        assert!(iterations < 50);
+        assert!(labels.iter().all(|label| label.is_some()));
+        assert_eq!(labels[0].as_deref(), Some("A"));
+        assert_eq!(labels[11].as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn label_spreading_probabilities() {
+        let graph = chain_graph(5);
+        let distribution = label_spreading(&graph, &SpreadingConfig::default()).unwrap();
+
+        assert_eq!(distribution.labels, vec!["A", "B"]);
+        for row in &distribution.probabilities {
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+        // Clamped labelled nodes keep their labels, the middle node is undecided
+        assert_eq!(distribution.probabilities[0], vec![1.0, 0.0]);
+        assert_eq!(distribution.best(1).unwrap().0, "A");
+        assert_eq!(distribution.best(3).unwrap().0, "B");
+        assert!((distribution.probabilities[2][0] - 0.5).abs() < 1e-6);
+
+        let mut labelled = graph.clone();
+        distribution.apply(&mut labelled);
+        let node = &labelled[NodeIndex::new(1)];
+        assert_eq!(node.label.as_deref(), Some("A"));
+        assert!(node.label_confidence > 0.5 && node.label_confidence < 1.0);
+        assert_eq!(node.runner_up_label.as_deref(), Some("B"));
+        assert_eq!(node.label_probabilities.as_ref().unwrap().len(), 2);
+
+        // Without clamping the labelled nodes are influenced by their neighbors
+        let soft = label_spreading(&graph, &SpreadingConfig { clamp: false, ..Default::default() }).unwrap();
+        assert!(soft.probabilities[0][0] < 1.0 && soft.probabilities[0][0] > 0.5);
+
+        assert!(label_spreading(&graph, &SpreadingConfig { alpha: 1.0, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn abstention_assigns_novel_label() {
+        let weights = VoteWeights { centrality: 0.0, weight: 1.0, af: 0.0, ani: 0.0, aai: 0.0 };
+        let predicted = || {
+            let mut graph = star_graph();
+            label_propagation(&mut graph, &LabelConfig { max_iterations: 1, vote_weights: weights.clone(), ..Default::default() }, None, true, None);
+            graph
+        };
+        let queries = vec!["q".to_string()];
+
+        // Vote share of 2/3 and nearest labelled distance of 10 pass the thresholds
+        let mut graph = predicted();
+        let config = AbstentionConfig { min_confidence: Some(0.6), max_distance: Some(10.0), ..Default::default() };
+        assert_eq!(abstain_novel(&mut graph, &queries, &config), 0);
+        assert_eq!(graph[NodeIndex::new(0)].label.as_deref(), Some("A"));
+
+        let mut graph = predicted();
+        let config = AbstentionConfig { min_confidence: Some(0.7), max_distance: Some(5.0), ..Default::default() };
+        assert_eq!(abstain_novel(&mut graph, &queries, &config), 1);
+        let node = &graph[NodeIndex::new(0)];
+        assert_eq!(node.label.as_deref(), Some("novel"));
+        let reason = node.abstention.as_deref().unwrap();
+        assert!(reason.contains("label_confidence") && reason.contains("nearest labelled distance") && reason.contains("'A'"));
+
+        // Reference nodes are not subject to abstention
+        assert_eq!(graph[NodeIndex::new(1)].abstention, None);
+    }
+
+    #[test]
+    fn hierarchical_labels_truncate_at_supported_rank() {
+        let hierarchy = HierarchyConfig::default();
+        let votes = vec![
+            ("d__B;p__X;s__1".to_string(), 1.0),
+            ("d__B;p__X;s__2".to_string(), 1.0),
+            ("d__B;p__Y;s__3".to_string(), 1.0),
+        ];
+        let assignment = hierarchy.assign(&votes).unwrap();
+        assert_eq!(assignment.label, "d__B;p__X");
+        assert!((assignment.confidence - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(assignment.rank_confidence.as_ref().unwrap().len(), 2);
+        assert_eq!(assignment.runner_up.as_ref().unwrap().0, "d__B;p__Y");
+
+        // Propagation on the graph assigns the truncated lineage with confidences per rank
+        let mut graph = star_graph();
+        let lineages = vec![None, Some(votes[0].0.clone()), Some(votes[1].0.clone()), Some(votes[2].0.clone())];
+        label_nodes(&mut graph, lineages).unwrap();
+        let weights = VoteWeights { centrality: 0.0, weight: 1.0, af: 0.0, ani: 0.0, aai: 0.0 };
+        label_propagation(&mut graph, &LabelConfig { max_iterations: 1, vote_weights: weights, hierarchy: Some(hierarchy.clone()), ..Default::default() }, None, true, None);
+
+        let node = &graph[NodeIndex::new(0)];
+        assert_eq!(node.label.as_deref(), Some("d__B;p__X"));
+        assert_eq!(node.rank_confidence.as_ref().unwrap()[0], 1.0);
+
+        // Insufficient support at the first rank leaves the node unlabelled
+        let strict = HierarchyConfig { min_support: 0.9, ..Default::default() };
+        assert!(strict.assign(&[("A".to_string(), 1.0), ("B".to_string(), 1.0)]).is_none());
+    }
+
+    #[test]
+    fn label_table_selects_columns_and_delimiter() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Delimiters are detected from the header when not given by extension
+        let semicolon = dir.path().join("labels.txt");
+        std::fs::write(&semicolon, "name;country;serotype\ns1;AU;1\ns2;;2\n").unwrap();
+        let columns = LabelColumns {
+            id: "name".to_string(),
+            labels: vec!["serotype".to_string(), "country".to_string()],
+            ..Default::default()
+        };
+        let table = read_label_table(&semicolon, &columns).unwrap();
+        assert_eq!(table.ids, vec!["s1", "s2"]);
+        assert_eq!(table.values[0], vec![Some("1".to_string()), Some("2".to_string())]);
+        assert_eq!(table.labels(1)[1].label, None);
+
+        // Combined labels for stratification are unlabelled if any column is empty
+        let strata = table.strata();
+        assert_eq!(strata[0].label, Some("1|AU".to_string()));
+        assert_eq!(strata[1].label, None);
+
+        let tab = dir.path().join("labels.tsv");
+        std::fs::write(&tab, "id\tlabel\ns1\tA\n").unwrap();
+        assert_eq!(read_label_table(&tab, &LabelColumns::default()).unwrap().values[0], vec![Some("A".to_string())]);
+
+        // Extensions are matched on the file name without compression suffix
+        let auto = LabelDelimiter::Auto;
+        assert_eq!(auto.resolve(Path::new("labels.TSV.gz"), "id,label"), b'\t');
+        assert_eq!(auto.resolve(Path::new("data.tsv/labels.csv"), "id,label"), b',');
+        assert_eq!(auto.resolve(Path::new("labels.tabular.csv"), "id;label"), b';');
+
+        let missing = LabelColumns { labels: vec!["host".to_string()], ..columns };
+        assert!(matches!(read_label_table(&semicolon, &missing), Err(NetviewError::LabelColumnError(_))));
+
+        // Subsets of the table are written in the given identifier order
+        let output = dir.path().join("subset.csv");
+        table.write(&output, Some(&["s2".to_string(), "s1".to_string()]), false).unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "name,serotype,country\ns2,2,\ns1,1,AU\n");
+    }
+
+    #[test]
+    fn label_columns_are_written_per_column() {
+        assert_eq!(
+            label_column_path(Path::new("out/votes.tsv.gz"), "serotype"), 
+            PathBuf::from("out/votes.serotype.tsv.gz")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("labels.csv");
+        let mut country = star_graph();
+        country[NodeIndex::new(0)].rank_confidence = Some(vec![0.9, 0.5]);
+        country[NodeIndex::new(0)].abstention = Some("novel".to_string());
+        let predictions = vec![
+            ("serotype".to_string(), label_propagation(&mut star_graph(), &LabelConfig { max_iterations: 10, ..Default::default() }, None, true, None)),
+            ("country".to_string(), country),
+        ];
+        write_label_columns_to_file(&predictions, &path, None, false).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut lines = content.lines();
+        assert_eq!(
+            lines.next().unwrap(), 
+            "id,serotype,serotype_confidence,serotype_runner_up_label,serotype_runner_up_confidence,\
+            serotype_rank_confidence,serotype_abstention,\
+            country,country_confidence,country_runner_up_label,country_runner_up_confidence,\
+            country_rank_confidence,country_abstention"
+        );
+        let line = lines.next().unwrap();
+        assert!(line.starts_with("q,A,"));
+        assert!(line.ends_with(",0.9000;0.5000,novel"));
+    }
+}
@@ -10,10 +10,22 @@ pub mod centrality;
 pub mod validation;
 pub mod config;
 pub mod derep;
+pub mod ordination;
+pub mod community;
+pub mod compare;
+pub mod evaluation;
+pub mod tune;
+pub mod metadata;
 
 #[cfg(feature = "plot")]
 pub mod plot;
 
+#[cfg(feature = "columnar")]
+pub mod columnar;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
 pub mod prelude {
     pub use crate::netview::*;
     pub use crate::dist::*;
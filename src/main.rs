@@ -4,21 +4,31 @@
 
 use netview::centrality::NodeCentrality;
 
-use netview::config::NetviewConfig;
+use netview::config::{GraphConfig, LabelConfig, NetviewConfig};
 use netview::derep::Dereplicator;
 #[cfg(feature = "plot")]
 use netview::plot::plot_test;
+#[cfg(feature = "columnar")]
+use netview::columnar::{write_graph_tables, write_matrix_table};
+#[cfg(feature = "sqlite")]
+use netview::sqlite::SqliteExporter;
 
-use netview::dist::{skani_distance_matrix, write_ids, write_matrix_to_file};
-use netview::label::{read_labels_from_file, VoteWeights};
-use netview::mknn::write_graph_to_file;
+use netview::dist::{build_tree, parse_identifiers, parse_input_matrix, skani_distance_matrix, write_ids, write_matrix_to_file, write_newick_to_file};
+use netview::metadata::{filter_nodes, read_labels_with_metadata, read_node_metadata};
+use netview::label::{add_label_column_attributes, label_column_path, read_label_table, read_labels_from_file, write_label_columns_to_file, AbstentionConfig, HierarchyConfig, LabelColumns, SpreadingConfig, VoteReport, VoteReportFormat, VoteWeights};
+use netview::mknn::{mknn_graphs, write_dot_to_file, write_edgelist_to_file, write_graph_to_file, write_matrix_market_to_file, DotConfig, EdgeAttribute, EdgeListConfig, GraphFormat, GraphJson, GraphMetadata};
 use netview::log::init_logger;
 
 use netview::terminal::{App, Commands};
 use netview::error::NetviewError;
-use netview::netview::Netview;
+use netview::utils::is_stdio;
+use netview::netview::{Netview, NodeLabel, PredictOptions};
+use netview::compare::{compare_partitions, read_partition};
+use netview::community::{consensus_communities, detect_communities, CommunityConfig, ConsensusConfig};
+use netview::ordination::{pcoa, spectral_embedding, EigenConfig};
 
-use netview::validation::CrossFoldValidation;
+use netview::validation::{read_folds, CrossFoldValidation};
+use netview::tune::{label_nodes_by_id, tune_label_propagation, write_tune_results, TuneGrid, TuneValidation};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use clap::Parser;
 
@@ -34,34 +44,104 @@ pub fn main() -> Result<(), NetviewError> {
             
             let netview = Netview::new(NetviewConfig::default());
 
-            args.k.par_iter().for_each(|k| {
+            let mut metadata = GraphMetadata::new()
+                .distance_abstraction("euclidean")
+                .edge_threshold(args.edge_threshold)
+                .input("dist", &args.dist)?;
+            if let Some(path) = &args.afrac {
+                metadata = metadata.input("afrac", path)?;
+            }
+            if let Some(path) = &args.ids {
+                metadata = metadata.input("ids", path)?;
+            }
+
+            // Graphs of multiple k are written to separate files
+            if args.k.len() > 1 && (is_stdio(&args.output) || args.spectral.as_deref().is_some_and(is_stdio)) {
+                return Err(NetviewError::WriteError(
+                    "graphs of multiple k cannot be written to stdout ('-'), use an output file".to_string()
+                ))
+            }
+
+            // Inputs are read once (e.g. from stdin) and the distance abstraction is shared by all k
+            log::info!("Reading distance matrix: {}", args.dist.display());
+            let distance = parse_input_matrix(&args.dist, false)?;
+            let af = match &args.afrac {
+                Some(path) => Some(parse_input_matrix(path, false)?),
+                None => None
+            };
+            let ids = match &args.ids {
+                Some(path) => Some(parse_identifiers(path)?),
+                None => None
+            };
+            let graphs = mknn_graphs(
+                &distance, 
+                af.as_ref(), 
+                ids, 
+                &args.k, 
+                args.threads, 
+                args.chunk_size, 
+                args.edge_threshold
+            )?;
+
+            graphs.par_iter().try_for_each(|(k, graph)| -> Result<(), NetviewError> {
 
-                log::info!("Computing mutual nearest neighbor graph at k = {k}");
-                
-                let graph = netview.graph_from_files(
-                    &args.dist, 
-                    *k, 
-                    args.afrac.clone(),
-                    args.ids.clone(),
-                    false,
-                    args.threads,
-                    args.chunk_size,
-                    args.edge_threshold
-                ).expect(&format!("Failed to create graph (k = {k})"));
-                
                 let output = if args.k.len() == 1 {
                     args.output.clone()
                 } else {
                     args.output.with_extension(format!("k{k}.{}", args.format))
                 };
 
-                write_graph_to_file(
-                    &graph, 
-                    &output, 
-                    &args.format, 
-                    args.weights
-                ).expect(&format!("Failed to write graph (k = {k})"));
-            });
+                match args.format {
+                    GraphFormat::Json => {
+                        log::info!("Writing graph to: {}", output.display());
+                        GraphJson::from(graph)
+                            .with_metadata(metadata.clone().k(*k))
+                            .write(&output)
+                    },
+                    GraphFormat::Dot => {
+                        let config = DotConfig {
+                            tooltip: args.dot_tooltip,
+                            edge_attributes: args.weights,
+                            cluster: args.dot_cluster.clone(),
+                            ..Default::default()
+                        };
+                        write_dot_to_file(graph, &output, &config)
+                    },
+                    GraphFormat::Edges => {
+                        let mut attributes = args.edge_attributes.clone();
+                        if args.weights && !attributes.contains(&EdgeAttribute::Weight) {
+                            attributes.insert(0, EdgeAttribute::Weight);
+                        }
+                        let config = EdgeListConfig { ids: args.edge_ids, attributes };
+                        write_edgelist_to_file(graph, &output, &config)
+                    },
+                    GraphFormat::MatrixMarket => write_matrix_market_to_file(
+                        graph,
+                        &output,
+                        &args.mtx_weight,
+                        true
+                    ),
+                    _ => write_graph_to_file(
+                        graph, 
+                        &output, 
+                        &args.format, 
+                        args.weights
+                    )
+                }?;
+
+                if let Some(path) = &args.spectral {
+                    let path = if args.k.len() == 1 {
+                        path.clone()
+                    } else {
+                        path.with_extension(format!("k{k}.tsv"))
+                    };
+                    log::info!("Writing spectral embedding to: {}", path.display());
+                    let config = EigenConfig { max_iterations: args.spectral_max_iterations, tolerance: args.spectral_tolerance, ..Default::default() };
+                    spectral_embedding(graph, args.spectral_dimensions, args.spectral_weight.as_ref(), &config)?
+                        .write_coordinates(&path)?;
+                }
+                Ok(())
+            })?;
 
         },
         Commands::Dist(args) => {
@@ -92,40 +172,359 @@ pub fn main() -> Result<(), NetviewError> {
                 write_ids(&excluded, &path)?;
             }
         },
+        Commands::Tree(args) => {
+
+            log::info!("Reading distance matrix: {}", args.dist.display());
+            let dist = parse_input_matrix(&args.dist, false)?;
+
+            let ids = match &args.ids {
+                Some(path) => Some(parse_identifiers(path)?),
+                None => None
+            };
+            if let Some(ids) = &ids {
+                if ids.len() != dist.len() {
+                    return Err(NetviewError::NodeLabelLengthError(dist.len()))
+                }
+            }
+
+            let mut tips: Vec<NodeLabel> = (0..dist.len())
+                .map(|i| NodeLabel::new(i, ids.as_ref().map(|ids| ids[i].clone())))
+                .collect();
+
+            if let Some(path) = &args.labels {
+                log::info!("Reading labels from file: {}", path.display());
+                let labels = read_labels_from_file(path, false)?;
+                if labels.len() != tips.len() {
+                    return Err(NetviewError::NodeLabelLengthError(tips.len()))
+                }
+                for (tip, label) in tips.iter_mut().zip(labels) {
+                    tip.label = label.label;
+                    tip.label_confidence = 1.0;
+                }
+            }
+
+            log::info!("Constructing tree with method: {}", args.method);
+            let tree = build_tree(&dist, &args.method)?;
+
+            log::info!("Writing tree to: {}", args.output.display());
+            write_newick_to_file(&tree, &tips, &args.output, args.labels.is_some())?;
+        },
+        Commands::Pcoa(args) => {
+
+            log::info!("Reading distance matrix: {}", args.dist.display());
+            let dist = parse_input_matrix(&args.dist, false)?;
+
+            let ids = match &args.ids {
+                Some(path) => Some(parse_identifiers(path)?),
+                None => None
+            };
+
+            log::info!("Computing principal coordinates (m = {})", args.dimensions);
+            let config = EigenConfig { max_iterations: args.max_iterations, tolerance: args.tolerance, ..Default::default() };
+            let ordination = pcoa(&dist, ids, args.dimensions, &config)?;
+
+            for (i, explained) in ordination.explained_variance.iter().enumerate() {
+                log::info!("PC{}: {:.2}% explained variance", i + 1, explained * 100.0);
+            }
+
+            log::info!("Writing coordinates to: {}", args.output.display());
+            ordination.write_coordinates(&args.output)?;
+
+            if let Some(path) = &args.variance {
+                log::info!("Writing explained variance to: {}", path.display());
+                ordination.write_variance(path)?;
+            }
+
+            if let Some(path) = &args.graph {
+                let graph_json = GraphJson::read(path)?;
+                let metadata = graph_json.metadata.clone();
+
+                let mut graph = graph_json.into_graph()?;
+                ordination.apply_layout(&mut graph)?;
+
+                let mut graph_json = GraphJson::from(&graph);
+                if let Some(metadata) = metadata {
+                    graph_json = graph_json.with_metadata(metadata);
+                }
+                
+                let output = args.graph_output.as_ref().unwrap_or(path);
+                log::info!("Writing graph with layout to: {}", output.display());
+                graph_json.write(output)?;
+            }
+        },
+        Commands::Community(args) => {
+
+            let graph_json = GraphJson::read(&args.graph)?;
+            let metadata = graph_json.metadata.clone();
+            let mut graph = graph_json.into_graph()?;
+
+            let config = CommunityConfig {
+                method: args.method.clone(),
+                resolution: args.resolution,
+                seed: args.seed,
+                distance_percent: !args.fraction,
+                steps: args.steps,
+                communities: args.communities,
+                ..Default::default()
+            };
+
+            log::info!("Detecting communities with method: {}", config.method);
+            let communities = detect_communities(&graph, &config)?;
+            log::info!("Detected {} communities (modularity = {:.4})", communities.count(), communities.modularity);
+            if let Some(codelength) = communities.codelength {
+                log::info!("Map equation codelength: {codelength:.4} bits");
+            }
+
+            if let Some(dendrogram) = &communities.dendrogram {
+                if let Some(path) = &args.dendrogram {
+                    log::info!("Writing merge dendrogram to: {}", path.display());
+                    dendrogram.write_merges(path)?;
+                }
+                if let Some(path) = &args.dendrogram_newick {
+                    log::info!("Writing merge dendrogram to: {}", path.display());
+                    let tips: Vec<NodeLabel> = graph.node_weights().cloned().collect();
+                    write_newick_to_file(&dendrogram.to_tree(), &tips, path, false)?;
+                }
+            } else if args.dendrogram.is_some() || args.dendrogram_newick.is_some() {
+                log::warn!("Merge dendrogram is only available for walktrap");
+            }
+
+            communities.apply(&mut graph)?;
+
+            log::info!("Writing community membership to: {}", args.output.display());
+            communities.write_membership(&graph, &args.output)?;
+
+            if let Some(path) = &args.graph_output {
+                let mut graph_json = GraphJson::from(&graph);
+                if let Some(metadata) = metadata {
+                    graph_json = graph_json.with_metadata(metadata);
+                }
+                log::info!("Writing graph with communities to: {}", path.display());
+                graph_json.write(path)?;
+            }
+        },
+        Commands::Consensus(args) => {
+
+            let mut graphs = Vec::new();
+            let mut metadata = None;
+            for path in &args.graphs {
+                log::info!("Reading graph: {}", path.display());
+                let graph_json = GraphJson::read(path)?;
+                if metadata.is_none() {
+                    metadata = graph_json.metadata.clone();
+                }
+                graphs.push(graph_json.into_graph()?);
+            }
+
+            let config = CommunityConfig {
+                method: args.method.clone(),
+                resolution: args.resolution,
+                seed: args.seed,
+                distance_percent: !args.fraction,
+                ..Default::default()
+            };
+            let consensus_config = ConsensusConfig {
+                seeds: (args.seed..args.seed + args.runs).collect(),
+                threshold: args.threshold,
+                min_stability: args.min_stability
+            };
+
+            log::info!("Detecting communities with method: {} ({} graphs, {} runs)", config.method, graphs.len(), args.runs);
+            let consensus = consensus_communities(&graphs, &config, &consensus_config)?;
+            log::info!(
+                "Detected {} consensus communities with {} unstable nodes",
+                consensus.count(),
+                consensus.unstable.iter().filter(|u| **u).count()
+            );
+
+            let mut graph = graphs.swap_remove(0);
+            consensus.apply(&mut graph)?;
+
+            log::info!("Writing consensus membership to: {}", args.output.display());
+            consensus.write_membership(&graph, &args.output)?;
+
+            if let Some(path) = &args.coassignment {
+                log::info!("Writing co-assignment matrix to: {}", path.display());
+                consensus.write_coassignment(&graph, path)?;
+            }
+
+            if let Some(path) = &args.graph_output {
+                let mut graph_json = GraphJson::from(&graph);
+                if let Some(metadata) = metadata {
+                    graph_json = graph_json.with_metadata(metadata);
+                }
+                log::info!("Writing graph with consensus communities to: {}", path.display());
+                graph_json.write(path)?;
+            }
+        },
+        Commands::Compare(args) => {
+
+            log::info!("Reading reference partition: {}", args.reference.display());
+            let reference = read_partition(&args.reference, args.reference_column.as_deref())?;
+
+            log::info!("Reading predicted partition: {}", args.predicted.display());
+            let predicted = read_partition(&args.predicted, args.predicted_column.as_deref())?;
+
+            let comparison = compare_partitions(&reference, &predicted)?;
+            log::info!(
+                "Compared {} nodes: ARI = {:.4}, NMI = {:.4}, purity = {:.4}, V-measure = {:.4}",
+                comparison.nodes,
+                comparison.adjusted_rand_index,
+                comparison.normalized_mutual_information,
+                comparison.purity,
+                comparison.v_measure
+            );
+            if comparison.missing_reference > 0 || comparison.missing_predicted > 0 {
+                log::warn!(
+                    "Nodes without a class: {} in reference, {} in prediction",
+                    comparison.missing_reference,
+                    comparison.missing_predicted
+                );
+            }
+
+            log::info!("Writing comparison to: {}", args.output.display());
+            comparison.write(&args.output)?;
+
+            if let Some(path) = &args.contingency {
+                log::info!("Writing contingency table to: {}", path.display());
+                comparison.contingency.write(path)?;
+            }
+        },
         Commands::Label(args) => {
 
-            let netview = Netview::new(NetviewConfig::default());
+            let label_config = LabelConfig {
+                centrality_metric: NodeCentrality::Degree,
+                max_iterations: args.max_iterations,
+                vote_weights: VoteWeights::default(),
+                neighbor_centrality_vote: args.neighbor_centrality,
+                distance_percent: true,
+                update: args.update,
+                seed: args.seed,
+                spreading: args.spreading.then(|| SpreadingConfig {
+                    alpha: args.alpha,
+                    clamp: !args.soft,
+                    ..Default::default()
+                }),
+                hierarchy: args.hierarchy.as_ref().map(|separator| HierarchyConfig {
+                    separator: separator.clone(),
+                    min_support: args.min_support,
+                }),
+                abstention: AbstentionConfig {
+                    label: args.novel_label.clone(),
+                    min_confidence: args.min_confidence,
+                    max_distance: args.max_distance,
+                    min_af: args.min_af,
+                },
+            };
+            let netview = Netview::new(NetviewConfig { label: label_config, ..Default::default() });
 
             let mut graph = netview.read_json_graph(&args.graph)?;
 
             log::info!("Reading labels from file...");
-            let labels: Vec<Option<String>> = read_labels_from_file(&args.labels, false)?
-                .into_iter()
-                .map(|g| g.label)
-                .collect();
+            let label_columns = args.label_columns.columns();
+            let (label_table, label_metadata) = read_labels_with_metadata(&args.labels, &label_columns)?;
 
-            log::info!("Decorating nodes with labels...");
-            netview.label_nodes(&mut graph, labels)?;
-
-            netview.label_propagation(
-                &mut graph,
-                NodeCentrality::Degree, 
-                args.max_iterations, 
-                VoteWeights::default(),
-                args.neighbor_centrality, 
-                true, 
-                args.query.clone(), 
-                args.unlabelled
-            );
+            // Additional columns of the label file and metadata table are typed node attributes
+            label_metadata.apply(&mut graph);
+            if let Some(path) = &args.metadata {
+                let metadata_columns = LabelColumns { labels: Vec::new(), ..label_columns.clone() };
+                read_node_metadata(path, &metadata_columns)?.apply(&mut graph);
+            }
+
+            let filtered = (!args.filter.is_empty()).then(|| filter_nodes(&graph, &args.filter));
+            if let Some(filtered) = &filtered {
+                log::info!("Selected {} of {} nodes with attribute filters", filtered.len(), graph.node_count());
+            }
+
+            // Labels are predicted separately for each label column
+            let multiple_columns = label_table.columns.len() > 1;
+            let mut predictions = Vec::new();
+            for (column, name) in label_table.columns.iter().enumerate() {
+
+                let output = |path: &std::path::PathBuf| if multiple_columns { label_column_path(path, name) } else { path.clone() };
+
+                log::info!("Decorating nodes with labels ({name})...");
+                let mut column_graph = graph.clone();
+                netview.label_nodes(&mut column_graph, label_table.values[column].clone())?;
+
+                let unlabelled_nodes: Vec<String> = column_graph.node_weights()
+                    .filter(|node| node.label.is_none())
+                    .map(|node| node.id.clone().unwrap_or_else(|| node.index.to_string()))
+                    .collect();
+
+                // Attribute filters restrict the query to matching nodes that are also 
+                // unlabelled or in the query if these options are given
+                let (query, unlabelled) = match &filtered {
+                    Some(filtered) => (Some(filtered.iter().filter(|id| {
+                        args.query.as_ref().map_or(true, |query| query.contains(id))
+                            && (!args.unlabelled || unlabelled_nodes.contains(id))
+                    }).cloned().collect::<Vec<_>>()), false),
+                    None => (args.query.clone(), args.unlabelled)
+                };
+
+                // Targets of label prediction that may be assigned the novel label
+                let targets: Vec<String> = match (&query, unlabelled) {
+                    (Some(query), false) => query.clone(),
+                    _ => unlabelled_nodes
+                };
 
-            netview.write_labels(&graph, &args.output_labels, None)?;
+                let mut vote_report = args.output_votes.as_ref().map(|_| VoteReport::new());
+                let distribution = netview.propagate_labels(
+                    &mut column_graph, 
+                    query, 
+                    unlabelled, 
+                    &targets, 
+                    vote_report.as_mut()
+                )?;
+
+                if let (Some(distribution), Some(path)) = (distribution, &args.output_probabilities) {
+                    let path = output(path);
+                    log::info!("Writing label probabilities to: {}", path.display());
+                    distribution.write(&path, false)?;
+                }
+                if let (Some(report), Some(path)) = (vote_report.filter(|_| !args.spreading), &args.output_votes) {
+                    let path = output(path);
+                    log::info!("Writing label votes to: {}", path.display());
+                    report.write(&path, &VoteReportFormat::from_path(&path))?;
+                }
+                predictions.push((name.clone(), column_graph));
+            }
+
+            let (_, mut graph) = predictions[0].clone();
+            if multiple_columns {
+                for (name, prediction) in &predictions {
+                    add_label_column_attributes(&mut graph, name, prediction);
+                }
+                log::info!("Writing label columns to: {}", args.output_labels.display());
+                write_label_columns_to_file(&predictions, &args.output_labels, None, false)?;
+            } else {
+                netview.write_labels(&graph, &args.output_labels, None)?;
+            }
+            netview.write_json_graph(&graph, &args.output_graph, None)?;
+
+            if let Some(path) = &args.output_dot {
+                if let Some(name) = &args.dot_color {
+                    if !graph.node_weights().any(|node| node.attributes.contains_key(name)) {
+                        log::warn!("Node attribute '{name}' not found, DOT nodes are not filled");
+                    }
+                }
+                let config = DotConfig {
+                    tooltip: args.dot_tooltip,
+                    color_attribute: args.dot_color.clone(),
+                    ..Default::default()
+                };
+                log::info!("Writing graph to: {}", path.display());
+                write_dot_to_file(&graph, path, &config)?;
+            }
             
         },
         Commands::Derep(args) => {
 
+            let label_columns = args.label_columns.columns();
             let drp = Dereplicator::new(
                 &args.fasta, 
                 &args.labels, 
+                &label_columns,
                 args.max_per_label
             );
 
@@ -136,6 +535,7 @@ pub fn main() -> Result<(), NetviewError> {
 
             let cv = CrossFoldValidation::new(
                 &args.labels, 
+                &args.label_columns.columns(),
                 &args.fasta, 
                 args.k_folds, 
                 args.max_per_label.clone(),
@@ -145,13 +545,135 @@ pub fn main() -> Result<(), NetviewError> {
             cv.generate_k_folds()?;
             
         },
-        Commands::Predict(args) => {
+        Commands::Eval(args) => {
 
             let config = match (&args.json, &args.toml) {
                 (Some(path), _)    => NetviewConfig::read_json(path)?,
                 (None, Some(path)) => NetviewConfig::read_toml(path)?,
                 _ => NetviewConfig::default()
             };
+            let netview = Netview::new(config);
+            let graph = netview.read_json_graph(&args.graph)?;
+
+            // Known labels from the label columns or the labels of the graph
+            let columns = match &args.labels {
+                Some(path) => {
+                    let table = read_label_table(path, &args.label_columns.columns())?;
+                    let mut columns = Vec::new();
+                    for (column, name) in table.columns.iter().enumerate() {
+                        let mut column_graph = graph.clone();
+                        netview.label_nodes(&mut column_graph, table.values[column].clone())?;
+                        columns.push((name.clone(), column_graph));
+                    }
+                    columns
+                },
+                None => vec![(String::from("label"), graph)]
+            };
+
+            let multiple_columns = columns.len() > 1;
+            for (name, graph) in columns {
+                let output = |path: &std::path::PathBuf| if multiple_columns { label_column_path(path, &name) } else { path.clone() };
+
+                let evaluation = netview.leave_one_out(&graph, args.max_per_label, args.seed)?;
+
+                log::info!("Writing evaluation ({name}) to: {}", output(&args.output).display());
+                evaluation.write(&output(&args.output))?;
+                if let Some(path) = &args.confusion {
+                    log::info!("Writing confusion matrix to: {}", output(path).display());
+                    evaluation.confusion.write(&output(path))?;
+                }
+                if let Some(path) = &args.classes {
+                    log::info!("Writing class metrics to: {}", output(path).display());
+                    evaluation.write_classes(&output(path))?;
+                }
+                if let Some(path) = &args.predictions {
+                    log::info!("Writing predictions to: {}", output(path).display());
+                    evaluation.write_predictions(&output(path))?;
+                }
+            }
+            
+        },
+        Commands::Tune(args) => {
+
+            let config = match (&args.json, &args.toml) {
+                (Some(path), _)    => NetviewConfig::read_json(path)?,
+                (None, Some(path)) => NetviewConfig::read_toml(path)?,
+                _ => NetviewConfig::default()
+            };
+
+            log::info!("Reading labels from file...");
+            let label_table = read_label_table(&args.labels, &args.label_columns.columns())?;
+            if label_table.columns.len() > 1 {
+                log::warn!("Parameters are tuned for the first label column: {}", label_table.columns[0]);
+            }
+            let labels = label_table.labels(0).into_iter()
+                .filter_map(|label| label.label.map(|value| (label.id, value)))
+                .collect();
+
+            let validation = match &args.folds {
+                Some(outdir) => {
+                    log::info!("Reading cross-validation folds: {}", outdir.display());
+                    TuneValidation::Folds(read_folds(outdir, &args.label_columns.columns())?)
+                },
+                None => TuneValidation::LeaveOneOut { max_per_label: args.max_per_label, seed: args.seed }
+            };
+
+            log::info!("Reading distance matrix: {}", args.dist.display());
+            let distance = parse_input_matrix(&args.dist, false)?;
+            let af = match &args.afrac {
+                Some(path) => Some(parse_input_matrix(path, false)?),
+                None => None
+            };
+            let ids = parse_identifiers(&args.ids)?;
+
+            // Parameters not given on the command line are taken from the base configuration
+            let base = TuneGrid::from_config(&config);
+            let grid = TuneGrid {
+                k: args.k.clone().unwrap_or(base.k),
+                centrality_metric: args.centrality_metric.clone().unwrap_or(base.centrality_metric),
+                neighbor_centrality_vote: args.neighbor_centrality.clone().unwrap_or(base.neighbor_centrality_vote),
+                centrality: args.centrality_weight.clone().unwrap_or(base.centrality),
+                weight: args.distance_weight.clone().unwrap_or(base.weight),
+                af: args.af_weight.clone().unwrap_or(base.af),
+                ani: args.ani_weight.clone().unwrap_or(base.ani),
+                aai: args.aai_weight.clone().unwrap_or(base.aai),
+            };
+
+            let mut graphs = mknn_graphs(&distance, af.as_ref(), Some(ids), &grid.k, args.threads, args.chunk_size, None)?;
+            for (_, graph) in graphs.iter_mut() {
+                label_nodes_by_id(graph, &labels)?;
+            }
+
+            let results = tune_label_propagation(&graphs, &grid, &config.label, &validation, &args.metric)?;
+
+            log::info!("Writing ranked parameter combinations to: {}", args.output.display());
+            write_tune_results(&results, &args.output)?;
+
+            if let Some(best) = results.first() {
+                log::info!(
+                    "Best parameters by {} ({:.4}): k = {}, {}, neighbor centrality vote = {}",
+                    args.metric, args.metric.value(&best.evaluation), best.k, best.label.centrality_metric, best.label.neighbor_centrality_vote
+                );
+                let best_config = NetviewConfig {
+                    graph: GraphConfig { k: best.k },
+                    label: best.label.clone(),
+                    ..config
+                };
+                log::info!("Writing configuration to: {}", args.output_config.display());
+                best_config.write_toml(&args.output_config)?;
+            }
+            
+        },
+        Commands::Predict(args) => {
+
+            let mut config = match (&args.json, &args.toml) {
+                (Some(path), _)    => NetviewConfig::read_json(path)?,
+                (None, Some(path)) => NetviewConfig::read_toml(path)?,
+                _ => NetviewConfig::default()
+            };
+            if let Some(k) = args.k {
+                config.graph.k = k;
+            }
 
             let netview = Netview::new(config);
 
@@ -159,13 +681,16 @@ pub fn main() -> Result<(), NetviewError> {
                 &args.fasta, 
                 &args.db,
                 &args.labels, 
-                args.k, 
                 &args.outdir,
-                args.all,
-                args.basename.clone(),
-                args.threads,
-                args.chunk_size,
-                args.edge_threshold.clone()
+                &PredictOptions {
+                    propagate_all: args.all,
+                    basename: args.basename.clone(),
+                    threads: args.threads,
+                    chunk_size: args.chunk_size,
+                    edge_threshold: args.edge_threshold,
+                    vote_format: args.votes.clone(),
+                    label_columns: args.label_columns.columns(),
+                }
             )?;
             
         },
@@ -173,6 +698,67 @@ pub fn main() -> Result<(), NetviewError> {
         #[cfg(feature = "plot")]
         Commands::Plot(args) => {
             plot_test(&args.graph)?;
+        },
+        #[cfg(feature = "columnar")]
+        Commands::Export(args) => {
+
+            let output = |table: &str| args.output.with_extension(format!("{table}.{}", args.format));
+
+            if let Some(path) = &args.graph {
+                let graph_json = GraphJson::read(path)?;
+                let metadata = graph_json.metadata.clone();
+                let graph = graph_json.into_graph()?;
+
+                write_graph_tables(
+                    &graph, 
+                    &output("nodes"), 
+                    &output("edges"), 
+                    &args.format, 
+                    metadata.as_ref()
+                )?;
+            }
+            if let Some(path) = &args.dist {
+                let matrix = parse_input_matrix(path, false)?;
+                let ids = match &args.ids {
+                    Some(path) => Some(parse_identifiers(path)?),
+                    None => None
+                };
+                write_matrix_table(&matrix, ids.as_ref(), &output("matrix"), &args.format, args.diagonal)?;
+            }
+        },
+        #[cfg(feature = "sqlite")]
+        Commands::Sqlite(args) => {
+
+            let config = match (&args.json, &args.toml) {
+                (Some(path), _)    => Some(NetviewConfig::read_json(path)?),
+                (None, Some(path)) => Some(NetviewConfig::read_toml(path)?),
+                _ => None
+            };
+
+            let graph_json = GraphJson::read(&args.graph)?;
+            let metadata = graph_json.metadata.clone();
+            let graph = graph_json.into_graph()?;
+
+            let dist = match &args.dist {
+                Some(path) => Some(parse_input_matrix(path, false)?),
+                None => None
+            };
+            let af = match &args.afrac {
+                Some(path) => Some(parse_input_matrix(path, false)?),
+                None => None
+            };
+
+            let mut exporter = SqliteExporter::new(&graph);
+            if let Some(dist) = &dist {
+                exporter = exporter.distances(dist, af.as_ref(), args.pairs.clone());
+            }
+            if let Some(config) = &config {
+                exporter = exporter.config(config);
+            }
+            if let Some(metadata) = &metadata {
+                exporter = exporter.metadata(metadata);
+            }
+            exporter.write(&args.output)?;
         }
     }
     Ok(())
@@ -0,0 +1,410 @@
+// Typed node metadata (e.g. country, year, host, ST, MIC) from label or metadata tables
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::NetviewError;
+use crate::label::{read_table, LabelColumns, LabelTable};
+use crate::mknn::node_id_or_index;
+use crate::netview::{NetviewGraph, NodeLabel};
+
+/// Value of a node attribute, untagged in JSON so that attributes are plain values
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+impl std::fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttributeValue::Boolean(value) => write!(f, "{}", value),
+            AttributeValue::Integer(value) => write!(f, "{}", value),
+            AttributeValue::Float(value) => write!(f, "{}", value),
+            AttributeValue::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+impl AttributeValue {
+    pub fn attribute_type(&self) -> AttributeType {
+        match self {
+            AttributeValue::Boolean(_) => AttributeType::Boolean,
+            AttributeValue::Integer(_) => AttributeType::Integer,
+            AttributeValue::Float(_) => AttributeType::Float,
+            AttributeValue::Text(_) => AttributeType::Text,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            AttributeValue::Integer(value) => Some(*value as f64),
+            AttributeValue::Float(value) => Some(*value),
+            _ => None
+        }
+    }
+    // Value as the common type of its column (see `attribute_columns`)
+    pub fn cast(&self, attribute_type: &AttributeType) -> AttributeValue {
+        match (self, attribute_type) {
+            (AttributeValue::Integer(value), AttributeType::Float) => AttributeValue::Float(*value as f64),
+            (value, AttributeType::Text) => AttributeValue::Text(value.to_string()),
+            (value, _) => value.clone()
+        }
+    }
+    // Parse a table value as the inferred column type
+    fn parse(value: &str, attribute_type: &AttributeType) -> Option<Self> {
+        match attribute_type {
+            AttributeType::Boolean => parse_bool(value).map(AttributeValue::Boolean),
+            AttributeType::Integer => value.parse().ok().map(AttributeValue::Integer),
+            AttributeType::Float => value.parse().ok().map(AttributeValue::Float),
+            AttributeType::Text => Some(AttributeValue::Text(value.to_string())),
+        }
+    }
+}
+
+/// Type of an attribute column, inferred from all non-empty values
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AttributeType {
+    Boolean,
+    Integer,
+    Float,
+    Text,
+}
+impl std::fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            AttributeType::Boolean => "boolean",
+            AttributeType::Integer => "integer",
+            AttributeType::Float => "float",
+            AttributeType::Text => "text",
+        };
+        write!(f, "{}", output)
+    }
+}
+impl AttributeType {
+    // Most specific type of a single value, numbers must be finite and without leading 
+    // zeros or plus signs so that identifiers such as '007' are kept as text
+    fn infer(value: &str) -> Self {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        let padded = (digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.")) || value.starts_with('+');
+        if parse_bool(value).is_some() {
+            AttributeType::Boolean
+        } else if value.parse::<i64>().is_ok_and(|number| number.to_string() == value) {
+            AttributeType::Integer
+        } else if !padded && value.parse::<f64>().is_ok_and(f64::is_finite) {
+            AttributeType::Float
+        } else {
+            AttributeType::Text
+        }
+    }
+    // Common type of two types, integers widen to floats and anything else to text
+    fn unify(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (AttributeType::Integer, AttributeType::Float) | (AttributeType::Float, AttributeType::Integer) => AttributeType::Float,
+            _ => AttributeType::Text
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "TRUE" | "True" => Some(true),
+        "false" | "FALSE" | "False" => Some(false),
+        _ => None
+    }
+}
+
+/// Node attributes keyed by node identifier with the inferred column types
+#[derive(Clone, Debug, Default)]
+pub struct NodeMetadata {
+    pub columns: Vec<(String, AttributeType)>,
+    pub attributes: HashMap<String, BTreeMap<String, AttributeValue>>,
+}
+impl NodeMetadata {
+    // Attributes of the columns except the identifier and label columns of the records of a table (see `read_table`)
+    pub(crate) fn from_records(header: &csv::StringRecord, records: &[csv::StringRecord], columns: &LabelColumns) -> Result<Self, NetviewError> {
+
+        let header: Vec<String> = header.iter().map(String::from).collect();
+        let id_column = header.iter().position(|column| column == &columns.id)
+            .ok_or_else(|| NetviewError::MetadataColumnError(columns.id.clone()))?;
+        let attribute_columns: Vec<usize> = (0..header.len())
+            .filter(|&i| i != id_column && !columns.labels.contains(&header[i]))
+            .collect();
+
+        // Infer the type of each attribute column from its non-empty values
+        let columns: Vec<(String, AttributeType)> = attribute_columns.iter().map(|&i| {
+            let attribute_type = records.iter()
+                .filter_map(|record| record.get(i).filter(|value| !value.is_empty()))
+                .map(AttributeType::infer)
+                .reduce(AttributeType::unify)
+                .unwrap_or(AttributeType::Text);
+            (header[i].clone(), attribute_type)
+        }).collect();
+
+        let mut attributes = HashMap::new();
+        for record in records {
+            let id = record.get(id_column).unwrap_or_default().to_string();
+            let node_attributes: BTreeMap<String, AttributeValue> = attribute_columns.iter()
+                .zip(&columns)
+                .filter_map(|(&i, (name, attribute_type))| {
+                    let value = record.get(i).filter(|value| !value.is_empty())?;
+                    AttributeValue::parse(value, attribute_type).map(|value| (name.clone(), value))
+                })
+                .collect();
+            attributes.insert(id, node_attributes);
+        }
+
+        Ok(NodeMetadata { columns, attributes })
+    }
+    // Set the attributes of nodes by identifier, returns the number of nodes with metadata
+    pub fn apply(&self, graph: &mut NetviewGraph) -> usize {
+        let mut matched = 0;
+        for node in graph.node_weights_mut() {
+            if let Some(attributes) = self.attributes.get(&node_id_or_index(node)) {
+                node.attributes.extend(attributes.clone());
+                matched += 1;
+            }
+        }
+        log::info!(
+            "Added {} metadata columns to {} of {} nodes",
+            self.columns.len(), matched, graph.node_count()
+        );
+        matched
+    }
+}
+
+/// Reads node metadata from a CSV or TSV table with header and an identifier column.
+///
+/// All columns except the identifier and label columns are node attributes. 
+/// Column types are inferred over all non-empty values: boolean, integer, float 
+/// or text, where mixed integer and float columns are floats and other mixed 
+/// columns are text. Non-finite numbers (nan, inf) and numbers with leading zeros
+/// or plus signs (e.g. '007', '+4') are text. Empty values are missing attributes.
+pub fn read_node_metadata<P: AsRef<Path>>(path: P, columns: &LabelColumns) -> Result<NodeMetadata, NetviewError> {
+    let (header, records) = read_table(path.as_ref(), &columns.delimiter)?;
+    NodeMetadata::from_records(&header, &records, columns)
+}
+
+/// Reads the label columns and the node metadata of all other columns from a
+/// single pass over a label table, so that label tables can be read from stdin.
+pub fn read_labels_with_metadata<P: AsRef<Path>>(path: P, columns: &LabelColumns) -> Result<(LabelTable, NodeMetadata), NetviewError> {
+    let (header, records) = read_table(path.as_ref(), &columns.delimiter)?;
+    Ok((
+        LabelTable::from_records(&header, &records, columns)?,
+        NodeMetadata::from_records(&header, &records, columns)?
+    ))
+}
+
+/// Comparison of an attribute filter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+/// Node filter on a typed attribute, e.g. `country=AU` or `year>=2019`
+///
+/// Numeric attributes are compared as numbers with numeric values, all other
+/// attributes by their text with `=` and `!=` only. Nodes without the attribute
+/// do not match any filter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeFilter {
+    pub name: String,
+    pub operator: FilterOperator,
+    pub value: String,
+}
+impl std::str::FromStr for AttributeFilter {
+    type Err = NetviewError;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let error = || NetviewError::AttributeFilterError(filter.to_string());
+        let start = filter.find(['=', '!', '<', '>']).ok_or_else(error)?;
+        let (name, rest) = filter.split_at(start);
+
+        // Two-character operators before their single-character prefixes
+        let (operator, value) = [
+            ("!=", FilterOperator::NotEqual),
+            ("<=", FilterOperator::LessEqual),
+            (">=", FilterOperator::GreaterEqual),
+            ("=", FilterOperator::Equal),
+            ("<", FilterOperator::Less),
+            (">", FilterOperator::Greater),
+        ].into_iter().find_map(|(symbol, operator)| rest.strip_prefix(symbol).map(|value| (operator, value)))
+            .ok_or_else(error)?;
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(error())
+        }
+        Ok(AttributeFilter { name: name.to_string(), operator, value: value.trim().to_string() })
+    }
+}
+impl AttributeFilter {
+    // Whether the attribute of a node satisfies the filter
+    pub fn matches(&self, node: &NodeLabel) -> bool {
+        let Some(attribute) = node.attributes.get(&self.name) else {
+            return false
+        };
+        let ordering = match (attribute.as_f64(), self.value.parse::<f64>()) {
+            (Some(a), Ok(b)) => a.partial_cmp(&b),
+            _ => match self.operator {
+                FilterOperator::Equal | FilterOperator::NotEqual => Some(attribute.to_string().cmp(&self.value)),
+                _ => None
+            }
+        };
+        let Some(ordering) = ordering else {
+            return false
+        };
+        match self.operator {
+            FilterOperator::Equal => ordering.is_eq(),
+            FilterOperator::NotEqual => ordering.is_ne(),
+            FilterOperator::Less => ordering.is_lt(),
+            FilterOperator::LessEqual => ordering.is_le(),
+            FilterOperator::Greater => ordering.is_gt(),
+            FilterOperator::GreaterEqual => ordering.is_ge(),
+        }
+    }
+}
+
+/// Identifiers of the nodes of a graph that match all attribute filters
+pub fn filter_nodes(graph: &NetviewGraph, filters: &[AttributeFilter]) -> Vec<String> {
+    graph.node_weights()
+        .filter(|node| filters.iter().all(|filter| filter.matches(node)))
+        .map(node_id_or_index)
+        .collect()
+}
+
+/// Attribute columns across all nodes of a graph with their common types, sorted by name
+pub fn attribute_columns(graph: &NetviewGraph) -> Vec<(String, AttributeType)> {
+    let mut columns: BTreeMap<String, AttributeType> = BTreeMap::new();
+    for node in graph.node_weights() {
+        for (name, value) in &node.attributes {
+            columns.entry(name.clone())
+                .and_modify(|attribute_type| *attribute_type = attribute_type.unify(value.attribute_type()))
+                .or_insert(value.attribute_type());
+        }
+    }
+    columns.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mknn::GraphJson;
+
+    #[test]
+    fn metadata_types_are_inferred() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("labels.csv");
+        std::fs::write(&path, "id,label,country,year,mic,resistant,st\n\
+            a,L1,AU,2019,0.5,true,1\n\
+            b,L2,PNG,2020,2,false,ST2\n\
+            c,,,,,,\n").unwrap();
+
+        let metadata = read_node_metadata(&path, &LabelColumns::default()).unwrap();
+        assert_eq!(metadata.columns, vec![
+            ("country".to_string(), AttributeType::Text),
+            ("year".to_string(), AttributeType::Integer),
+            ("mic".to_string(), AttributeType::Float),
+            ("resistant".to_string(), AttributeType::Boolean),
+            ("st".to_string(), AttributeType::Text),
+        ]);
+        assert_eq!(metadata.attributes["a"]["mic"], AttributeValue::Float(0.5));
+        assert_eq!(metadata.attributes["b"]["mic"], AttributeValue::Float(2.0));
+        assert_eq!(metadata.attributes["a"]["st"], AttributeValue::Text("1".to_string()));
+        assert!(metadata.attributes["c"].is_empty());
+
+        // Attributes are kept through the JSON graph format with their types
+        let mut graph = NetviewGraph::new_undirected();
+        for (i, id) in ["a", "b", "c"].iter().enumerate() {
+            graph.add_node(NodeLabel::new(i, Some(id.to_string())));
+        }
+        assert_eq!(metadata.apply(&mut graph), 3);
+
+        let json = dir.path().join("graph.json");
+        GraphJson::from(&graph).write(&json).unwrap();
+        let graph = GraphJson::read(&json).unwrap().into_graph().unwrap();
+        assert_eq!(graph[petgraph::graph::NodeIndex::new(0)].attributes["year"], AttributeValue::Integer(2019));
+        assert_eq!(graph[petgraph::graph::NodeIndex::new(1)].attributes["mic"], AttributeValue::Float(2.0));
+        assert_eq!(attribute_columns(&graph).len(), 5);
+
+        // Labels and metadata from a single read of the label table
+        let (labels, combined) = read_labels_with_metadata(&path, &LabelColumns::default()).unwrap();
+        assert_eq!(labels.values[0], vec![Some("L1".to_string()), Some("L2".to_string()), None]);
+        assert_eq!(combined.columns, metadata.columns);
+        assert_eq!(combined.attributes, metadata.attributes);
+    }
+
+    #[test]
+    fn metadata_values_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metadata.tsv");
+        std::fs::write(&path, "id\tcode\tratio\tcount\tsigned\n\
+            a\t007\tnan\t12\t-3\n\
+            b\t12\tinf\t0\t+4\n\
+            c\t0.5\tNaN\t-7\t-0.25\n").unwrap();
+
+        let columns = LabelColumns { labels: vec![], ..Default::default() };
+        let metadata = read_node_metadata(&path, &columns).unwrap();
+        assert_eq!(metadata.columns, vec![
+            ("code".to_string(), AttributeType::Text),
+            ("ratio".to_string(), AttributeType::Text),
+            ("count".to_string(), AttributeType::Integer),
+            ("signed".to_string(), AttributeType::Text),
+        ]);
+
+        // Values are written as read through the JSON graph format
+        let mut graph = NetviewGraph::new_undirected();
+        for (i, id) in ["a", "b", "c"].iter().enumerate() {
+            graph.add_node(NodeLabel::new(i, Some(id.to_string())));
+        }
+        metadata.apply(&mut graph);
+        let json = dir.path().join("graph.json");
+        GraphJson::from(&graph).write(&json).unwrap();
+        let graph = GraphJson::read(&json).unwrap().into_graph().unwrap();
+
+        let table = std::fs::read_to_string(&path).unwrap();
+        for (node, line) in graph.node_weights().zip(table.lines().skip(1)) {
+            let values: Vec<String> = ["code", "ratio", "count", "signed"].iter()
+                .map(|column| node.attributes[*column].to_string())
+                .collect();
+            assert_eq!(line.split('\t').skip(1).collect::<Vec<_>>(), values);
+        }
+    }
+
+    #[test]
+    fn attribute_filters_select_nodes() {
+        let mut graph = NetviewGraph::new_undirected();
+        for (i, (country, year)) in [("AU", 2019), ("PNG", 2020), ("AU", 2021)].iter().enumerate() {
+            let mut node = NodeLabel::new(i, Some(format!("s{i}")));
+            node.attributes.insert("country".to_string(), AttributeValue::Text(country.to_string()));
+            node.attributes.insert("year".to_string(), AttributeValue::Integer(*year));
+            graph.add_node(node);
+        }
+        graph.add_node(NodeLabel::new(3, Some("s3".to_string())));
+
+        let filter = |filters: &[&str]| -> Vec<String> {
+            let filters: Vec<AttributeFilter> = filters.iter().map(|filter| filter.parse().unwrap()).collect();
+            filter_nodes(&graph, &filters)
+        };
+        assert_eq!(filter(&["country=AU"]), vec!["s0", "s2"]);
+        assert_eq!(filter(&["country!=AU"]), vec!["s1"]);
+        assert_eq!(filter(&["year>=2020"]), vec!["s1", "s2"]);
+        assert_eq!(filter(&["country = AU", "year<2020"]), vec!["s0"]);
+        assert_eq!(filter(&["year=2020.0"]), vec!["s1"]);
+        assert!(filter(&["country>AU"]).is_empty());
+        assert_eq!(filter(&[]).len(), 4);
+
+        let filter: AttributeFilter = "mic<=0.5".parse().unwrap();
+        assert_eq!((filter.name.as_str(), filter.operator, filter.value.as_str()), ("mic", FilterOperator::LessEqual, "0.5"));
+        assert!(matches!("country".parse::<AttributeFilter>(), Err(NetviewError::AttributeFilterError(_))));
+        assert!(matches!("=AU".parse::<AttributeFilter>(), Err(NetviewError::AttributeFilterError(_))));
+    }
+}
@@ -1,18 +1,20 @@
 use rayon::prelude::*;
-use petgraph::{dot::Dot, Graph, Undirected};
+use petgraph::{Graph, Undirected};
 use csv::WriterBuilder;
 use serde_json;
 use serde::{Deserialize, Serialize};
 use std::io::{BufReader, BufRead, Read};
-use std::{fs::File, io::Write, path::Path};
+use std::{io::Write, path::{Path, PathBuf}};
 use petgraph::visit::EdgeRef;
 use core::f64::NAN;
 use petgraph::graph::NodeIndex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-use crate::dist::make_symmetrical;
+use crate::config::NetviewConfig;
+use crate::dist::{euclidean_distance_of_distances, make_symmetrical};
 use crate::error::NetviewError;
 use crate::netview::{EdgeLabel, NetviewGraph, NodeLabel};
+use crate::utils::{get_file_reader, get_file_writer, is_stdio, sha256_checksum};
 
 
 /// Calculates the k-mutual nearest neighbors from a distance matrix.
@@ -87,6 +89,28 @@ pub fn k_mutual_nearest_neighbors(distance_matrix: &Vec<Vec<f64>>, k: usize) ->
 }
 
 
+/// Mutual nearest neighbor graphs of a distance matrix for each k, the distance
+/// abstraction is computed once for all k
+pub fn mknn_graphs(
+    distance: &Vec<Vec<f64>>,
+    af: Option<&Vec<Vec<f64>>>,
+    ids: Option<Vec<String>>,
+    ks: &[usize],
+    threads: Option<usize>,
+    chunk_size: Option<usize>,
+    edge_threshold: Option<f64>
+) -> Result<Vec<(usize, NetviewGraph)>, NetviewError> {
+
+    log::info!("Computing Euclidean distance abstraction matrix");
+    let distance_of_distances = euclidean_distance_of_distances(distance, false, threads, chunk_size)?;
+
+    ks.iter().map(|&k| {
+        log::info!("Computing mutual nearest neighbor graph (k = {k})");
+        let mutual_nearest_neighbors = k_mutual_nearest_neighbors(&distance_of_distances, k)?;
+        Ok((k, convert_to_graph(&mutual_nearest_neighbors, Some(distance), af, ids.clone(), edge_threshold)?))
+    }).collect()
+}
+
 // Function to convert mutual nearest neighbors to a graph with NodeLabel and EdgeLabel
 pub fn convert_to_graph(
     mutual_nearest_neighbors: &Vec<Vec<usize>>, 
@@ -175,6 +199,8 @@ pub enum GraphFormat {
     Json,
     Adjacency,
     Edges,
+    Cytoscape,
+    MatrixMarket,
 }
 impl std::fmt::Display for GraphFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -183,6 +209,8 @@ impl std::fmt::Display for GraphFormat {
             GraphFormat::Json => "json",
             GraphFormat::Adjacency => "adj",
             GraphFormat::Edges => "edges",
+            GraphFormat::Cytoscape => "cyjs",
+            GraphFormat::MatrixMarket => "mtx",
         };
         write!(f, "{}", output)
     }
@@ -193,10 +221,12 @@ impl std::fmt::Display for GraphFormat {
 ///
 /// This function supports exporting the graph to various formats for visualization
 /// or further processing. Currently supported formats are:
-/// - **DOT**: For use with Graphviz for visualizing the graph.
+/// - **DOT**: For use with Graphviz for visualizing the graph, see `write_dot_to_file`.
 /// - **JSON**: For generic data interchange, representing nodes and edges as JSON objects.
 /// - **Adjacency Matrix**: Outputs the adjacency matrix representation of the graph in TSV format.
 /// - **Edges**: Outputs an edge list with source, target, and optional weights.
+/// - **Cytoscape**: Cytoscape.js elements JSON with string node identifiers.
+/// - **MatrixMarket**: Sparse symmetric adjacency matrix with distances, see `write_matrix_market_to_file`.
 ///
 /// # Arguments
 /// * `graph`  - Reference to the graph to be written.
@@ -237,12 +267,11 @@ where
 {
     
     log::info!("Writing graph to: {}", path.display());
-    let mut file = File::create(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
 
     match format {
         GraphFormat::Dot => {
-            let dot = Dot::with_config(graph, &[petgraph::dot::Config::EdgeNoLabel]);
-            write!(file, "{:?}", dot).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+            let config = DotConfig { edge_attributes: include_weights, ..Default::default() };
+            write_dot_to_file(graph, path, &config)?;
         },
         GraphFormat::Json => {
             let jgraph = GraphJson::from(graph);
@@ -253,16 +282,294 @@ where
             write_adjacency_matrix_to_file(&adj_matrix, path)?;   
         },
         GraphFormat::Edges => {
-            let edgelist = graph_to_edgelist(graph);
-            write_edgelist_to_file(&edgelist, path, include_weights)?;
+            let config = EdgeListConfig {
+                ids: false,
+                attributes: if include_weights { vec![EdgeAttribute::Weight] } else { Vec::new() }
+            };
+            write_edgelist_to_file(graph, path, &config)?;
+        },
+        GraphFormat::Cytoscape => {
+            let cgraph = CytoscapeJson::from(graph)?;
+            cgraph.write(path)?;
+        },
+        GraphFormat::MatrixMarket => {
+            write_matrix_market_to_file(graph, path, &SparseWeight::Distance, true)?;
         }
     }
 
     Ok(())
 }
 
+
+/// Fill colors for node labels in DOT output, cycled in order of sorted labels
+pub const DOT_LABEL_PALETTE: [&str; 12] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b",
+    "#e377c2", "#7f7f7f", "#bcbd22", "#17becf", "#aec7e8", "#ffbb78",
+];
+
+/// Fill color for nodes without a label in DOT output
+pub const DOT_UNLABELLED_COLOR: &str = "#d3d3d3";
+
+/// Grouping of nodes into DOT cluster subgraphs
+#[derive(Serialize, Deserialize, Clone, Debug, clap::ValueEnum)]
+pub enum DotCluster {
+    Label,
+    Community,
+}
+
+/// Options for the attribute-rich DOT writer
+#[derive(Clone, Debug)]
+pub struct DotConfig {
+    pub tooltip: bool,                  // Node tooltip with identifier, label and confidence
+    pub edge_attributes: bool,          // Edge `len` and `penwidth` derived from distance
+    pub cluster: Option<DotCluster>,    // Group nodes into `cluster_*` subgraphs
+    pub color_attribute: Option<String>, // Fill nodes by the value of this node attribute instead of the label
+    pub min_len: f64,                   // Edge length of the closest pair
+    pub max_len: f64,                   // Edge length of the most distant pair
+    pub min_penwidth: f64,              // Edge pen width of the most distant pair
+    pub max_penwidth: f64,              // Edge pen width of the closest pair
+}
+
+impl Default for DotConfig {
+    fn default() -> Self {
+        Self {
+            tooltip: false,
+            edge_attributes: true,
+            cluster: None,
+            color_attribute: None,
+            min_len: 0.5,
+            max_len: 3.0,
+            min_penwidth: 0.5,
+            max_penwidth: 4.0,
+        }
+    }
+}
+
+/// Writes a `NetviewGraph` to a Graphviz DOT file.
+///
+/// Nodes are named by their identifier (`NodeLabel.id` or the node index) and
+/// filled with a color from `DOT_LABEL_PALETTE` assigned to each distinct label, or
+/// to each distinct value of a node attribute (e.g. country) with `color_attribute`.
+/// Edge `len` (for `neato` and `fdp`) and `penwidth` are scaled linearly over the
+/// range of edge distances in the graph, so that close neighbors are drawn short
+/// and thick. Nodes can optionally be grouped into cluster subgraphs.
+///
+/// # Arguments
+///
+/// * `graph`  - Reference to the graph to be written.
+/// * `path`   - Path to the output DOT file.
+/// * `config` - Tooltip, edge attribute and clustering options.
+///
+/// # Example
+///
+/// ```
+/// let config = DotConfig { tooltip: true, cluster: Some(DotCluster::Label), ..Default::default() };
+/// write_dot_to_file(&graph, Path::new("graph.dot"), &config).unwrap();
+/// ```
+pub fn write_dot_to_file(graph: &NetviewGraph, path: &Path, config: &DotConfig) -> Result<(), NetviewError> {
+    let mut writer = get_file_writer(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+
+    let dot = graph_to_dot(graph, config);
+    writer.write_all(dot.as_bytes()).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Renders a `NetviewGraph` as a DOT string, see `write_dot_to_file`.
+pub fn graph_to_dot(graph: &NetviewGraph, config: &DotConfig) -> String {
+
+    let node_ids: Vec<String> = graph.node_weights().map(node_id_or_index).collect();
+
+    // Assign palette colors to labels or attribute values in sorted order for stable output
+    let color_keys: Vec<Option<String>> = graph.node_weights().map(|node| match &config.color_attribute {
+        Some(name) => node.attributes.get(name).map(|value| value.to_string()),
+        None => node.label.clone()
+    }).collect();
+    let labels: BTreeSet<&String> = color_keys.iter().flatten().collect();
+    let label_colors: HashMap<&String, &str> = labels.iter().enumerate()
+        .map(|(i, label)| (*label, DOT_LABEL_PALETTE[i % DOT_LABEL_PALETTE.len()]))
+        .collect();
+
+    let mut dot = String::from("graph netview {\n");
+    dot.push_str("    node [shape=circle, style=filled, fontsize=8];\n");
+
+    let node_statement = |node: NodeIndex| -> String {
+        let node_label = &graph[node];
+        let id = &node_ids[node.index()];
+        let color = color_keys[node.index()].as_ref()
+            .and_then(|label| label_colors.get(label).copied())
+            .unwrap_or(DOT_UNLABELLED_COLOR);
+
+        let mut attributes = vec![
+            format!("label={}", dot_quote(id)),
+            format!("fillcolor={}", dot_quote(color)),
+        ];
+        if config.tooltip {
+            let mut tooltip = format!(
+                "{}\nlabel: {}\nconfidence: {:.4}",
+                id,
+                node_label.label.as_deref().unwrap_or("None"),
+                node_label.label_confidence
+            );
+            if let Some(community) = node_label.community {
+                tooltip.push_str(&format!("\ncommunity: {community}"));
+            }
+            for (name, value) in &node_label.attributes {
+                tooltip.push_str(&format!("\n{name}: {value}"));
+            }
+            attributes.push(format!("tooltip={}", dot_quote(&tooltip)));
+        }
+        format!("{} [{}];\n", dot_quote(id), attributes.join(", "))
+    };
+
+    match &config.cluster {
+        Some(cluster) => {
+            let mut clusters: BTreeMap<Option<String>, Vec<NodeIndex>> = BTreeMap::new();
+            for node in graph.node_indices() {
+                let key = match cluster {
+                    DotCluster::Label => graph[node].label.clone(),
+                    DotCluster::Community => graph[node].community.map(|c| format!("community {c}")),
+                };
+                clusters.entry(key).or_default().push(node);
+            }
+            for (i, (label, nodes)) in clusters.into_iter().enumerate() {
+                match label {
+                    Some(label) => {
+                        dot.push_str(&format!("    subgraph {} {{\n", dot_quote(&format!("cluster_{i}"))));
+                        dot.push_str(&format!("        label={};\n", dot_quote(&label)));
+                        for node in nodes {
+                            dot.push_str(&format!("        {}", node_statement(node)));
+                        }
+                        dot.push_str("    }\n");
+                    },
+                    None => {
+                        // Unlabelled or unassigned nodes are not clustered
+                        for node in nodes {
+                            dot.push_str(&format!("    {}", node_statement(node)));
+                        }
+                    }
+                }
+            }
+        },
+        None => {
+            for node in graph.node_indices() {
+                dot.push_str(&format!("    {}", node_statement(node)));
+            }
+        }
+    }
+
+    let (min_dist, max_dist) = graph.edge_weights().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), edge| {
+        (min.min(edge.weight), max.max(edge.weight))
+    });
+
+    for edge_ref in graph.edge_references() {
+        let source = dot_quote(&node_ids[edge_ref.source().index()]);
+        let target = dot_quote(&node_ids[edge_ref.target().index()]);
+
+        if config.edge_attributes {
+            // Scale distance to 0 - 1 over the range of edge distances in the graph
+            let scaled = if max_dist > min_dist {
+                (edge_ref.weight().weight - min_dist) / (max_dist - min_dist)
+            } else {
+                0.0
+            };
+            let len = config.min_len + scaled * (config.max_len - config.min_len);
+            let penwidth = config.max_penwidth - scaled * (config.max_penwidth - config.min_penwidth);
+            // No Graphviz `weight`, which pulls edges shorter with larger values and would invert distances
+            dot.push_str(&format!(
+                "    {} -- {} [len={:.4}, penwidth={:.4}];\n", source, target, len, penwidth
+            ));
+        } else {
+            dot.push_str(&format!("    {} -- {};\n", source, target));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+// Quote and escape a DOT identifier or attribute value
+fn dot_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Current schema version of `GraphJson` files
+///
+/// Files without a `version` field are read as version 0 (nodes and edges only).
+pub const GRAPH_JSON_VERSION: u32 = 1;
+
+/// Input file of a netview run with its SHA-256 checksum
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputFile {
+    pub name: String,       // Role of the input e.g. 'dist', 'afrac', 'ids'
+    pub path: PathBuf,
+    pub sha256: Option<String>,  // Not computed for stdin
+}
+
+/// Run metadata stored with a `GraphJson`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GraphMetadata {
+    pub netview_version: String,              // Version of netview that created the graph
+    pub created: String,                      // Creation time (RFC 3339, UTC)
+    pub k: Option<usize>,                     // K parameter for mutual nearest neighbor graph
+    pub distance_abstraction: Option<String>, // Distance abstraction used for nearest neighbors
+    pub edge_threshold: Option<f64>,          // Distance threshold for mutual nearest neighbor edges
+    pub config: Option<NetviewConfig>,        // Netview configuration of the run
+    pub inputs: Vec<InputFile>,               // Input files with checksums
+}
+
+impl GraphMetadata {
+    pub fn new() -> Self {
+        Self {
+            netview_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: chrono::Utc::now().to_rfc3339(),
+            k: None,
+            distance_abstraction: None,
+            edge_threshold: None,
+            config: None,
+            inputs: Vec::new(),
+        }
+    }
+    pub fn k(mut self, k: usize) -> Self {
+        self.k = Some(k);
+        self
+    }
+    pub fn distance_abstraction(mut self, abstraction: &str) -> Self {
+        self.distance_abstraction = Some(abstraction.to_string());
+        self
+    }
+    pub fn edge_threshold(mut self, threshold: Option<f64>) -> Self {
+        self.edge_threshold = threshold;
+        self
+    }
+    pub fn config(mut self, config: &NetviewConfig) -> Self {
+        self.config = Some(config.clone());
+        self
+    }
+    // Add an input file and compute its checksum, unless the input is stdin
+    pub fn input(mut self, name: &str, path: &Path) -> Result<Self, NetviewError> {
+        self.inputs.push(InputFile {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            sha256: if is_stdio(path) { None } else { Some(sha256_checksum(path)?) },
+        });
+        Ok(self)
+    }
+}
+
+impl Default for GraphMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GraphJson {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<GraphMetadata>,
     pub nodes: Vec<NodeLabel>,
     pub edges: Vec<EdgeLabel>
 }
@@ -271,7 +578,7 @@ pub struct GraphJson {
 impl GraphJson {
     // Create a new GraphJson from vectors of node and edge references
     pub fn new(nodes: Vec<NodeLabel>, edges: Vec<EdgeLabel>) -> Self {
-        Self { nodes, edges }
+        Self { version: GRAPH_JSON_VERSION, metadata: None, nodes, edges }
     }
 
     // Create a GraphJson from a petgraph::Graph
@@ -281,26 +588,68 @@ impl GraphJson {
         Self::new(nodes, edges)
     }
 
+    // Attach run metadata to the GraphJson
+    pub fn with_metadata(mut self, metadata: GraphMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
     // Write the GraphJson to a file as JSON
     pub fn write(&self, path: &Path) -> Result<(), NetviewError> {
-        let file = File::create(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
-        serde_json::to_writer_pretty(file, self)
+        let mut writer = get_file_writer(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+        serde_json::to_writer_pretty(&mut writer, self)
             .map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+        writer.flush()?;
         Ok(())
     }
 
-    // Read a GraphJson from a JSON file and deserialize it
+    // Read a GraphJson from a JSON file, deserialize and validate it
     pub fn read(path: &Path) -> Result<Self, NetviewError> {
-        let mut file = File::open(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+        let mut reader = get_file_reader(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
         let mut data = String::new();
-        file.read_to_string(&mut data).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+        reader.read_to_string(&mut data).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
         let graph_json: GraphJson = serde_json::from_str(&data)
-            .map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+            .map_err(|e| NetviewError::GraphDeserializationError(e.to_string()))?;
+        graph_json.validate()?;
         Ok(graph_json)
     }
 
-    // Convert GraphJson back into a petgraph::Graph
-    pub fn into_graph(self) -> Graph<NodeLabel, EdgeLabel, Undirected> {
+    /// Validates the schema version, edge endpoints, duplicate edges and duplicate node identifiers.
+    pub fn validate(&self) -> Result<(), NetviewError> {
+        if self.version > GRAPH_JSON_VERSION {
+            return Err(NetviewError::GraphVersionError(self.version, GRAPH_JSON_VERSION));
+        }
+
+        let mut ids = HashSet::new();
+        for node in &self.nodes {
+            if let Some(id) = &node.id {
+                if !ids.insert(id) {
+                    return Err(NetviewError::GraphDuplicateIdError(id.clone()));
+                }
+            }
+        }
+
+        let n = self.nodes.len();
+        let mut edges = HashSet::new();
+        for edge in &self.edges {
+            for endpoint in [edge.source, edge.target] {
+                if endpoint >= n {
+                    return Err(NetviewError::GraphEdgeEndpointError(edge.index, endpoint, n));
+                }
+            }
+            let pair = (edge.source.min(edge.target), edge.source.max(edge.target));
+            if !edges.insert(pair) {
+                return Err(NetviewError::GraphDuplicateEdgeError(pair.0, pair.1));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Validate and convert GraphJson back into a petgraph::Graph
+    pub fn into_graph(self) -> Result<Graph<NodeLabel, EdgeLabel, Undirected>, NetviewError> {
+        self.validate()?;
+
         let mut graph = Graph::<NodeLabel, EdgeLabel, Undirected>::new_undirected();
 
         // Create node indices in the graph
@@ -313,67 +662,272 @@ impl GraphJson {
             graph.add_edge(node_indices[edge.source], node_indices[edge.target], edge);
         }
 
-        graph
+        Ok(graph)
     }
 }
 
 
-/// Reads an edge list from a file and constructs a petgraph Graph.
+/// Cytoscape.js element data, a `{"data": {...}}` wrapper for nodes and edges
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CytoscapeElement {
+    pub data: serde_json::Map<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CytoscapeElements {
+    pub nodes: Vec<CytoscapeElement>,
+    pub edges: Vec<CytoscapeElement>
+}
+
+/// Graph in the Cytoscape.js elements format: `{"elements": {"nodes": [...], "edges": [...]}}`
+///
+/// Node `data` carries all `NodeLabel` attributes with the `id` field replaced
+/// by a string identifier (`NodeLabel.id` or the node index if no identifier
+/// is available). Edge `data` carries all `EdgeLabel` attributes with `source`
+/// and `target` referencing the string node identifiers and `id` set to the
+/// edge index prefixed with `e`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CytoscapeJson {
+    pub elements: CytoscapeElements
+}
+
+impl CytoscapeJson {
+    // Create a CytoscapeJson from a petgraph::Graph
+    pub fn from(graph: &Graph<NodeLabel, EdgeLabel, Undirected>) -> Result<Self, NetviewError> {
+
+        let node_ids: Vec<String> = graph.node_weights().map(node_id_or_index).collect();
+
+        let mut nodes = Vec::new();
+        for (node, id) in graph.node_weights().zip(node_ids.iter()) {
+            let mut data = cytoscape_data(node)?;
+            data.insert("id".to_string(), serde_json::Value::String(id.clone()));
+            
+            // Node attributes are flattened into the data for selectors and mappers
+            if let Some(serde_json::Value::Object(attributes)) = data.remove("attributes") {
+                for (name, value) in attributes {
+                    data.entry(name).or_insert(value);
+                }
+            }
+            nodes.push(CytoscapeElement { data });
+        }
+
+        let mut edges = Vec::new();
+        for edge_ref in graph.edge_references() {
+            let edge = edge_ref.weight();
+            let mut data = cytoscape_data(edge)?;
+            data.insert("id".to_string(), serde_json::Value::String(format!("e{}", edge.index)));
+            data.insert("source".to_string(), serde_json::Value::String(node_ids[edge_ref.source().index()].clone()));
+            data.insert("target".to_string(), serde_json::Value::String(node_ids[edge_ref.target().index()].clone()));
+            edges.push(CytoscapeElement { data });
+        }
+
+        Ok(Self { elements: CytoscapeElements { nodes, edges } })
+    }
+
+    // Write the CytoscapeJson to a file as JSON
+    pub fn write(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// String node identifier, falls back to the node index
+pub(crate) fn node_id_or_index(node: &NodeLabel) -> String {
+    node.id.clone().unwrap_or_else(|| node.index.to_string())
+}
+
+// Serialize a node or edge label into a Cytoscape.js data object
+fn cytoscape_data<T: Serialize>(value: &T) -> Result<serde_json::Map<String, serde_json::Value>, NetviewError> {
+    match serde_json::to_value(value).map_err(|e| NetviewError::GraphSerializationError(e.to_string()))? {
+        serde_json::Value::Object(map) => Ok(map),
+        _ => Err(NetviewError::GraphSerializationError("label is not a JSON object".to_string()))
+    }
+}
+
+
+/// Edge attribute columns of an edge list
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum EdgeAttribute {
+    Weight,
+    Af,
+    Ani,
+    Aai,
+}
+impl std::fmt::Display for EdgeAttribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            EdgeAttribute::Weight => "weight",
+            EdgeAttribute::Af => "af",
+            EdgeAttribute::Ani => "ani",
+            EdgeAttribute::Aai => "aai",
+        };
+        write!(f, "{}", output)
+    }
+}
+impl EdgeAttribute {
+    fn from_header(column: &str) -> Option<Self> {
+        match column {
+            "weight" => Some(EdgeAttribute::Weight),
+            "af" => Some(EdgeAttribute::Af),
+            "ani" => Some(EdgeAttribute::Ani),
+            "aai" => Some(EdgeAttribute::Aai),
+            _ => None
+        }
+    }
+    fn value(&self, edge: &EdgeLabel) -> Option<f64> {
+        match self {
+            EdgeAttribute::Weight => Some(edge.weight),
+            EdgeAttribute::Af => edge.af,
+            EdgeAttribute::Ani => edge.ani,
+            EdgeAttribute::Aai => edge.aai,
+        }
+    }
+}
+
+/// Options for writing edge lists
+#[derive(Clone, Debug, Default)]
+pub struct EdgeListConfig {
+    pub ids: bool,                          // Node identifiers instead of node indices as endpoints
+    pub attributes: Vec<EdgeAttribute>,     // Attribute columns written after the endpoints
+}
+
+/// Reads a tab-delimited edge list with header into a `NetviewGraph`.
+///
+/// The header determines how the edge list is read: endpoint columns are either
+/// `source` and `target` (node indices) or `source_id` and `target_id` (node identifiers),
+/// followed by any of the attribute columns `weight`, `af`, `ani` and `aai`. Empty
+/// attribute fields are read as missing values; a missing `weight` column defaults
+/// edge weights to 1.0.
+///
+/// With node indices, nodes `0..nodes` are created so that node indices match the
+/// written graph. An edge list does not record isolated nodes, so without `nodes` the
+/// node count is inferred as `max(index) + 1` and isolated nodes after the largest
+/// index are lost. With node identifiers, nodes are created in order of their first
+/// appearance in the edge list and isolated nodes are always lost.
+///
+/// Each undirected edge must appear once: a repeated or reversed edge is an error.
 ///
 /// # Arguments
 ///
-/// * `filename` - Path to the edge list file.
-/// * `has_weights` - If `true`, the third column of each row is treated as the edge weight.
-///                   If `false`, edges will have a default weight of 1.0.
+/// * `filename` - Path to the edge list file, e.g. written by `write_edgelist_to_file`.
+/// * `nodes` - Optional number of nodes of the graph, used only with node indices.
 ///
 /// # Returns
 ///
-/// A `Result` containing a `Graph<usize, f64, Undirected>`, where nodes are indexed by their positions in the edge list.
+/// A `Result` containing the `NetviewGraph`, or a `NetviewError::EdgeListParseError`
+/// naming the line number of a malformed header, malformed or duplicate edge, or a
+/// node index outside of `0..nodes`.
 ///
 /// # Example
 ///
 /// ```rust
-/// let graph = read_edgelist("edgelist.tsv", true).unwrap();
+/// let graph = read_edgelist(Path::new("graph.edges"), Some(100)).unwrap();
 /// ```
-pub fn read_edgelist(filename: &Path, has_weights: bool) -> Result<Graph<usize, f64, Undirected>, NetviewError> {
-    // Create a new undirected graph
-    let mut graph = Graph::<usize, f64, Undirected>::new_undirected();
-    
-    // A map to keep track of nodes and their corresponding NodeIndex in the graph
-    let mut node_map: HashMap<usize, NodeIndex> = HashMap::new();
-
-    // Open the edge list file
-    let file = File::open(filename).map_err(|_| NetviewError::GraphDeserializationError(filename.to_string_lossy().to_string()))?;
-    let reader = BufReader::new(file);
-
-    // Read the file line by line
-    for line in reader.lines() {
+pub fn read_edgelist(filename: &Path, nodes: Option<usize>) -> Result<NetviewGraph, NetviewError> {
+
+    let reader = get_file_reader(filename).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+    let mut lines = BufReader::new(reader).lines();
+
+    let header = match lines.next() {
+        Some(line) => line?,
+        None => return Err(NetviewError::EdgeListParseError(1, "missing header".to_string()))
+    };
+    let columns: Vec<&str> = header.split('\t').collect();
+
+    let ids = match (columns.first(), columns.get(1)) {
+        (Some(&"source"), Some(&"target")) => false,
+        (Some(&"source_id"), Some(&"target_id")) => true,
+        _ => return Err(NetviewError::EdgeListParseError(
+            1, "header must start with 'source' and 'target' or 'source_id' and 'target_id'".to_string()
+        ))
+    };
+    let attributes = columns[2..].iter().map(|column| {
+        EdgeAttribute::from_header(column).ok_or_else(|| {
+            NetviewError::EdgeListParseError(1, format!("unknown edge attribute column '{column}'"))
+        })
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    let mut graph = NetviewGraph::new_undirected();
+    let mut node_map: HashMap<String, NodeIndex> = HashMap::new();
+    let mut edges: Vec<(usize, usize, EdgeLabel)> = Vec::new();
+    let mut seen: HashSet<(usize, usize)> = HashSet::new();
+    let mut max_index: Option<usize> = None;
+
+    for (i, line) in lines.enumerate() {
+        let line_number = i + 2;
         let line = line?;
-
-        // Split the line by tab
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() < 2 {
-            continue; // Invalid line, skip
+        if line.trim().is_empty() {
+            continue;
         }
 
-        // Parse the node indices (assuming nodes are represented by usize)
-        let node1: usize = parts[0].parse().unwrap();
-        let node2: usize = parts[1].parse().unwrap();
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != columns.len() {
+            return Err(NetviewError::EdgeListParseError(
+                line_number, format!("expected {} fields but found {}", columns.len(), fields.len())
+            ));
+        }
 
-        // Parse the weight if `has_weights` is true, otherwise assign 1.0 as the default weight
-        let weight: f64 = if has_weights && parts.len() == 3 {
-            parts[2].parse().unwrap_or(1.0)
+        let (source, target) = if ids {
+            let mut node_index = |id: &str| -> usize {
+                node_map.entry(id.to_string()).or_insert_with(|| {
+                    let index = graph.node_count();
+                    graph.add_node(NodeLabel::new(index, Some(id.to_string())))
+                }).index()
+            };
+            (node_index(fields[0]), node_index(fields[1]))
         } else {
-            1.0
+            let parse_index = |field: &str| -> Result<usize, NetviewError> {
+                field.parse().map_err(|_| NetviewError::EdgeListParseError(
+                    line_number, format!("invalid node index '{field}'")
+                ))
+            };
+            let (source, target) = (parse_index(fields[0])?, parse_index(fields[1])?);
+            if let Some(nodes) = nodes {
+                if source.max(target) >= nodes {
+                    return Err(NetviewError::EdgeListParseError(
+                        line_number, format!("node index {} out of range for {nodes} nodes", source.max(target))
+                    ));
+                }
+            }
+            max_index = max_index.max(Some(source.max(target)));
+            (source, target)
         };
+        if !seen.insert((source.min(target), source.max(target))) {
+            return Err(NetviewError::EdgeListParseError(
+                line_number, format!("duplicate edge '{}' - '{}'", fields[0], fields[1])
+            ));
+        }
 
-        // Get or create NodeIndex for node1
-        let node1_index = *node_map.entry(node1).or_insert_with(|| graph.add_node(node1));
-        // Get or create NodeIndex for node2
-        let node2_index = *node_map.entry(node2).or_insert_with(|| graph.add_node(node2));
+        let mut edge = EdgeLabel::new(edges.len(), source, target, 1.0, None);
+        for (attribute, field) in attributes.iter().zip(&fields[2..]) {
+            let value = if field.is_empty() {
+                None
+            } else {
+                Some(field.parse::<f64>().map_err(|_| NetviewError::EdgeListParseError(
+                    line_number, format!("invalid value '{field}' in column '{attribute}'")
+                ))?)
+            };
+            match attribute {
+                EdgeAttribute::Weight => edge.weight = value.unwrap_or(1.0),
+                EdgeAttribute::Af => edge.af = value,
+                EdgeAttribute::Ani => edge.ani = value,
+                EdgeAttribute::Aai => edge.aai = value,
+            }
+        }
+        edges.push((source, target, edge));
+    }
 
-        // Add the edge between node1 and node2 with the weight
-        graph.add_edge(node1_index, node2_index, weight);
+    if !ids {
+        for index in 0..nodes.unwrap_or(max_index.map_or(0, |index| index + 1)) {
+            graph.add_node(NodeLabel::new(index, None));
+        }
+    }
+
+    for (source, target, edge) in edges {
+        graph.add_edge(NodeIndex::new(source), NodeIndex::new(target), edge);
     }
 
     Ok(graph)
@@ -402,7 +956,7 @@ pub fn read_edgelist(filename: &Path, has_weights: bool) -> Result<Graph<usize,
 /// write_adjacency_matrix_to_file(&matrix, "path/to/matrix.tsv").unwrap();
 /// ```
 pub fn write_adjacency_matrix_to_file(matrix: &Vec<Vec<f64>>, path: impl AsRef<Path>) -> Result<(), NetviewError> {
-    let file = File::create(path).map_err(|e| NetviewError::WriteError(e.to_string()))?;
+    let file = get_file_writer(path.as_ref()).map_err(|e| NetviewError::WriteError(e.to_string()))?;
     let mut wtr = WriterBuilder::new().delimiter(b'\t').from_writer(file);
 
     for row in matrix {
@@ -412,13 +966,15 @@ pub fn write_adjacency_matrix_to_file(matrix: &Vec<Vec<f64>>, path: impl AsRef<P
 }
 
 
-/// Writes the edge list to a file.
+/// Writes the edges of a graph to a tab-delimited edge list with header.
 ///
 /// # Arguments
 ///
-/// * `edgelist` - A `Vec<(usize, usize, f64)>` representing the edges (source, target, weight).
+/// * `graph` - Reference to the graph to be written.
 /// * `filename` - The file path where the edge list will be written.
-/// * `include_weights` - If `true`, includes the weights in the output; if `false`, only writes the source and target.
+/// * `config` - Whether to write node identifiers (`source_id`, `target_id`) instead of
+///   node indices (`source`, `target`) and which attribute columns to include.
+///   Missing attribute values are written as empty fields.
 ///
 /// # Returns
 ///
@@ -427,28 +983,230 @@ pub fn write_adjacency_matrix_to_file(matrix: &Vec<Vec<f64>>, path: impl AsRef<P
 /// # Example
 ///
 /// ```
-/// let edgelist = vec![(0, 1, 1.5), (1, 2, 2.5)];
-/// write_edgelist_to_file(&edgelist, "edgelist.txt", true).expect("Failed to write file");
+/// let config = EdgeListConfig { ids: true, attributes: vec![EdgeAttribute::Weight, EdgeAttribute::Af] };
+/// write_edgelist_to_file(&graph, Path::new("graph.edges"), &config).expect("Failed to write file");
 /// ```
-pub fn write_edgelist_to_file(edgelist: &Vec<(usize, usize, f64)>, filename: &Path, include_weights: bool) -> Result<(), NetviewError> {
-    // Step 1: Create or open the file
-    let mut file = File::create(filename).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
-
-    // Step 2: Iterate over the edge list and write each edge to the file
-    for (source, target, weight) in edgelist {
-        if include_weights {
-            // Write the edge as "source target weight\n"
-            writeln!(file, "{} {} {}", source, target, weight).map_err(|e| NetviewError::GraphSerializationError(e.to_string()))?;
+pub fn write_edgelist_to_file(graph: &NetviewGraph, filename: &Path, config: &EdgeListConfig) -> Result<(), NetviewError> {
+
+    let file = get_file_writer(filename).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+    let mut wtr = WriterBuilder::new().delimiter(b'\t').from_writer(file);
+
+    let mut header = match config.ids {
+        true => vec!["source_id".to_string(), "target_id".to_string()],
+        false => vec!["source".to_string(), "target".to_string()]
+    };
+    header.extend(config.attributes.iter().map(|attribute| attribute.to_string()));
+    wtr.write_record(&header)?;
+
+    for edge_ref in graph.edge_references() {
+        let (source, target) = if config.ids {
+            (node_id_or_index(&graph[edge_ref.source()]), node_id_or_index(&graph[edge_ref.target()]))
         } else {
-            // Write the edge as "source target\n" (no weight)
-            writeln!(file, "{} {}", source, target).map_err(|e| NetviewError::GraphSerializationError(e.to_string()))?;
+            (edge_ref.source().index().to_string(), edge_ref.target().index().to_string())
+        };
+
+        let mut record = vec![source, target];
+        record.extend(config.attributes.iter().map(|attribute| {
+            attribute.value(edge_ref.weight()).map(|value| value.to_string()).unwrap_or_default()
+        }));
+        wtr.write_record(&record)?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Edge values of a sparse adjacency matrix
+#[derive(Serialize, Deserialize, Clone, Debug, clap::ValueEnum)]
+pub enum SparseWeight {
+    Distance,       // Original distance from the distance matrix
+    Similarity,     // 1 - distance, with distance in percent scaled to 0 - 1
+    Af,             // Alignment fraction, required for all edges
+}
+
+/// Sparse matrix in coordinate format with zero-based indices
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseMatrix {
+    pub nrows: usize,
+    pub ncols: usize,
+    pub symmetric: bool,                    // Only the lower triangle is stored in `entries`
+    pub entries: Vec<(usize, usize, f64)>,  // Row, column, value
+}
+
+impl SparseMatrix {
+    // Convert into a dense matrix, mirroring entries of symmetric matrices
+    pub fn to_dense(&self) -> Vec<Vec<f64>> {
+        let mut matrix = vec![vec![0.0; self.ncols]; self.nrows];
+        for &(row, col, value) in &self.entries {
+            matrix[row][col] = value;
+            if self.symmetric {
+                matrix[col][row] = value;
+            }
         }
+        matrix
     }
+}
 
-    // Return Ok(()) if everything succeeded
+/// Converts a graph into a sparse symmetric adjacency matrix.
+///
+/// Entries are stored in the lower triangle (row >= column) with one entry per edge,
+/// so memory is O(kn) for mutual nearest neighbor graphs instead of O(n²).
+///
+/// # Arguments
+///
+/// * `graph`  - Input graph, matrix rows and columns are node indices.
+/// * `weight` - Edge value written to the matrix (distance, similarity or alignment fraction).
+/// * `distance_percent` - If distances are in percent (e.g. from `skani`), similarity is `1 - distance / 100`.
+///
+/// # Errors
+///
+/// Returns `NetviewError::GraphSerializationError` if the alignment fraction is
+/// selected and an edge has no alignment fraction.
+pub fn graph_to_sparse_matrix(graph: &NetviewGraph, weight: &SparseWeight, distance_percent: bool) -> Result<SparseMatrix, NetviewError> {
+
+    let n = graph.node_count();
+    let mut entries = Vec::with_capacity(graph.edge_count());
+
+    for edge_ref in graph.edge_references() {
+        let (source, target) = (edge_ref.source().index(), edge_ref.target().index());
+        let edge = edge_ref.weight();
+
+        let value = match weight {
+            SparseWeight::Distance => edge.weight,
+            SparseWeight::Similarity => match distance_percent {
+                true => 1.0 - edge.weight / 100.0,
+                false => 1.0 - edge.weight
+            },
+            SparseWeight::Af => edge.af.ok_or_else(|| NetviewError::GraphSerializationError(
+                format!("edge {} ({source} - {target}) has no alignment fraction", edge.index)
+            ))?
+        };
+        entries.push((source.max(target), source.min(target), value));
+    }
+
+    entries.sort_by_key(|&(row, col, _)| (col, row));  // column-major order
+
+    Ok(SparseMatrix { nrows: n, ncols: n, symmetric: true, entries })
+}
+
+/// Writes the adjacency matrix of a graph in Matrix Market coordinate format.
+///
+/// The matrix is written as `real symmetric` with one-based indices in the lower
+/// triangle, readable by `scipy.io.mmread` and `Matrix::readMM`. Node identifiers
+/// are not part of the format and can be written separately with `write_ids`.
+///
+/// # Example
+///
+/// ```
+/// write_matrix_market_to_file(&graph, Path::new("graph.mtx"), &SparseWeight::Similarity, true).unwrap();
+/// ```
+pub fn write_matrix_market_to_file(graph: &NetviewGraph, path: &Path, weight: &SparseWeight, distance_percent: bool) -> Result<(), NetviewError> {
+
+    let matrix = graph_to_sparse_matrix(graph, weight, distance_percent)?;
+
+    let mut writer = get_file_writer(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+
+    writeln!(writer, "%%MatrixMarket matrix coordinate real symmetric")?;
+    writeln!(writer, "% netview {} mutual nearest neighbor graph ({:?})", env!("CARGO_PKG_VERSION"), weight)?;
+    writeln!(writer, "{} {} {}", matrix.nrows, matrix.ncols, matrix.entries.len())?;
+    for (row, col, value) in &matrix.entries {
+        writeln!(writer, "{} {} {}", row + 1, col + 1, value)?;
+    }
+
+    writer.flush()?;
     Ok(())
 }
 
+/// Reads a sparse matrix in Matrix Market coordinate format.
+///
+/// Supports `real`, `integer` and `pattern` (values of 1.0) fields with `general`
+/// or `symmetric` symmetry. Indices are converted to zero-based indices.
+///
+/// # Errors
+///
+/// Returns `NetviewError::MatrixMarketParseError` naming the line number of a
+/// malformed header, size line or entry.
+pub fn read_matrix_market(path: &Path) -> Result<SparseMatrix, NetviewError> {
+
+    let reader = get_file_reader(path).map_err(|e| NetviewError::GraphFileError(e.to_string()))?;
+    let mut lines = BufReader::new(reader).lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    let header = match lines.next() {
+        Some((_, line)) => line?,
+        None => return Err(NetviewError::MatrixMarketParseError(1, "missing header".to_string()))
+    };
+    let banner: Vec<String> = header.split_whitespace().map(|s| s.to_lowercase()).collect();
+    if banner.len() != 5 || banner[0] != "%%matrixmarket" || banner[1] != "matrix" || banner[2] != "coordinate" {
+        return Err(NetviewError::MatrixMarketParseError(1, "expected '%%MatrixMarket matrix coordinate <field> <symmetry>'".to_string()));
+    }
+    let pattern = match banner[3].as_str() {
+        "real" | "integer" => false,
+        "pattern" => true,
+        field => return Err(NetviewError::MatrixMarketParseError(1, format!("unsupported field '{field}'")))
+    };
+    let symmetric = match banner[4].as_str() {
+        "general" => false,
+        "symmetric" => true,
+        symmetry => return Err(NetviewError::MatrixMarketParseError(1, format!("unsupported symmetry '{symmetry}'")))
+    };
+
+    let mut size: Option<(usize, usize, usize)> = None;
+    let mut size_line = 1;
+    let mut entries = Vec::new();
+
+    for (line_number, line) in lines {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let parse_index = |field: &str| -> Result<usize, NetviewError> {
+            field.parse().map_err(|_| NetviewError::MatrixMarketParseError(line_number, format!("invalid integer '{field}'")))
+        };
+
+        match size {
+            None => {
+                if fields.len() != 3 {
+                    return Err(NetviewError::MatrixMarketParseError(line_number, "expected size line '<rows> <columns> <entries>'".to_string()));
+                }
+                size = Some((parse_index(fields[0])?, parse_index(fields[1])?, parse_index(fields[2])?));
+                size_line = line_number;
+            },
+            Some((nrows, ncols, _)) => {
+                let expected = if pattern { 2 } else { 3 };
+                if fields.len() != expected {
+                    return Err(NetviewError::MatrixMarketParseError(
+                        line_number, format!("expected {expected} fields but found {}", fields.len())
+                    ));
+                }
+                let (row, col) = (parse_index(fields[0])?, parse_index(fields[1])?);
+                if row == 0 || col == 0 || row > nrows || col > ncols {
+                    return Err(NetviewError::MatrixMarketParseError(
+                        line_number, format!("entry ({row}, {col}) is outside of the {nrows} x {ncols} matrix")
+                    ));
+                }
+                let value = if pattern {
+                    1.0
+                } else {
+                    fields[2].parse::<f64>().map_err(|_| NetviewError::MatrixMarketParseError(
+                        line_number, format!("invalid value '{}'", fields[2])
+                    ))?
+                };
+                entries.push((row - 1, col - 1, value));
+            }
+        }
+    }
+
+    let (nrows, ncols, nnz) = size.ok_or_else(|| NetviewError::MatrixMarketParseError(1, "missing size line".to_string()))?;
+    if entries.len() != nnz {
+        // Count mismatches are reported on the size line which declares the number of entries
+        return Err(NetviewError::MatrixMarketParseError(size_line, format!("size line declares {nnz} entries but found {}", entries.len())));
+    }
+
+    Ok(SparseMatrix { nrows, ncols, symmetric, entries })
+}
 
 /// Converts a `Graph<N, E, Undirected>` into an adjacency matrix
 ///
@@ -560,6 +1318,7 @@ pub fn graph_to_edgelist(graph: &Graph<NodeLabel, EdgeLabel, Undirected>) -> Vec
 mod tests {
 
     use super::*;
+    use crate::metadata::AttributeValue;
 
     #[test]
     fn test_empty_matrix() {
@@ -712,6 +1471,282 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_cytoscape_json_string_ids() {
+        let mnn = vec![vec![1], vec![0, 2], vec![1]];
+        let dist = vec![vec![0.0, 1.0, 2.0], vec![1.0, 0.0, 3.0], vec![2.0, 3.0, 0.0]];
+        let ids = Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        let graph = convert_to_graph(&mnn, Some(&dist), None, ids, None).unwrap();
+
+        let cgraph = CytoscapeJson::from(&graph).unwrap();
+        let value = serde_json::to_value(&cgraph).unwrap();
+
+        let nodes = value["elements"]["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0]["data"]["id"], "a");
+        assert_eq!(nodes[0]["data"]["index"], 0);
+
+        let edges = value["elements"]["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[0]["data"]["id"], "e0");
+        assert_eq!(edges[0]["data"]["source"], "a");
+        assert_eq!(edges[0]["data"]["target"], "b");
+        assert_eq!(edges[0]["data"]["weight"], 1.0);
+    }
+
+    #[test]
+    fn test_cytoscape_json_flattens_attributes() {
+        let mnn = vec![vec![1], vec![0]];
+        let ids = Some(vec!["a".to_string(), "b".to_string()]);
+        let mut graph = convert_to_graph(&mnn, None, None, ids, None).unwrap();
+        graph[NodeIndex::new(0)].attributes.insert("country".to_string(), AttributeValue::Text("AU".to_string()));
+        graph[NodeIndex::new(0)].attributes.insert("id".to_string(), AttributeValue::Text("other".to_string()));
+
+        let value = serde_json::to_value(CytoscapeJson::from(&graph).unwrap()).unwrap();
+        let data = &value["elements"]["nodes"][0]["data"];
+        assert_eq!(data["country"], "AU");
+        assert_eq!(data["id"], "a");
+        assert!(data.get("attributes").is_none());
+    }
+
+    #[test]
+    fn test_cytoscape_json_index_fallback() {
+        let mnn = vec![vec![1], vec![0]];
+        let graph = convert_to_graph(&mnn, None, None, None, None).unwrap();
+
+        let cgraph = CytoscapeJson::from(&graph).unwrap();
+        assert_eq!(cgraph.elements.nodes[1].data["id"], "1");
+        assert_eq!(cgraph.elements.edges[0].data["target"], "1");
+    }
+
+    fn labelled_test_graph() -> NetviewGraph {
+        let mnn = vec![vec![1], vec![0, 2], vec![1]];
+        let dist = vec![vec![0.0, 1.0, 2.0], vec![1.0, 0.0, 3.0], vec![2.0, 3.0, 0.0]];
+        let ids = Some(vec!["a".to_string(), "b".to_string(), "c \"q\"".to_string()]);
+        let mut graph = convert_to_graph(&mnn, Some(&dist), None, ids, None).unwrap();
+        graph[NodeIndex::new(0)].label = Some("L1".to_string());
+        graph[NodeIndex::new(1)].label = Some("L2".to_string());
+        graph
+    }
+
+    #[test]
+    fn test_dot_nodes_and_edge_attributes() {
+        let graph = labelled_test_graph();
+        let dot = graph_to_dot(&graph, &DotConfig::default());
+
+        assert!(dot.starts_with("graph netview {"));
+        assert!(dot.contains(&format!("\"a\" [label=\"a\", fillcolor=\"{}\"];", DOT_LABEL_PALETTE[0])));
+        assert!(dot.contains(&format!("fillcolor=\"{}\"];", DOT_UNLABELLED_COLOR)));
+        assert!(dot.contains("\"c \\\"q\\\"\""));
+        // Closest edge is shortest and thickest, most distant edge is longest and thinnest
+        assert!(dot.contains("\"a\" -- \"b\" [len=0.5000, penwidth=4.0000];"));
+        assert!(dot.contains("\"b\" -- \"c \\\"q\\\"\" [len=3.0000, penwidth=0.5000];"));
+        assert!(!dot.contains("weight="));
+    }
+
+    #[test]
+    fn test_dot_clusters_and_tooltip() {
+        let graph = labelled_test_graph();
+        let config = DotConfig { tooltip: true, edge_attributes: false, cluster: Some(DotCluster::Label), ..Default::default() };
+        let dot = graph_to_dot(&graph, &config);
+
+        assert!(dot.contains("subgraph \"cluster_1\" {"));
+        assert!(dot.contains("label=\"L1\";"));
+        assert!(dot.contains("tooltip=\"a\\nlabel: L1\\nconfidence: 0.0000\""));
+        assert!(dot.contains("\"a\" -- \"b\";"));
+    }
+
+    #[test]
+    fn test_dot_community_clusters() {
+        let mut graph = labelled_test_graph();
+        graph[NodeIndex::new(0)].community = Some(0);
+        graph[NodeIndex::new(1)].community = Some(0);
+        let config = DotConfig { tooltip: true, cluster: Some(DotCluster::Community), ..Default::default() };
+        let dot = graph_to_dot(&graph, &config);
+
+        assert!(dot.contains("label=\"community 0\";"));
+        assert!(dot.contains("confidence: 0.0000\\ncommunity: 0\""));
+        assert_eq!(dot.matches("subgraph").count(), 1);
+    }
+
+    #[test]
+    fn test_dot_attribute_colors() {
+        let mut graph = labelled_test_graph();
+        graph[NodeIndex::new(1)].attributes.insert("country".to_string(), AttributeValue::Text("AU".to_string()));
+        graph[NodeIndex::new(2)].attributes.insert("country".to_string(), AttributeValue::Text("AU".to_string()));
+        let config = DotConfig { color_attribute: Some("country".to_string()), ..Default::default() };
+        let dot = graph_to_dot(&graph, &config);
+
+        // Labelled node without the attribute is unfilled, nodes of the same value share a color
+        assert!(dot.contains(&format!("\"a\" [label=\"a\", fillcolor=\"{}\"];", DOT_UNLABELLED_COLOR)));
+        assert!(dot.contains(&format!("\"b\" [label=\"b\", fillcolor=\"{}\"];", DOT_LABEL_PALETTE[0])));
+        assert_eq!(dot.matches(&format!("fillcolor=\"{}\"", DOT_LABEL_PALETTE[0])).count(), 2);
+    }
+
+    #[test]
+    fn test_edgelist_roundtrip_ids() {
+        let mnn = vec![vec![1], vec![0, 2], vec![1]];
+        let dist = vec![vec![0.0, 1.0, 2.0], vec![1.0, 0.0, 3.0], vec![2.0, 3.0, 0.0]];
+        let af = vec![vec![100.0, 90.0, 80.0], vec![90.0, 100.0, 70.0], vec![80.0, 70.0, 100.0]];
+        let ids = Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        let graph = convert_to_graph(&mnn, Some(&dist), Some(&af), ids, None).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.edges");
+        let config = EdgeListConfig { ids: true, attributes: vec![EdgeAttribute::Weight, EdgeAttribute::Af, EdgeAttribute::Ani] };
+        write_edgelist_to_file(&graph, &path, &config).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "source_id\ttarget_id\tweight\taf\tani\na\tb\t1\t90\t\nb\tc\t3\t70\t\n");
+
+        let read = read_edgelist(&path, None).unwrap();
+        assert_eq!(read.node_count(), 3);
+        assert_eq!(read.edge_count(), 2);
+        assert_eq!(read[NodeIndex::new(2)].id, Some("c".to_string()));
+        let edge = read.edge_weights().nth(1).unwrap();
+        assert_eq!((edge.source, edge.target, edge.weight, edge.af, edge.ani), (1, 2, 3.0, Some(70.0), None));
+    }
+
+    #[test]
+    fn test_edgelist_roundtrip_indices() {
+        let mnn = vec![vec![1], vec![0], vec![3], vec![2]];
+        let graph = convert_to_graph(&mnn, None, None, None, None).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.edges");
+        write_edgelist_to_file(&graph, &path, &EdgeListConfig::default()).unwrap();
+
+        let read = read_edgelist(&path, None).unwrap();
+        assert_eq!(read.node_count(), 4);
+        assert_eq!(read.edge_count(), 2);
+        assert_eq!(read[NodeIndex::new(3)].index, 3);
+
+        // Isolated nodes after the largest index are kept with the node count
+        let read = read_edgelist(&path, Some(6)).unwrap();
+        assert_eq!((read.node_count(), read.edge_count()), (6, 2));
+        assert!(matches!(read_edgelist(&path, Some(3)), Err(NetviewError::EdgeListParseError(3, _))));
+    }
+
+    #[test]
+    fn test_edgelist_malformed_line_number() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.edges");
+        std::fs::write(&path, "source\ttarget\tweight\n0\t1\t0.5\n1\tx\t0.5\n").unwrap();
+        assert!(matches!(read_edgelist(&path, None), Err(NetviewError::EdgeListParseError(3, _))));
+
+        std::fs::write(&path, "source\ttarget\tweight\n0\t1\n").unwrap();
+        assert!(matches!(read_edgelist(&path, None), Err(NetviewError::EdgeListParseError(2, _))));
+
+        // Repeated and reversed edges
+        std::fs::write(&path, "source\ttarget\n0\t1\n1\t2\n0\t1\n").unwrap();
+        assert!(matches!(read_edgelist(&path, None), Err(NetviewError::EdgeListParseError(4, _))));
+        std::fs::write(&path, "source_id\ttarget_id\na\tb\nb\ta\n").unwrap();
+        assert!(matches!(read_edgelist(&path, None), Err(NetviewError::EdgeListParseError(3, _))));
+
+        std::fs::write(&path, "source target\n").unwrap();
+        assert!(matches!(read_edgelist(&path, None), Err(NetviewError::EdgeListParseError(1, _))));
+    }
+
+    #[test]
+    fn test_graph_json_legacy_format() {
+        let json = r#"{"nodes": [
+            {"index": 0, "id": "a", "label": null, "label_confidence": 0.0},
+            {"index": 1, "id": "b", "label": null, "label_confidence": 0.0}
+        ], "edges": [
+            {"index": 0, "source": 0, "target": 1, "weight": 1.0, "ani": null, "aai": null, "af": null}
+        ]}"#;
+        let graph_json: GraphJson = serde_json::from_str(json).unwrap();
+        assert_eq!(graph_json.version, 0);
+        assert!(graph_json.metadata.is_none());
+        assert_eq!(graph_json.into_graph().unwrap().edge_count(), 1);
+    }
+
+    #[test]
+    fn test_graph_json_metadata_roundtrip() {
+        let graph = labelled_test_graph();
+        let metadata = GraphMetadata::new().k(2).distance_abstraction("euclidean").edge_threshold(Some(100.0));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.json");
+        GraphJson::from(&graph).with_metadata(metadata).write(&path).unwrap();
+
+        let read = GraphJson::read(&path).unwrap();
+        assert_eq!(read.version, GRAPH_JSON_VERSION);
+        let metadata = read.metadata.unwrap();
+        assert_eq!(metadata.k, Some(2));
+        assert_eq!(metadata.netview_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(metadata.edge_threshold, Some(100.0));
+    }
+
+    #[test]
+    fn test_graph_json_validation_errors() {
+        let nodes = vec![NodeLabel::new(0, Some("a".to_string())), NodeLabel::new(1, Some("b".to_string()))];
+
+        let graph_json = GraphJson::new(nodes.clone(), vec![EdgeLabel::new(0, 0, 2, 1.0, None)]);
+        assert!(matches!(graph_json.into_graph(), Err(NetviewError::GraphEdgeEndpointError(0, 2, 2))));
+
+        let edges = vec![EdgeLabel::new(0, 0, 1, 1.0, None), EdgeLabel::new(1, 1, 0, 1.0, None)];
+        let graph_json = GraphJson::new(nodes.clone(), edges);
+        assert!(matches!(graph_json.into_graph(), Err(NetviewError::GraphDuplicateEdgeError(0, 1))));
+
+        let duplicates = vec![NodeLabel::new(0, Some("a".to_string())), NodeLabel::new(1, Some("a".to_string()))];
+        let graph_json = GraphJson::new(duplicates, Vec::new());
+        assert!(matches!(graph_json.validate(), Err(NetviewError::GraphDuplicateIdError(_))));
+
+        let mut graph_json = GraphJson::new(nodes, Vec::new());
+        graph_json.version = GRAPH_JSON_VERSION + 1;
+        assert!(matches!(graph_json.validate(), Err(NetviewError::GraphVersionError(_, _))));
+    }
+
+    #[test]
+    fn test_matrix_market_roundtrip() {
+        let mnn = vec![vec![1], vec![0, 2], vec![1], vec![]];
+        let dist = vec![vec![0.0, 10.0, 20.0, 0.0], vec![10.0, 0.0, 30.0, 0.0], vec![20.0, 30.0, 0.0, 0.0], vec![0.0; 4]];
+        let graph = convert_to_graph(&mnn, Some(&dist), None, None, None).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.mtx");
+        write_matrix_market_to_file(&graph, &path, &SparseWeight::Similarity, true).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("%%MatrixMarket matrix coordinate real symmetric\n"));
+        assert!(content.contains("\n4 4 2\n2 1 0.9\n3 2 0.7\n"));
+
+        let matrix = read_matrix_market(&path).unwrap();
+        assert_eq!((matrix.nrows, matrix.ncols, matrix.symmetric), (4, 4, true));
+        assert_eq!(matrix.entries, vec![(1, 0, 0.9), (2, 1, 0.7)]);
+        assert_eq!(matrix.to_dense()[1][2], 0.7);
+
+        let dense = graph_to_adjacency_matrix(&graph, false).unwrap();
+        let sparse = graph_to_sparse_matrix(&graph, &SparseWeight::Distance, true).unwrap();
+        assert_eq!(sparse.to_dense(), dense);
+    }
+
+    #[test]
+    fn test_matrix_market_errors() {
+        let mnn = vec![vec![1], vec![0]];
+        let graph = convert_to_graph(&mnn, None, None, None, None).unwrap();
+        assert!(matches!(
+            graph_to_sparse_matrix(&graph, &SparseWeight::Af, true),
+            Err(NetviewError::GraphSerializationError(_))
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.mtx");
+        std::fs::write(&path, "%%MatrixMarket matrix coordinate real general\n% comment\n2 2 1\n3 1 1.0\n").unwrap();
+        assert!(matches!(read_matrix_market(&path), Err(NetviewError::MatrixMarketParseError(4, _))));
+
+        std::fs::write(&path, "%%MatrixMarket matrix array real general\n").unwrap();
+        assert!(matches!(read_matrix_market(&path), Err(NetviewError::MatrixMarketParseError(1, _))));
+
+        // Missing entries are reported on the size line
+        std::fs::write(&path, "%%MatrixMarket matrix coordinate real general\n% comment\n2 2 2\n2 1 1.0\n").unwrap();
+        assert!(matches!(read_matrix_market(&path), Err(NetviewError::MatrixMarketParseError(3, _))));
+
+        std::fs::write(&path, "%%MatrixMarket matrix coordinate pattern general\n2 2 1\n2 1\n").unwrap();
+        assert_eq!(read_matrix_market(&path).unwrap().entries, vec![(1, 0, 1.0)]);
+    }
+
     fn setup_test_graph() -> Graph<&'static str, i32> {
         let mut graph = Graph::new();
         let a = graph.add_node("A");
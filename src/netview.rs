@@ -1,17 +1,20 @@
 
 use serde::{Deserialize, Serialize};
 use petgraph::{Graph, Undirected};
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::ops::{Add, Sub};
 use std::cmp::Ordering;
 
-use crate::centrality::NodeCentrality;
-use crate::config::NetviewConfig;
+use crate::centrality::node_centrality;
+use crate::config::{LabelConfig, NetviewConfig};
 use crate::dist::{euclidean_distance_of_distances, parse_identifiers, parse_input_matrix, skani_distance_matrix, write_ids, write_matrix_to_file};
-use crate::mknn::{convert_to_graph, k_mutual_nearest_neighbors, write_graph_to_file, GraphFormat, GraphJson};
-use crate::label::{label_nodes, label_propagation, read_labels_from_file, write_graph_labels_to_file, VoteWeights};
+use crate::mknn::{convert_to_graph, k_mutual_nearest_neighbors, read_edgelist, write_graph_to_file, GraphFormat, GraphJson, GraphMetadata};
+use crate::label::{abstain_novel, add_label_column_attributes, label_column_path, label_nodes, label_propagation, label_spreading, write_label_columns_to_file, LabelColumns, LabelDistribution, SpreadingConfig, write_graph_labels_to_file, VoteReport, VoteReportFormat};
 use crate::error::NetviewError;
+use crate::evaluation::{leave_one_out, Evaluation};
+use crate::metadata::{read_labels_with_metadata, AttributeValue};
 use crate::utils::{concatenate_fasta_files, get_ids_from_fasta_files, write_tsv};
 
 pub type NetviewGraph = Graph<NodeLabel, EdgeLabel, Undirected>;
@@ -20,6 +23,31 @@ pub struct Netview {
     config: NetviewConfig
 }
 
+// Settings of the prediction workflow, the graph k and label settings are configured in `NetviewConfig`
+#[derive(Debug, Clone)]
+pub struct PredictOptions {
+    pub propagate_all: bool,                    // Propagate all labels across the graph topology, not only to the query genomes
+    pub basename: String,                       // Basename of output files in the output directory
+    pub threads: Option<usize>,
+    pub chunk_size: Option<usize>,
+    pub edge_threshold: Option<f64>,
+    pub vote_format: Option<VoteReportFormat>,  // Write the label vote breakdown of the query genomes
+    pub label_columns: LabelColumns,
+}
+impl Default for PredictOptions {
+    fn default() -> Self {
+        Self {
+            propagate_all: false,
+            basename: "netview".to_string(),
+            threads: None,
+            chunk_size: None,
+            edge_threshold: None,
+            vote_format: None,
+            label_columns: LabelColumns::default(),
+        }
+    }
+}
+
 pub struct NetviewPredictFiles {
     data: PathBuf,
     label: PathBuf,
@@ -33,6 +61,8 @@ pub struct NetviewPredictFiles {
     graph_predict: PathBuf,
     label_predict: PathBuf,
     label_predict_all: PathBuf,
+    label_votes: PathBuf,
+    label_probabilities: PathBuf,
 }
 impl NetviewPredictFiles {
     fn from(outdir: &PathBuf, name: String) -> Self {
@@ -49,6 +79,8 @@ impl NetviewPredictFiles {
             graph_predict: outdir.join(format!("{name}.predict.json")),
             label_predict: outdir.join(format!("{name}.predict.csv")),
             label_predict_all: outdir.join(format!("{name}.predict.all.csv")),
+            label_votes: outdir.join(format!("{name}.predict.votes")),
+            label_probabilities: outdir.join(format!("{name}.predict.probabilities.csv")),
         }
     }
 }
@@ -58,28 +90,48 @@ impl Netview {
         Self { config }
     }
     pub fn read_json_graph(&self, path: &Path) -> Result<NetviewGraph, NetviewError> {
-        Ok(GraphJson::read(path)?.into_graph())
+        GraphJson::read(path)?.into_graph()
+    }
+    pub fn write_json_graph(&self, graph: &NetviewGraph, path: &Path, metadata: Option<GraphMetadata>) -> Result<(), NetviewError> {
+        log::info!("Writing graph to: {}", path.display());
+        let graph_json = GraphJson::from(graph);
+        match metadata {
+            Some(metadata) => graph_json.with_metadata(metadata).write(path),
+            None => graph_json.write(path)
+        }
+    }
+    pub fn read_edgelist_graph(&self, path: &Path, nodes: Option<usize>) -> Result<NetviewGraph, NetviewError> {
+        read_edgelist(path, nodes)
     }
     pub fn predict(
         &self, 
         fasta: &Vec<PathBuf>, 
         db: &PathBuf, 
         labels: &PathBuf, 
-        k: usize, 
         outdir: &PathBuf,
-        propagate_all: bool,
-        basename: String,
-        threads: Option<usize>,
-        chunk_size: Option<usize>,
-        edge_threshold: Option<f64>
+        options: &PredictOptions
     ) -> Result<NetviewGraph, NetviewError> {
         
         if !outdir.exists() {
             std::fs::create_dir_all(&outdir)?;
         }
 
-        let files = NetviewPredictFiles::from(outdir, basename);
+        let PredictOptions { propagate_all, threads, chunk_size, edge_threshold, .. } = *options;
+        let (k, label_columns, vote_format) = (self.config.graph.k, &options.label_columns, &options.vote_format);
+
+        let files = NetviewPredictFiles::from(outdir, options.basename.clone());
         let fasta_ids = get_ids_from_fasta_files(&fasta)?; // seq ids for prediction
+
+        let mut metadata = GraphMetadata::new()
+            .k(k)
+            .distance_abstraction("euclidean")
+            .edge_threshold(edge_threshold)
+            .config(&self.config)
+            .input("db", db)?
+            .input("labels", labels)?;
+        for path in fasta {
+            metadata = metadata.input("fasta", path)?;
+        }
        
         concatenate_fasta_files(db, fasta, &files.data)?;
 
@@ -102,37 +154,81 @@ impl Netview {
             dist, k, Some(af), Some(ids), threads, chunk_size, edge_threshold
         )?;
 
-        let db_labels: Vec<Option<String>> = read_labels_from_file(&labels, false)?
-            .into_iter()
-            .map(|g| g.label)
-            .collect();
+        // Database labels of the selected label columns in order of database genomes,
+        // additional columns of the label file are typed node attributes
+        let (label_table, label_metadata) = read_labels_with_metadata(labels, label_columns)?;
+        label_metadata.apply(&mut graph);
 
         // Add unknowns to labels for prediction, this is a bit hacky right now...
-        let mut labels = db_labels.clone();
-        for _ in &fasta_ids { labels.push(None) };
+        let column_labels = |column: usize| -> Vec<Option<String>> {
+            let mut labels = label_table.values[column].clone();
+            for _ in &fasta_ids { labels.push(None) };
+            labels
+        };
+        let multiple_columns = label_table.columns.len() > 1;
+        if multiple_columns {
+            // Known labels of all label columns are node attributes of the input graph
+            for (column, _) in label_table.columns.iter().enumerate() {
+                let mut column_graph = graph.clone();
+                self.label_nodes(&mut column_graph, column_labels(column))?;
+                add_label_column_attributes(&mut graph, &label_table.columns[column], &column_graph);
+            }
+        }
 
-        self.label_nodes(&mut graph, labels)?;
+        self.label_nodes(&mut graph, column_labels(0))?;
         self.write_labels(&graph, &files.label, None)?;
 
-        write_graph_to_file(&graph, &files.graph_json, &GraphFormat::Json, true)?;
+        self.write_json_graph(&graph, &files.graph_json, Some(metadata.clone()))?;
         write_graph_to_file(&graph, &files.graph_edges, &GraphFormat::Edges, false)?;
         write_graph_to_file(&graph, &files.graph_edges_weight, &GraphFormat::Edges, true)?;
 
-        self.label_propagation(
-            &mut graph,
-            self.config.label.centrality_metric.clone(), 
-            self.config.label.max_iterations, 
-            self.config.label.vote_weights.clone(),
-            self.config.label.neighbor_centrality_vote, 
-            true, 
-            if propagate_all { None } else { Some(fasta_ids.clone()) }, 
-            false
-        );
-
-        write_graph_to_file(&graph, &files.graph_predict, &GraphFormat::Json, true)?;
+        // Labels are predicted separately for each label column
+        let mut predictions = Vec::new();
+        for (column, name) in label_table.columns.iter().enumerate() {
+
+            let output = |path: &PathBuf| if multiple_columns { label_column_path(path, name) } else { path.clone() };
+            
+            let mut column_graph = graph.clone();
+            self.label_nodes(&mut column_graph, column_labels(column))?;
+
+            let mut vote_report = vote_format.as_ref().map(|_| VoteReport::new());
+            let distribution = self.propagate_labels(
+                &mut column_graph,
+                if propagate_all { None } else { Some(fasta_ids.clone()) },
+                false,
+                &fasta_ids,
+                vote_report.as_mut()
+            )?;
+
+            if let Some(distribution) = distribution {
+                let path = output(&files.label_probabilities);
+                log::info!("Writing label probabilities to: {}", path.display());
+                distribution.write(&path, false)?;
+            }
+            if let (Some(report), Some(format)) = (vote_report, vote_format) {
+                let path = output(&files.label_votes.with_extension(format!("votes.{format}")));
+                log::info!("Writing label votes to: {}", path.display());
+                report.write(&path, format)?;
+            }
+            predictions.push((name.clone(), column_graph));
+        }
+
+        let (_, mut graph) = predictions[0].clone();
+        if multiple_columns {
+            for (name, prediction) in &predictions {
+                add_label_column_attributes(&mut graph, name, prediction);
+            }
+            self.write_json_graph(&graph, &files.graph_predict, Some(metadata))?;
+
+            log::info!("Writing predicted label columns to: {}", files.label_predict.display());
+            write_label_columns_to_file(&predictions, &files.label_predict, Some(fasta_ids), false)?;
+            write_label_columns_to_file(&predictions, &files.label_predict_all, None, false)?;
+        } else {
+            self.write_json_graph(&graph, &files.graph_predict, Some(metadata))?;
         
-        self.write_labels(&graph, &files.label_predict, Some(fasta_ids))?;
-        self.write_labels(&graph, &files.label_predict_all, None)?;
+            self.write_labels(&graph, &files.label_predict, Some(fasta_ids))?;
+            self.write_labels(&graph, &files.label_predict_all, None)?;
+        }
 
         Ok(graph)
 
@@ -246,29 +342,68 @@ impl Netview {
 
         Ok(mknn_graph)
     }
+    // Label propagation or label spreading with abstention as configured, returns 
+    // the label probabilities from label spreading
+    pub fn propagate_labels(
+        &self,
+        graph: &mut NetviewGraph,
+        query_nodes: Option<Vec<String>>,   // Optional subset of nodes by identifiers
+        propagate_on_unlabeled: bool,       // Whether to propagate only on nodes without a label (None)
+        targets: &[String],                 // Identifiers of nodes that may be assigned the novel label
+        mut vote_report: Option<&mut VoteReport>
+    ) -> Result<Option<LabelDistribution>, NetviewError> {
+
+        let distribution = match &self.config.label.spreading {
+            Some(spreading) => {
+                if vote_report.take().is_some() {
+                    log::warn!("Label votes are not available with label spreading");
+                }
+                Some(self.label_spreading(graph, spreading)?)
+            },
+            None => {
+                self.label_propagation(
+                    graph,
+                    &self.config.label,
+                    query_nodes, 
+                    propagate_on_unlabeled,
+                    vote_report
+                );
+                None
+            }
+        };
+
+        if self.config.label.abstention.is_enabled() {
+            abstain_novel(graph, targets, &self.config.label.abstention);
+        }
+
+        Ok(distribution)
+    }
     pub fn label_propagation(
         &self,
         graph: &mut NetviewGraph,
-        centrality_metric: NodeCentrality,
-        max_iterations: usize,
-        vote_weights: VoteWeights,
-        neighbor_centrality_vote: bool,
-        distance_percent: bool,             // If distance weight in percent scale to 0 - 1
+        config: &LabelConfig,               // Centrality, vote weights, iterations and updates of label propagation
         query_nodes: Option<Vec<String>>,   // Optional subset of nodes by identifiers
-        propagate_on_unlabeled: bool        // Whether to propagate only on nodes without a label (None)
+        propagate_on_unlabeled: bool,       // Whether to propagate only on nodes without a label (None)
+        vote_report: Option<&mut VoteReport> // Optional vote breakdown of the target nodes
     ) -> NetviewGraph {
 
         label_propagation(
             graph, 
-            centrality_metric,
-            max_iterations, 
-            vote_weights,
-            neighbor_centrality_vote,
-            distance_percent,
+            config,
             query_nodes,
-            propagate_on_unlabeled
+            propagate_on_unlabeled,
+            vote_report
         )
     }
+    pub fn label_spreading(&self, graph: &mut NetviewGraph, config: &SpreadingConfig) -> Result<LabelDistribution, NetviewError> {
+        let distribution = label_spreading(graph, config)?;
+        distribution.apply(graph);
+        Ok(distribution)
+    }
+    pub fn leave_one_out(&self, graph: &NetviewGraph, max_per_label: Option<usize>, seed: u64) -> Result<Evaluation, NetviewError> {
+        let centrality = node_centrality(graph, &self.config.label.centrality_metric);
+        leave_one_out(graph, &self.config.label, &centrality, max_per_label, seed)
+    }
     pub fn label_nodes(&self, graph: &mut NetviewGraph, labels: Vec<Option<String>>) -> Result<(), NetviewError> {
         log::info!("Labelling nodes on graph (n = {})", labels.len());
         label_nodes(graph, labels)
@@ -289,6 +424,22 @@ pub struct NodeLabel {
     pub id: Option<String>,              // Node identifier e.g. sample or sequence identifier from input matrix
     pub label: Option<String>,           // Label added or inferred downstream
     pub label_confidence: f64,           // Confidence in the label (0.0 to 1.0) computed downstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner_up_label: Option<String>, // Label with the second highest vote in label propagation
+    #[serde(default)]
+    pub runner_up_confidence: f64,       // Share of the total vote weight of the runner-up label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_probabilities: Option<BTreeMap<String, f64>>, // Probabilities over labels from label spreading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank_confidence: Option<Vec<f64>>, // Vote share of each rank of hierarchical labels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abstention: Option<String>,      // Reason for assigning the novel label instead of the predicted label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<[f64; 2]>,        // Node position e.g. first two principal coordinates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub community: Option<usize>,        // Community membership from community detection
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, AttributeValue>, // Typed metadata e.g. country, year, host, ST, MIC
 }
 
 impl NodeLabel {
@@ -306,7 +457,15 @@ impl NodeLabel {
             index,
             id,
             label: None,
-            label_confidence: 0.0
+            label_confidence: 0.0,
+            runner_up_label: None,
+            runner_up_confidence: 0.0,
+            label_probabilities: None,
+            rank_confidence: None,
+            abstention: None,
+            layout: None,
+            community: None,
+            attributes: BTreeMap::new()
         }
     }
 }
@@ -339,6 +498,14 @@ impl NodeLabelBuilder {
             index: self.index,
             label: self.label,
             label_confidence: self.label_confidence,
+            runner_up_label: None,
+            runner_up_confidence: 0.0,
+            label_probabilities: None,
+            rank_confidence: None,
+            abstention: None,
+            layout: None,
+            community: None,
+            attributes: BTreeMap::new(),
         }
     }
 }
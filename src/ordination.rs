@@ -0,0 +1,824 @@
+use std::io::Write;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::dist::make_symmetrical;
+use crate::error::NetviewError;
+use crate::mknn::{graph_to_sparse_matrix, node_id_or_index, SparseWeight};
+use crate::netview::NetviewGraph;
+use petgraph::unionfind::UnionFind;
+use crate::utils::get_file_writer;
+
+/// Settings for the eigensolver
+#[derive(Clone, Debug)]
+pub struct EigenConfig {
+    pub max_iterations: usize,  // Maximum number of Lanczos iterations (dimension of the Krylov subspace)
+    pub tolerance: f64,         // Convergence tolerance on the residual norm relative to the largest Ritz value
+    pub seed: u64,              // Seed for the random starting vector
+}
+
+impl Default for EigenConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: 1000,
+            tolerance: 1e-8,
+            seed: 42
+        }
+    }
+}
+
+// Operators up to this size are solved with a dense eigensolver
+const DENSE_MAX_SIZE: usize = 500;
+
+/// Computes the `m` largest eigenvalues and eigenvectors of a symmetric matrix.
+///
+/// Small matrices are solved exactly with a dense eigensolver (Householder reduction
+/// and implicit QL), larger matrices with Lanczos iteration and full reorthogonalization,
+/// which finds the largest algebraic eigenvalues even if the matrix has negative 
+/// eigenvalues of larger magnitude (e.g. non-Euclidean distances in PCoA).
+/// Eigenvalues are returned in decreasing order, eigenvectors have unit length and
+/// their largest absolute component is positive so that results are reproducible.
+///
+/// # Errors
+///
+/// Returns `NetviewError::EmptyMatrix` for empty input and `NetviewError::NonSquareMatrix`
+/// if the matrix is not square. Returns `NetviewError::InvalidDimensions` if `m` is zero
+/// or larger than the matrix. Returns `NetviewError::EigenConvergenceError` if not all 
+/// eigenpairs converged within the maximum number of iterations.
+pub fn top_eigenpairs(matrix: &[Vec<f64>], m: usize, config: &EigenConfig) -> Result<(Vec<f64>, Vec<Vec<f64>>), NetviewError> {
+
+    let n = matrix.len();
+    if n == 0 {
+        return Err(NetviewError::EmptyMatrix)
+    }
+    if matrix.iter().any(|row| row.len() != n) {
+        return Err(NetviewError::NonSquareMatrix)
+    }
+
+    symmetric_eigenpairs(|v| matrix.iter().map(|row| dot(row, v)).collect(), n, m, &[], config)
+}
+
+// Largest algebraic eigenpairs of a symmetric operator restricted to the complement of `deflate`,
+// which must be orthonormal vectors of the operator's invariant subspace
+fn symmetric_eigenpairs<F: Fn(&[f64]) -> Vec<f64>>(
+    operator: F,
+    n: usize,
+    m: usize,
+    deflate: &[Vec<f64>],
+    config: &EigenConfig
+) -> Result<(Vec<f64>, Vec<Vec<f64>>), NetviewError> {
+
+    if m == 0 || m + deflate.len() > n {
+        return Err(NetviewError::InvalidDimensions)
+    }
+
+    let (eigenvalues, eigenvectors) = if n <= DENSE_MAX_SIZE {
+        dense_eigenpairs(&operator, n, m, deflate)?
+    } else {
+        lanczos(&operator, n, m, deflate, config)?
+    };
+
+    // Sort by decreasing eigenvalue and fix the sign of each eigenvector
+    let mut pairs: Vec<(f64, Vec<f64>)> = eigenvalues.into_iter().zip(eigenvectors).collect();
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let (eigenvalues, eigenvectors) = pairs.into_iter()
+        .map(|(value, mut vector)| {
+            let max = vector.iter().cloned().fold(0.0, |acc: f64, x| if x.abs() > acc.abs() { x } else { acc });
+            if max < 0.0 {
+                vector.iter_mut().for_each(|x| *x = -*x);
+            }
+            (value, vector)
+        })
+        .unzip();
+
+    Ok((eigenvalues, eigenvectors))
+}
+
+// Dense eigendecomposition of the operator, deflated eigenvectors are shifted below the spectrum
+fn dense_eigenpairs<F: Fn(&[f64]) -> Vec<f64>>(
+    operator: &F,
+    n: usize,
+    m: usize,
+    deflate: &[Vec<f64>]
+) -> Result<(Vec<f64>, Vec<Vec<f64>>), NetviewError> {
+
+    // Columns of the operator, which are its rows since it is symmetric
+    let mut matrix: Vec<Vec<f64>> = (0..n)
+        .map(|j| {
+            let unit: Vec<f64> = (0..n).map(|i| if i == j { 1.0 } else { 0.0 }).collect();
+            operator(&unit)
+        })
+        .collect();
+
+    if !deflate.is_empty() {
+        let bound = matrix.iter().map(|row| row.iter().map(|v| v.abs()).sum::<f64>()).fold(0.0, f64::max) + 1.0;
+        for u in deflate {
+            for (row, ui) in matrix.iter_mut().zip(u) {
+                row.iter_mut().zip(u).for_each(|(x, uj)| *x -= 2.0 * bound * ui * uj);
+            }
+        }
+    }
+
+    let mut d = vec![0.0; n];
+    let mut e = vec![0.0; n];
+    householder_tridiagonal(&mut matrix, &mut d, &mut e);
+    tridiagonal_eigen(&mut d, &mut e, &mut matrix)?;
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|a, b| d[*b].total_cmp(&d[*a]));
+
+    Ok(order.into_iter().take(m)
+        .map(|j| (d[j], matrix.iter().map(|row| row[j]).collect()))
+        .unzip())
+}
+
+// Lanczos iteration with full reorthogonalization against the Krylov basis and the deflated
+// vectors, converged when the residual norms of the `m` largest Ritz pairs are below tolerance
+fn lanczos<F: Fn(&[f64]) -> Vec<f64>>(
+    operator: &F,
+    n: usize,
+    m: usize,
+    deflate: &[Vec<f64>],
+    config: &EigenConfig
+) -> Result<(Vec<f64>, Vec<Vec<f64>>), NetviewError> {
+
+    let max_steps = config.max_iterations.min(n - deflate.len());
+    if max_steps < m {
+        return Err(NetviewError::EigenConvergenceError(0, m, max_steps))
+    }
+
+    // Twice is enough (Kahan), deflated vectors and the basis are orthonormal
+    let orthogonalize = |w: &mut Vec<f64>, basis: &[Vec<f64>]| {
+        for _ in 0..2 {
+            for u in deflate.iter().chain(basis) {
+                let projection = dot(u, w);
+                w.iter_mut().zip(u).for_each(|(x, y)| *x -= projection * y);
+            }
+        }
+    };
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut random_vector = |basis: &[Vec<f64>]| -> Vec<f64> {
+        let mut v: Vec<f64> = (0..n).map(|_| rng.gen::<f64>() - 0.5).collect();
+        orthogonalize(&mut v, basis);
+        let norm = dot(&v, &v).sqrt();
+        v.iter_mut().for_each(|x| *x /= norm);
+        v
+    };
+
+    let mut basis = vec![random_vector(&[])];
+    let mut alpha: Vec<f64> = Vec::new();
+    let mut beta: Vec<f64> = Vec::new();
+    let mut scale = f64::MIN_POSITIVE;
+
+    loop {
+        let q = &basis[alpha.len()];
+        let mut w = operator(q);
+        alpha.push(dot(q, &w));
+        orthogonalize(&mut w, &basis);
+        let b = dot(&w, &w).sqrt();
+
+        let steps = alpha.len();
+        let invariant = b <= f64::EPSILON * scale.max(alpha[steps - 1].abs());
+        let last = steps == max_steps;
+
+        if steps >= m && (steps % 10 == 0 || invariant || last) {
+            // Ritz pairs from the eigendecomposition of the tridiagonal matrix
+            let mut d = alpha.clone();
+            let mut e: Vec<f64> = std::iter::once(0.0).chain(beta.iter().cloned()).collect();
+            let mut s: Vec<Vec<f64>> = (0..steps).map(|i| (0..steps).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+            tridiagonal_eigen(&mut d, &mut e, &mut s)?;
+
+            let mut order: Vec<usize> = (0..steps).collect();
+            order.sort_by(|a, c| d[*c].total_cmp(&d[*a]));
+            order.truncate(m);
+
+            scale = d.iter().map(|v| v.abs()).fold(f64::MIN_POSITIVE, f64::max);
+            let converged = order.iter()
+                .filter(|j| (b * s[steps - 1][**j]).abs() <= config.tolerance * scale)
+                .count();
+
+            if converged == m || invariant {
+                log::debug!("Lanczos eigensolver converged after {} iterations", steps);
+                return Ok(order.into_iter()
+                    .map(|j| {
+                        let mut vector = vec![0.0; n];
+                        for (k, q) in basis.iter().enumerate() {
+                            vector.iter_mut().zip(q).for_each(|(x, y)| *x += s[k][j] * y);
+                        }
+                        (d[j], vector)
+                    })
+                    .unzip())
+            }
+            if last {
+                return Err(NetviewError::EigenConvergenceError(converged, m, steps))
+            }
+        }
+
+        if invariant {
+            // Krylov subspace is exhausted before `m` Ritz pairs, continue with a new direction
+            beta.push(0.0);
+            let v = random_vector(&basis);
+            basis.push(v);
+        } else {
+            beta.push(b);
+            w.iter_mut().for_each(|x| *x /= b);
+            basis.push(w);
+        }
+    }
+}
+
+// Householder reduction of a symmetric matrix to tridiagonal form with diagonal `d` and 
+// subdiagonal `e[1..]`, the matrix is replaced by the orthogonal transformation (tred2 
+// of EISPACK in the formulation of JAMA)
+fn householder_tridiagonal(v: &mut [Vec<f64>], d: &mut [f64], e: &mut [f64]) {
+    let n = d.len();
+    d.copy_from_slice(&v[n - 1]);
+
+    for i in (1..n).rev() {
+        let scale: f64 = d[..i].iter().map(|x| x.abs()).sum();
+        let mut h = 0.0;
+        if scale == 0.0 {
+            e[i] = d[i - 1];
+            for j in 0..i {
+                d[j] = v[i - 1][j];
+                v[i][j] = 0.0;
+                v[j][i] = 0.0;
+            }
+        } else {
+            for x in d[..i].iter_mut() {
+                *x /= scale;
+                h += *x * *x;
+            }
+            let mut f = d[i - 1];
+            let mut g = if f > 0.0 { -h.sqrt() } else { h.sqrt() };
+            e[i] = scale * g;
+            h -= f * g;
+            d[i - 1] = f - g;
+            e[..i].iter_mut().for_each(|x| *x = 0.0);
+
+            for j in 0..i {
+                f = d[j];
+                v[j][i] = f;
+                g = e[j] + v[j][j] * f;
+                for k in j + 1..i {
+                    g += v[k][j] * d[k];
+                    e[k] += v[k][j] * f;
+                }
+                e[j] = g;
+            }
+            f = 0.0;
+            for j in 0..i {
+                e[j] /= h;
+                f += e[j] * d[j];
+            }
+            let hh = f / (h + h);
+            for j in 0..i {
+                e[j] -= hh * d[j];
+            }
+            for j in 0..i {
+                f = d[j];
+                g = e[j];
+                for k in j..i {
+                    v[k][j] -= f * e[k] + g * d[k];
+                }
+                d[j] = v[i - 1][j];
+                v[i][j] = 0.0;
+            }
+        }
+        d[i] = h;
+    }
+
+    // Accumulate the transformations
+    for i in 0..n - 1 {
+        v[n - 1][i] = v[i][i];
+        v[i][i] = 1.0;
+        let h = d[i + 1];
+        if h != 0.0 {
+            for k in 0..=i {
+                d[k] = v[k][i + 1] / h;
+            }
+            for j in 0..=i {
+                let g: f64 = (0..=i).map(|k| v[k][i + 1] * v[k][j]).sum();
+                for k in 0..=i {
+                    v[k][j] -= g * d[k];
+                }
+            }
+        }
+        for row in v[..=i].iter_mut() {
+            row[i + 1] = 0.0;
+        }
+    }
+    for j in 0..n {
+        d[j] = v[n - 1][j];
+        v[n - 1][j] = 0.0;
+    }
+    v[n - 1][n - 1] = 1.0;
+    e[0] = 0.0;
+}
+
+// Eigenvalues (`d`) and eigenvectors (columns of `v`, applied to the initial `v`) of a symmetric 
+// tridiagonal matrix with diagonal `d` and subdiagonal `e[1..]` by the implicit QL algorithm 
+// (tql2 of EISPACK in the formulation of JAMA)
+fn tridiagonal_eigen(d: &mut [f64], e: &mut [f64], v: &mut [Vec<f64>]) -> Result<(), NetviewError> {
+    let n = d.len();
+    for i in 1..n {
+        e[i - 1] = e[i];
+    }
+    e[n - 1] = 0.0;
+
+    let mut f = 0.0;
+    let mut tst1: f64 = 0.0;
+    for l in 0..n {
+        tst1 = tst1.max(d[l].abs() + e[l].abs());
+        let mut m = l;
+        while m < n - 1 && e[m].abs() > f64::EPSILON * tst1 {
+            m += 1;
+        }
+
+        let mut iterations = 0;
+        while m > l && e[l].abs() > f64::EPSILON * tst1 {
+            iterations += 1;
+            if iterations > 100 {
+                return Err(NetviewError::EigenConvergenceError(l, n, iterations))
+            }
+
+            let mut g = d[l];
+            let mut p = (d[l + 1] - g) / (2.0 * e[l]);
+            let mut r = p.hypot(1.0);
+            if p < 0.0 {
+                r = -r;
+            }
+            d[l] = e[l] / (p + r);
+            d[l + 1] = e[l] * (p + r);
+            let dl1 = d[l + 1];
+            let mut h = g - d[l];
+            for x in d[l + 2..].iter_mut() {
+                *x -= h;
+            }
+            f += h;
+
+            p = d[m];
+            let (mut c, mut c2, mut c3) = (1.0, 1.0, 1.0);
+            let el1 = e[l + 1];
+            let (mut s, mut s2) = (0.0, 0.0);
+            for i in (l..m).rev() {
+                c3 = c2;
+                c2 = c;
+                s2 = s;
+                g = c * e[i];
+                h = c * p;
+                r = p.hypot(e[i]);
+                e[i + 1] = s * r;
+                s = e[i] / r;
+                c = p / r;
+                p = c * d[i] - s * g;
+                d[i + 1] = h + s * (c * g + s * d[i]);
+                for row in v.iter_mut() {
+                    h = row[i + 1];
+                    row[i + 1] = s * row[i] + c * h;
+                    row[i] = c * row[i] - s * h;
+                }
+            }
+            p = -s * s2 * c3 * el1 * e[l] / dl1;
+            e[l] = s * p;
+            d[l] = c * p;
+        }
+        d[l] += f;
+        e[l] = 0.0;
+    }
+    Ok(())
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Sample coordinates from an ordination of the distance matrix
+#[derive(Clone, Debug)]
+pub struct Ordination {
+    pub ids: Vec<String>,               // Sample identifiers in order of matrix rows
+    pub coordinates: Vec<Vec<f64>>,     // Per-sample coordinates on each axis
+    pub eigenvalues: Vec<f64>,          // Eigenvalue of each axis
+    pub explained_variance: Vec<f64>,   // Fraction of total variance explained by each axis
+}
+
+impl Ordination {
+    /// Adds the first two coordinates to the nodes of a graph as layout.
+    ///
+    /// Coordinates are assigned by `NodeLabel.index`, a single axis is padded with zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetviewError::NodeLabelLengthError` if a node index is outside of the ordination.
+    pub fn apply_layout(&self, graph: &mut NetviewGraph) -> Result<(), NetviewError> {
+        for node in graph.node_weights_mut() {
+            let coordinates = self.coordinates
+                .get(node.index)
+                .ok_or(NetviewError::NodeLabelLengthError(self.coordinates.len()))?;
+            node.layout = Some([
+                coordinates.first().copied().unwrap_or(0.0),
+                coordinates.get(1).copied().unwrap_or(0.0)
+            ]);
+        }
+        Ok(())
+    }
+
+    /// Writes per-sample coordinates as TSV with columns `id`, `PC1`, `PC2`, ...
+    pub fn write_coordinates(&self, path: &Path) -> Result<(), NetviewError> {
+        write_coordinate_table(path, "PC", &self.ids, &self.coordinates, self.eigenvalues.len())
+    }
+
+    /// Writes eigenvalues and explained variance fractions as TSV with one row per axis.
+    pub fn write_variance(&self, path: &Path) -> Result<(), NetviewError> {
+        let mut writer = get_file_writer(path)?;
+
+        writeln!(writer, "axis\teigenvalue\texplained_variance")?;
+        for (i, (eigenvalue, explained)) in self.eigenvalues.iter().zip(&self.explained_variance).enumerate() {
+            writeln!(writer, "PC{}\t{eigenvalue}\t{explained}", i + 1)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// Coordinate table with an identifier column and one column per axis
+fn write_coordinate_table(path: &Path, prefix: &str, ids: &[String], coordinates: &[Vec<f64>], dimensions: usize) -> Result<(), NetviewError> {
+    let mut writer = get_file_writer(path)?;
+
+    let axes: Vec<String> = (1..=dimensions).map(|i| format!("{prefix}{i}")).collect();
+    writeln!(writer, "id\t{}", axes.join("\t"))?;
+    for (id, coordinates) in ids.iter().zip(coordinates) {
+        let values: Vec<String> = coordinates.iter().map(|c| c.to_string()).collect();
+        writeln!(writer, "{id}\t{}", values.join("\t"))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Principal coordinates analysis (classical multidimensional scaling) of a distance matrix.
+///
+/// The squared distances are double-centered, `B = -0.5 J D² J`, and the top `dimensions`
+/// eigenvectors of `B` are scaled by the square root of their eigenvalues. Axes with
+/// non-positive eigenvalues have zero coordinates. Explained variance is the eigenvalue
+/// divided by the trace of `B`, which is the total variance of the configuration.
+///
+/// # Arguments
+///
+/// * `distance_matrix` - Symmetrical or lower triangular distance matrix.
+/// * `ids` - Optional sample identifiers, otherwise samples are named by row index.
+/// * `dimensions` - Number of principal coordinates to compute.
+/// * `config` - Settings for the eigensolver.
+///
+/// # Errors
+///
+/// Returns `NetviewError::EigenConvergenceError` if the eigensolver did not converge.
+///
+/// # Example
+///
+/// ```
+/// use netview::ordination::{pcoa, EigenConfig};
+///
+/// let matrix = vec![vec![0.0], vec![3.0, 0.0], vec![4.0, 5.0, 0.0]];
+/// let ordination = pcoa(&matrix, None, 2, &EigenConfig::default()).unwrap();
+///
+/// assert_eq!(ordination.coordinates.len(), 3);
+/// ```
+pub fn pcoa(
+    distance_matrix: &Vec<Vec<f64>>,
+    ids: Option<Vec<String>>,
+    dimensions: usize,
+    config: &EigenConfig
+) -> Result<Ordination, NetviewError> {
+
+    let matrix = make_symmetrical(distance_matrix)?;
+    let n = matrix.len();
+
+    let ids = match ids {
+        Some(ids) if ids.len() != n => return Err(NetviewError::NodeLabelLengthError(n)),
+        Some(ids) => ids,
+        None => (0..n).map(|i| i.to_string()).collect()
+    };
+
+    let centered = double_center(&matrix);
+    let trace: f64 = (0..n).map(|i| centered[i][i]).sum();
+
+    let (eigenvalues, eigenvectors) = top_eigenpairs(&centered, dimensions.min(n), config)?;
+
+    let coordinates = (0..n)
+        .map(|i| {
+            eigenvalues.iter().zip(&eigenvectors)
+                .map(|(value, vector)| vector[i] * value.max(0.0).sqrt())
+                .collect()
+        })
+        .collect();
+
+    let explained_variance = eigenvalues.iter()
+        .map(|value| if trace > 0.0 { value / trace } else { 0.0 })
+        .collect();
+
+    Ok(Ordination { ids, coordinates, eigenvalues, explained_variance })
+}
+
+// Gower's double-centering of the squared distance matrix
+fn double_center(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = matrix.len() as f64;
+
+    let a: Vec<Vec<f64>> = matrix.iter()
+        .map(|row| row.iter().map(|d| -0.5 * d * d).collect())
+        .collect();
+
+    let row_means: Vec<f64> = a.iter().map(|row| row.iter().sum::<f64>() / n).collect();
+    let grand_mean = row_means.iter().sum::<f64>() / n;
+
+    a.iter().enumerate()
+        .map(|(i, row)| {
+            row.iter().enumerate()
+                .map(|(j, value)| value - row_means[i] - row_means[j] + grand_mean)
+                .collect()
+        })
+        .collect()
+}
+
+/// Per-node coordinates from a spectral embedding of a graph
+#[derive(Clone, Debug)]
+pub struct SpectralEmbedding {
+    pub ids: Vec<String>,               // Node identifiers (or indices) in graph order
+    pub coordinates: Vec<Vec<f64>>,     // Per-node coordinates on each axis
+    pub eigenvalues: Vec<f64>,          // Normalized Laplacian eigenvalue of each axis
+    pub components: usize,              // Number of connected components including isolated nodes
+}
+
+impl SpectralEmbedding {
+    /// Writes per-node coordinates as TSV with columns `id`, `SE1`, `SE2`, ...
+    pub fn write_coordinates(&self, path: &Path) -> Result<(), NetviewError> {
+        write_coordinate_table(path, "SE", &self.ids, &self.coordinates, self.eigenvalues.len())
+    }
+}
+
+/// Spectral embedding (Laplacian eigenmaps) of a graph.
+///
+/// Computes the eigenvectors of the smallest nontrivial eigenvalues of the normalized
+/// Laplacian `L = I - D^-1/2 W D^-1/2`. Each connected component contributes one trivial
+/// eigenvector with eigenvalue zero, these are projected out so that disconnected graphs
+/// do not fill the embedding with component indicators. Isolated nodes are placed at the
+/// origin. Coordinates are scaled by `D^-1/2` (random walk normalization).
+///
+/// # Arguments
+///
+/// * `graph` - Input graph, rows of the embedding are in node index order.
+/// * `dimensions` - Number of embedding dimensions.
+/// * `affinity` - Edge weights of `W`, unweighted adjacency if `None`. Distances are
+///   not suitable as affinities, use `SparseWeight::Similarity` or `SparseWeight::Af`.
+/// * `config` - Settings for the eigensolver.
+///
+/// # Errors
+///
+/// Returns `NetviewError::InvalidDimensions` if the graph has fewer nontrivial eigenvectors
+/// than `dimensions`, i.e. the number of nodes minus the number of components. Returns
+/// `NetviewError::EigenConvergenceError` if the eigensolver did not converge.
+pub fn spectral_embedding(
+    graph: &NetviewGraph,
+    dimensions: usize,
+    affinity: Option<&SparseWeight>,
+    config: &EigenConfig
+) -> Result<SpectralEmbedding, NetviewError> {
+
+    let n = graph.node_count();
+    if n == 0 {
+        return Err(NetviewError::EmptyMatrix)
+    }
+
+    let entries = match affinity {
+        Some(weight) => graph_to_sparse_matrix(graph, weight, true)?.entries,
+        None => graph_to_sparse_matrix(graph, &SparseWeight::Distance, true)?.entries
+            .into_iter()
+            .map(|(i, j, _)| (i, j, 1.0))
+            .collect()
+    };
+
+    let mut neighbors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    let mut components = UnionFind::new(n);
+    for (i, j, w) in entries {
+        let w = w.max(0.0);
+        neighbors[i].push((j, w));
+        neighbors[j].push((i, w));
+        components.union(i, j);
+    }
+
+    let degree: Vec<f64> = neighbors.iter().map(|row| row.iter().map(|(_, w)| w).sum()).collect();
+    let inv_sqrt_degree: Vec<f64> = degree.iter().map(|d| if *d > 0.0 { 1.0 / d.sqrt() } else { 0.0 }).collect();
+
+    // Null space of the normalized Laplacian: D^1/2 1 on each component, unit vectors on isolated nodes
+    let labels = components.into_labeling();
+    let mut roots: Vec<usize> = labels.clone();
+    roots.sort_unstable();
+    roots.dedup();
+
+    let deflate: Vec<Vec<f64>> = roots.iter()
+        .map(|root| {
+            let mut vector: Vec<f64> = (0..n)
+                .map(|i| match (labels[i] == *root, degree[i] > 0.0) {
+                    (true, true) => degree[i].sqrt(),
+                    (true, false) => 1.0,
+                    _ => 0.0
+                })
+                .collect();
+            let norm = dot(&vector, &vector).sqrt();
+            vector.iter_mut().for_each(|x| *x /= norm);
+            vector
+        })
+        .collect();
+
+    log::debug!("Spectral embedding of graph with {} nodes and {} components", n, roots.len());
+
+    // Largest eigenvalues of D^-1/2 W D^-1/2 in [-1, 1] are the smallest of L
+    let operator = |v: &[f64]| -> Vec<f64> {
+        neighbors.iter().enumerate()
+            .map(|(i, row)| {
+                inv_sqrt_degree[i] * row.iter().map(|(j, w)| w * inv_sqrt_degree[*j] * v[*j]).sum::<f64>()
+            })
+            .collect()
+    };
+    let (eigenvalues, eigenvectors) = symmetric_eigenpairs(operator, n, dimensions, &deflate, config)?;
+
+    let coordinates = (0..n)
+        .map(|i| eigenvectors.iter().map(|vector| vector[i] * inv_sqrt_degree[i]).collect())
+        .collect();
+
+    Ok(SpectralEmbedding {
+        ids: graph.node_weights().map(node_id_or_index).collect(),
+        coordinates,
+        eigenvalues: eigenvalues.iter().map(|mu| 1.0 - mu).collect(),
+        components: roots.len()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mknn::convert_to_graph;
+
+    // Points (0, 0), (3, 0), (0, 4) in the plane
+    fn triangle() -> Vec<Vec<f64>> {
+        vec![vec![0.0, 3.0, 4.0], vec![3.0, 0.0, 5.0], vec![4.0, 5.0, 0.0]]
+    }
+
+    fn euclidean(a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt()
+    }
+
+    #[test]
+    fn pcoa_recovers_euclidean_distances() {
+        let matrix = triangle();
+        let ordination = pcoa(&matrix, None, 2, &EigenConfig::default()).unwrap();
+
+        for (i, row) in matrix.iter().enumerate() {
+            for (j, expected) in row.iter().enumerate() {
+                let d = euclidean(&ordination.coordinates[i], &ordination.coordinates[j]);
+                assert!((d - expected).abs() < 1e-6, "distance ({i}, {j}) is {d}");
+            }
+        }
+        assert!(ordination.eigenvalues[0] >= ordination.eigenvalues[1]);
+        assert!((ordination.explained_variance.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn top_eigenpairs_with_negative_eigenvalues() {
+        // Eigenvalues 1, -3 and 2: the largest algebraic eigenvalue comes first
+        let matrix = vec![vec![1.0, 0.0, 0.0], vec![0.0, -3.0, 0.0], vec![0.0, 0.0, 2.0]];
+        let (values, vectors) = top_eigenpairs(&matrix, 2, &EigenConfig::default()).unwrap();
+
+        assert!((values[0] - 2.0).abs() < 1e-6);
+        assert!((values[1] - 1.0).abs() < 1e-6);
+        assert!((vectors[0][2] - 1.0).abs() < 1e-6);
+
+        assert!(matches!(top_eigenpairs(&matrix, 4, &EigenConfig::default()), Err(NetviewError::InvalidDimensions)));
+    }
+
+    #[test]
+    fn lanczos_agrees_with_dense_eigensolver() {
+        // Random symmetric matrix with entries in [-1, 1]
+        let mut rng = StdRng::seed_from_u64(7);
+        let n = 80;
+        let lower: Vec<f64> = (0..n * (n + 1) / 2).map(|_| rng.gen::<f64>() * 2.0 - 1.0).collect();
+        let matrix: Vec<Vec<f64>> = (0..n)
+            .map(|i| (0..n).map(|j| lower[i.max(j) * (i.max(j) + 1) / 2 + i.min(j)]).collect())
+            .collect();
+        let operator = |v: &[f64]| matrix.iter().map(|row| dot(row, v)).collect::<Vec<f64>>();
+
+        let (dense_values, dense_vectors) = dense_eigenpairs(&operator, n, 3, &[]).unwrap();
+        let (values, vectors) = lanczos(&operator, n, 3, &[], &EigenConfig::default()).unwrap();
+
+        for i in 0..3 {
+            assert!((dense_values[i] - values[i]).abs() < 1e-8);
+            assert!((dot(&dense_vectors[i], &vectors[i]).abs() - 1.0).abs() < 1e-6);
+        }
+
+        // Not enough iterations for three eigenpairs
+        let config = EigenConfig { max_iterations: 3, ..Default::default() };
+        assert!(matches!(lanczos(&operator, n, 3, &[], &config), Err(NetviewError::EigenConvergenceError(_, 3, _))));
+    }
+
+    #[test]
+    fn pcoa_recovers_euclidean_distances_of_large_matrix() {
+        // Points on a grid in the plane, larger than the dense eigensolver limit
+        let points: Vec<Vec<f64>> = (0..DENSE_MAX_SIZE + 100).map(|i| vec![(i % 30) as f64, (i / 30) as f64 * 0.5]).collect();
+        let matrix: Vec<Vec<f64>> = points.iter().map(|a| points.iter().map(|b| euclidean(a, b)).collect()).collect();
+
+        let ordination = pcoa(&matrix, None, 2, &EigenConfig::default()).unwrap();
+        for (i, j) in [(0, 1), (0, 599), (17, 342), (250, 251)] {
+            let distance = euclidean(&ordination.coordinates[i], &ordination.coordinates[j]);
+            assert!((distance - matrix[i][j]).abs() < 1e-6);
+        }
+        assert!((ordination.explained_variance.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn spectral_embedding_separates_communities() {
+        // Two triangles joined by a single edge (2 - 3) and an isolated node 6
+        let mnn = vec![vec![1, 2], vec![0, 2], vec![0, 1, 3], vec![2, 4, 5], vec![3, 5], vec![3, 4], vec![]];
+        let graph = convert_to_graph(&mnn, None, None, None, None).unwrap();
+
+        let embedding = spectral_embedding(&graph, 1, None, &EigenConfig::default()).unwrap();
+        assert_eq!(embedding.components, 2);
+        assert_eq!(embedding.ids, vec!["0", "1", "2", "3", "4", "5", "6"]);
+
+        let x: Vec<f64> = embedding.coordinates.iter().map(|c| c[0]).collect();
+        assert!(x[..3].iter().all(|v| v.signum() == x[0].signum()));
+        assert!(x[3..6].iter().all(|v| v.signum() == -x[0].signum()));
+        assert_eq!(x[6], 0.0);
+        assert!(embedding.eigenvalues[0] > 0.0 && embedding.eigenvalues[0] < 1.0);
+    }
+
+    #[test]
+    fn spectral_embedding_disconnected_components() {
+        // Two disconnected edges: the only nontrivial eigenvalues are 2 (bipartite edges)
+        let mnn = vec![vec![1], vec![0], vec![3], vec![2]];
+        let graph = convert_to_graph(&mnn, None, None, None, None).unwrap();
+
+        let embedding = spectral_embedding(&graph, 2, None, &EigenConfig::default()).unwrap();
+        assert_eq!(embedding.components, 2);
+        for value in &embedding.eigenvalues {
+            assert!((value - 2.0).abs() < 1e-6);
+        }
+        assert!(matches!(spectral_embedding(&graph, 3, None, &EigenConfig::default()), Err(NetviewError::InvalidDimensions)));
+    }
+
+    #[test]
+    fn spectral_embedding_of_large_graph() {
+        // Two random communities of 300 nodes joined by three edges, larger than the dense eigensolver limit
+        let mut rng = StdRng::seed_from_u64(11);
+        let n = 600;
+        let mut mnn: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let connect = |a: usize, b: usize, mnn: &mut Vec<Vec<usize>>| {
+            if a != b && !mnn[a].contains(&b) {
+                mnn[a].push(b);
+                mnn[b].push(a);
+            }
+        };
+        for i in 0..n {
+            let offset = if i < 300 { 0 } else { 300 };
+            connect(i, offset + (i + 1) % 300, &mut mnn);
+            for _ in 0..3 {
+                connect(i, offset + rng.gen_range(0..300), &mut mnn);
+            }
+        }
+        for (a, b) in [(0, 300), (100, 400), (200, 500)] {
+            connect(a, b, &mut mnn);
+        }
+        let graph = convert_to_graph(&mnn, None, None, None, None).unwrap();
+
+        let embedding = spectral_embedding(&graph, 2, None, &EigenConfig::default()).unwrap();
+        assert_eq!(embedding.components, 1);
+        assert!(embedding.eigenvalues[0] > 0.0 && embedding.eigenvalues[0] < embedding.eigenvalues[1]);
+
+        let x: Vec<f64> = embedding.coordinates.iter().map(|c| c[0]).collect();
+        assert!(x[..300].iter().all(|v| v.signum() == x[0].signum()));
+        assert!(x[300..].iter().all(|v| v.signum() == -x[0].signum()));
+
+        let config = EigenConfig { max_iterations: 5, ..Default::default() };
+        assert!(matches!(spectral_embedding(&graph, 2, None, &config), Err(NetviewError::EigenConvergenceError(..))));
+    }
+
+    #[test]
+    fn pcoa_layout_on_graph() {
+        let ordination = pcoa(&triangle(), Some(vec!["a".into(), "b".into(), "c".into()]), 2, &EigenConfig::default()).unwrap();
+
+        let mnn = vec![vec![1], vec![0], vec![]];
+        let mut graph = convert_to_graph(&mnn, Some(&triangle()), None, None, None).unwrap();
+        ordination.apply_layout(&mut graph).unwrap();
+
+        for node in graph.node_weights() {
+            let layout = node.layout.unwrap();
+            assert_eq!(layout[0], ordination.coordinates[node.index][0]);
+            assert_eq!(layout[1], ordination.coordinates[node.index][1]);
+        }
+
+        assert!(matches!(pcoa(&triangle(), Some(vec!["a".into()]), 2, &EigenConfig::default()), Err(NetviewError::NodeLabelLengthError(3))));
+    }
+}
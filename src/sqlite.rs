@@ -0,0 +1,502 @@
+// SQLite export of a netview run for queries from lab information systems
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, Transaction};
+use serde::{Deserialize, Serialize};
+
+use crate::config::NetviewConfig;
+use crate::dist::make_symmetrical;
+use crate::error::NetviewError;
+use crate::label::format_rank_confidence;
+use crate::metadata::{attribute_columns, AttributeType, AttributeValue};
+use crate::mknn::GraphMetadata;
+use crate::netview::NetviewGraph;
+
+/// Fixed columns of the samples table, node attributes are added as columns
+const SAMPLE_COLUMNS: [&str; 12] = [
+    "node_index", "id", "label", "label_confidence", "runner_up_label", "runner_up_confidence",
+    "rank_confidence", "abstention", "label_probabilities", "community", "layout_x", "layout_y"
+];
+
+const SCHEMA: &str = "
+CREATE TABLE samples (
+    node_index INTEGER PRIMARY KEY,
+    id TEXT,
+    label TEXT,
+    label_confidence REAL NOT NULL,
+    runner_up_label TEXT,
+    runner_up_confidence REAL,
+    rank_confidence TEXT,
+    abstention TEXT,
+    label_probabilities TEXT,
+    community INTEGER,
+    layout_x REAL,
+    layout_y REAL
+);
+CREATE TABLE edges (
+    edge_index INTEGER PRIMARY KEY,
+    source INTEGER NOT NULL REFERENCES samples (node_index),
+    target INTEGER NOT NULL REFERENCES samples (node_index),
+    source_id TEXT,
+    target_id TEXT,
+    weight REAL NOT NULL,
+    ani REAL,
+    aai REAL,
+    af REAL
+);
+CREATE TABLE distances (
+    i INTEGER NOT NULL REFERENCES samples (node_index),
+    j INTEGER NOT NULL REFERENCES samples (node_index),
+    i_id TEXT,
+    j_id TEXT,
+    distance REAL NOT NULL,
+    af REAL,
+    mknn INTEGER NOT NULL,
+    PRIMARY KEY (i, j)
+);
+CREATE VIEW distance_pairs AS
+    SELECT i_id AS query, j_id AS reference, distance, af, mknn FROM distances
+    UNION ALL
+    SELECT j_id AS query, i_id AS reference, distance, af, mknn FROM distances;
+CREATE TABLE parameters (
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
+CREATE TABLE inputs (
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    sha256 TEXT
+);
+CREATE INDEX samples_id ON samples (id);
+CREATE INDEX distances_i_id ON distances (i_id);
+CREATE INDEX distances_j_id ON distances (j_id);
+";
+
+/// Pairs of samples written to the `distances` table
+#[derive(Serialize, Deserialize, Clone, Debug, clap::ValueEnum)]
+pub enum DistancePairs {
+    All,
+    Mknn,
+    None
+}
+
+/// Exports a netview run to a single SQLite database.
+///
+/// The database contains the tables `samples` (nodes with labels and confidences),
+/// `edges` (all `EdgeLabel` fields), `distances` (all pairs `i < j` or only mutual
+/// nearest neighbor pairs), `parameters` (flattened `NetviewConfig` and run metadata)
+/// and `inputs` (input files with checksums). The view `distance_pairs` lists each
+/// pair in both directions, so that neighbors of a query are a single query:
+///
+/// ```sql
+/// SELECT reference, distance FROM distance_pairs WHERE query = 'X' AND distance <= 0.5;
+/// ```
+///
+/// # Example
+///
+/// ```
+/// SqliteExporter::new(&graph)
+///     .distances(&dist, Some(&af), DistancePairs::All)
+///     .config(&config)
+///     .write(Path::new("netview.sqlite"))?;
+/// ```
+pub struct SqliteExporter<'a> {
+    graph: &'a NetviewGraph,
+    distances: Option<&'a Vec<Vec<f64>>>,
+    af: Option<&'a Vec<Vec<f64>>>,
+    pairs: DistancePairs,
+    config: Option<&'a NetviewConfig>,
+    metadata: Option<&'a GraphMetadata>,
+}
+
+impl<'a> SqliteExporter<'a> {
+    pub fn new(graph: &'a NetviewGraph) -> Self {
+        Self {
+            graph,
+            distances: None,
+            af: None,
+            pairs: DistancePairs::None,
+            config: None,
+            metadata: None,
+        }
+    }
+    // Distance and alignment fraction matrices in order of node indices
+    pub fn distances(mut self, distances: &'a Vec<Vec<f64>>, af: Option<&'a Vec<Vec<f64>>>, pairs: DistancePairs) -> Self {
+        self.distances = Some(distances);
+        self.af = af;
+        self.pairs = pairs;
+        self
+    }
+    pub fn config(mut self, config: &'a NetviewConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+    pub fn metadata(mut self, metadata: &'a GraphMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    // Write the database, an existing database at the path is replaced
+    pub fn write(&self, path: &Path) -> Result<(), NetviewError> {
+
+        log::info!("Writing SQLite database to: {}", path.display());
+
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+
+        transaction.execute_batch(SCHEMA)?;
+
+        self.insert_samples(&transaction)?;
+        self.insert_edges(&transaction)?;
+        self.insert_distances(&transaction)?;
+        self.insert_parameters(&transaction)?;
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn insert_samples(&self, transaction: &Transaction) -> Result<(), NetviewError> {
+
+        // Node attributes are added as typed columns of the samples table, column
+        // names are case-insensitive in SQLite so the first of the names differing
+        // only in case is written
+        let mut columns: Vec<(String, AttributeType)> = Vec::new();
+        for (name, attribute_type) in attribute_columns(self.graph) {
+            if SAMPLE_COLUMNS.iter().any(|column| column.eq_ignore_ascii_case(&name)) {
+                log::warn!("Node attribute '{name}' is not written to the samples table (reserved column)");
+            } else if let Some((existing, _)) = columns.iter().find(|(column, _)| column.eq_ignore_ascii_case(&name)) {
+                log::warn!("Node attribute '{name}' is not written to the samples table (duplicate of column '{existing}')");
+            } else {
+                columns.push((name, attribute_type));
+            }
+        }
+        for (name, attribute_type) in &columns {
+            let sql_type = match attribute_type {
+                AttributeType::Boolean | AttributeType::Integer => "INTEGER",
+                AttributeType::Float => "REAL",
+                AttributeType::Text => "TEXT",
+            };
+            transaction.execute(&format!("ALTER TABLE samples ADD COLUMN {} {sql_type}", sql_identifier(name)), [])?;
+        }
+
+        let mut column_names: Vec<String> = SAMPLE_COLUMNS.iter().map(|c| c.to_string()).collect();
+        column_names.extend(columns.iter().map(|(name, _)| sql_identifier(name)));
+        let placeholders: Vec<String> = (1..=column_names.len()).map(|i| format!("?{i}")).collect();
+
+        let mut statement = transaction.prepare(&format!(
+            "INSERT INTO samples ({}) VALUES ({})", column_names.join(", "), placeholders.join(", ")
+        ))?;
+        for node in self.graph.node_indices() {
+            let node_label = &self.graph[node];
+            let mut values: Vec<SqlValue> = vec![
+                SqlValue::Integer(node.index() as i64),
+                node_label.id.clone().map_or(SqlValue::Null, SqlValue::Text),
+                node_label.label.clone().map_or(SqlValue::Null, SqlValue::Text),
+                SqlValue::Real(node_label.label_confidence),
+                node_label.runner_up_label.clone().map_or(SqlValue::Null, SqlValue::Text),
+                node_label.runner_up_label.as_ref().map_or(SqlValue::Null, |_| SqlValue::Real(node_label.runner_up_confidence)),
+                node_label.rank_confidence.as_deref().map_or(SqlValue::Null, |confidence| SqlValue::Text(format_rank_confidence(confidence))),
+                node_label.abstention.clone().map_or(SqlValue::Null, SqlValue::Text),
+                match &node_label.label_probabilities {
+                    Some(probabilities) => SqlValue::Text(serde_json::to_string(probabilities)?),
+                    None => SqlValue::Null
+                },
+                node_label.community.map_or(SqlValue::Null, |community| SqlValue::Integer(community as i64)),
+                node_label.layout.map_or(SqlValue::Null, |layout| SqlValue::Real(layout[0])),
+                node_label.layout.map_or(SqlValue::Null, |layout| SqlValue::Real(layout[1])),
+            ];
+            for (name, attribute_type) in &columns {
+                values.push(match node_label.attributes.get(name).map(|value| value.cast(attribute_type)) {
+                    Some(AttributeValue::Boolean(value)) => SqlValue::Integer(value as i64),
+                    Some(AttributeValue::Integer(value)) => SqlValue::Integer(value),
+                    Some(AttributeValue::Float(value)) => SqlValue::Real(value),
+                    Some(AttributeValue::Text(value)) => SqlValue::Text(value),
+                    None => SqlValue::Null,
+                });
+            }
+            statement.execute(params_from_iter(values))?;
+        }
+        Ok(())
+    }
+
+    fn insert_edges(&self, transaction: &Transaction) -> Result<(), NetviewError> {
+        let mut statement = transaction.prepare(
+            "INSERT INTO edges (edge_index, source, target, source_id, target_id, weight, ani, aai, af)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+        )?;
+        for edge_ref in self.graph.edge_references() {
+            let edge = edge_ref.weight();
+            statement.execute(params![
+                edge.index,
+                edge_ref.source().index(),
+                edge_ref.target().index(),
+                self.graph[edge_ref.source()].id,
+                self.graph[edge_ref.target()].id,
+                edge.weight,
+                edge.ani,
+                edge.aai,
+                edge.af
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn insert_distances(&self, transaction: &Transaction) -> Result<(), NetviewError> {
+
+        let distances = match (self.distances, &self.pairs) {
+            (Some(distances), DistancePairs::All | DistancePairs::Mknn) => make_symmetrical(distances)?,
+            _ => return Ok(())
+        };
+        let af = match self.af {
+            Some(af) => Some(make_symmetrical(af)?),
+            None => None
+        };
+
+        let n = self.graph.node_count();
+        if distances.len() != n || af.as_ref().is_some_and(|af| af.len() != n) {
+            return Err(NetviewError::InvalidDimensions);
+        }
+
+        let mknn: HashSet<(usize, usize)> = self.graph.edge_references()
+            .map(|edge| {
+                let (i, j) = (edge.source().index(), edge.target().index());
+                (i.min(j), i.max(j))
+            })
+            .collect();
+
+        let pairs: Vec<(usize, usize)> = match self.pairs {
+            DistancePairs::All => (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).collect(),
+            _ => {
+                let mut pairs: Vec<_> = mknn.iter().copied().collect();
+                pairs.sort();
+                pairs
+            }
+        };
+
+        let mut statement = transaction.prepare(
+            "INSERT INTO distances (i, j, i_id, j_id, distance, af, mknn) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+        )?;
+        for (i, j) in pairs {
+            let (node_i, node_j) = (NodeIndex::new(i), NodeIndex::new(j));
+            statement.execute(params![
+                i,
+                j,
+                self.graph[node_i].id,
+                self.graph[node_j].id,
+                distances[i][j],
+                af.as_ref().map(|af| af[i][j]),
+                mknn.contains(&(i, j))
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn insert_parameters(&self, transaction: &Transaction) -> Result<(), NetviewError> {
+
+        let mut parameters: Vec<(String, String)> = Vec::new();
+
+        let config = self.config.or(self.metadata.and_then(|metadata| metadata.config.as_ref()));
+        if let Some(config) = config {
+            flatten_json("", &serde_json::to_value(config)?, &mut parameters);
+        }
+
+        if let Some(metadata) = self.metadata {
+            let mut run = serde_json::to_value(metadata)?;
+            if let Some(run) = run.as_object_mut() {
+                run.remove("config");
+                run.remove("inputs");
+            }
+            flatten_json("run", &run, &mut parameters);
+
+            let mut statement = transaction.prepare("INSERT INTO inputs (name, path, sha256) VALUES (?1, ?2, ?3)")?;
+            for input in &metadata.inputs {
+                statement.execute(params![input.name, input.path.display().to_string(), input.sha256])?;
+            }
+        }
+
+        let mut statement = transaction.prepare("INSERT INTO parameters (key, value) VALUES (?1, ?2)")?;
+        for (key, value) in parameters {
+            statement.execute(params![key, value])?;
+        }
+        Ok(())
+    }
+}
+
+// Flatten nested JSON objects into dot-separated keys with scalar values
+fn flatten_json(prefix: &str, value: &serde_json::Value, parameters: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                flatten_json(&key, value, parameters);
+            }
+        },
+        serde_json::Value::String(string) => parameters.push((prefix.to_string(), string.clone())),
+        serde_json::Value::Null => {},
+        value => parameters.push((prefix.to_string(), value.to_string()))
+    }
+}
+
+
+// Quoted SQL identifier for a column name from a metadata table
+fn sql_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::mknn::convert_to_graph;
+
+    #[test]
+    fn test_sqlite_export() {
+        let mnn = vec![vec![1], vec![0, 2], vec![1]];
+        let dist = vec![vec![0.0, 0.2, 2.0], vec![0.2, 0.0, 0.4], vec![2.0, 0.4, 0.0]];
+        let ids = Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        let graph = convert_to_graph(&mnn, Some(&dist), None, ids, None).unwrap();
+
+        let config = NetviewConfig::default();
+        let metadata = GraphMetadata::new().k(1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("netview.sqlite");
+
+        SqliteExporter::new(&graph)
+            .distances(&dist, None, DistancePairs::All)
+            .config(&config)
+            .metadata(&metadata)
+            .write(&path)
+            .unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let count = |table: &str| -> usize {
+            connection.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(count("samples"), 3);
+        assert_eq!(count("edges"), 2);
+        assert_eq!(count("distances"), 3);
+
+        let mut statement = connection.prepare(
+            "SELECT reference FROM distance_pairs WHERE query = 'b' AND distance <= 0.5 ORDER BY reference"
+        ).unwrap();
+        let references: Vec<String> = statement.query_map([], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(references, vec!["a", "c"]);
+
+        let mknn: usize = connection.query_row("SELECT SUM(mknn) FROM distances", [], |row| row.get(0)).unwrap();
+        assert_eq!(mknn, 2);
+
+        let k: String = connection.query_row("SELECT value FROM parameters WHERE key = 'run.k'", [], |row| row.get(0)).unwrap();
+        assert_eq!(k, "1");
+        let iterations: String = connection.query_row(
+            "SELECT value FROM parameters WHERE key = 'label.max_iterations'", [], |row| row.get(0)
+        ).unwrap();
+        assert_eq!(iterations, "20");
+    }
+
+    #[test]
+    fn test_sqlite_mknn_pairs_only() {
+        let mnn = vec![vec![1], vec![0], vec![]];
+        let dist = vec![vec![0.0], vec![0.2, 0.0], vec![2.0, 0.4, 0.0]];
+        let graph = convert_to_graph(&mnn, Some(&dist), None, None, None).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("netview.sqlite");
+        SqliteExporter::new(&graph).distances(&dist, None, DistancePairs::Mknn).write(&path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let (i, j, distance): (usize, usize, f64) = connection.query_row(
+            "SELECT i, j, distance FROM distances", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        ).unwrap();
+        assert_eq!((i, j, distance), (0, 1, 0.2));
+    }
+
+    #[test]
+    fn test_sqlite_sample_attributes() {
+        use crate::metadata::AttributeValue;
+
+        let mnn = vec![vec![1], vec![0]];
+        let dist = vec![vec![0.0], vec![0.2, 0.0]];
+        let mut graph = convert_to_graph(&mnn, Some(&dist), None, None, None).unwrap();
+        graph[NodeIndex::new(0)].attributes.insert("year".to_string(), AttributeValue::Integer(2019));
+        graph[NodeIndex::new(1)].attributes.insert("year".to_string(), AttributeValue::Float(2020.5));
+        graph[NodeIndex::new(1)].attributes.insert("host country".to_string(), AttributeValue::Text("PNG".to_string()));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("netview.sqlite");
+        SqliteExporter::new(&graph).write(&path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let (year, country): (f64, Option<String>) = connection.query_row(
+            "SELECT year, \"host country\" FROM samples WHERE node_index = 0", [], |row| Ok((row.get(0)?, row.get(1)?))
+        ).unwrap();
+        assert_eq!((year, country), (2019.0, None));
+        let country: String = connection.query_row(
+            "SELECT \"host country\" FROM samples WHERE node_index = 1", [], |row| row.get(0)
+        ).unwrap();
+        assert_eq!(country, "PNG");
+    }
+
+    #[test]
+    fn test_sqlite_sample_attributes_case_insensitive() {
+        use crate::metadata::AttributeValue;
+
+        // Column names differing only in case from a reserved column or another attribute are skipped
+        let mnn = vec![vec![1], vec![0]];
+        let mut graph = convert_to_graph(&mnn, None, None, None, None).unwrap();
+        graph[NodeIndex::new(0)].attributes.insert("Label".to_string(), AttributeValue::Text("serotype".to_string()));
+        graph[NodeIndex::new(0)].attributes.insert("ID".to_string(), AttributeValue::Text("x".to_string()));
+        graph[NodeIndex::new(0)].attributes.insert("Year".to_string(), AttributeValue::Integer(2019));
+        graph[NodeIndex::new(1)].attributes.insert("year".to_string(), AttributeValue::Integer(2020));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("netview.sqlite");
+        SqliteExporter::new(&graph).write(&path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let columns: usize = connection.query_row("SELECT COUNT(*) FROM pragma_table_info('samples')", [], |row| row.get(0)).unwrap();
+        assert_eq!(columns, SAMPLE_COLUMNS.len() + 1);
+        let year: i64 = connection.query_row("SELECT \"Year\" FROM samples WHERE node_index = 0", [], |row| row.get(0)).unwrap();
+        assert_eq!(year, 2019);
+        let label: Option<String> = connection.query_row("SELECT label FROM samples WHERE node_index = 0", [], |row| row.get(0)).unwrap();
+        assert_eq!(label, None);
+    }
+
+    #[test]
+    fn test_sqlite_sample_predictions() {
+        let mnn = vec![vec![1], vec![0]];
+        let dist = vec![vec![0.0], vec![0.2, 0.0]];
+        let mut graph = convert_to_graph(&mnn, Some(&dist), None, None, None).unwrap();
+        graph[NodeIndex::new(1)].runner_up_label = Some("B".to_string());
+        graph[NodeIndex::new(1)].runner_up_confidence = 0.25;
+        graph[NodeIndex::new(1)].abstention = Some("distance".to_string());
+        graph[NodeIndex::new(1)].community = Some(3);
+        graph[NodeIndex::new(1)].layout = Some([0.5, -1.0]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("netview.sqlite");
+        SqliteExporter::new(&graph).write(&path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        type PredictionRow = (Option<String>, Option<f64>, Option<String>, Option<i64>, Option<f64>);
+        let row: PredictionRow = connection.query_row(
+            "SELECT runner_up_label, runner_up_confidence, abstention, community, layout_y FROM samples WHERE node_index = 1", 
+            [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        ).unwrap();
+        assert_eq!(row, (Some("B".to_string()), Some(0.25), Some("distance".to_string()), Some(3), Some(-1.0)));
+        let nulls: usize = connection.query_row(
+            "SELECT COUNT(*) FROM samples WHERE runner_up_confidence IS NULL AND community IS NULL", [], |row| row.get(0)
+        ).unwrap();
+        assert_eq!(nulls, 1);
+    }
+}
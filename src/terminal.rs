@@ -1,11 +1,17 @@
 use std::path::PathBuf;
 use clap::{Args, Parser, Subcommand};
 
-use crate::{centrality::NodeCentrality, mknn::GraphFormat};
+use crate::{centrality::NodeCentrality, community::CommunityMethod, dist::TreeMethod, label::{LabelColumns, LabelDelimiter, PropagationUpdate, VoteReportFormat}, metadata::AttributeFilter, mknn::{DotCluster, EdgeAttribute, GraphFormat, SparseWeight}, tune::TuneMetric};
 
 #[cfg(feature = "plot")]
 use crate::plot::PlotFormat;
 
+#[cfg(feature = "columnar")]
+use crate::columnar::ColumnarFormat;
+
+#[cfg(feature = "sqlite")]
+use crate::sqlite::DistancePairs;
+
 /// Netview
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -25,15 +31,35 @@ pub enum Commands {
     Dist(DistArgs),
     /// Mutual nearest neighbor population graph computation 
     Graph(GraphArgs),
+    /// Neighbor-joining or UPGMA tree from a distance matrix
+    Tree(TreeArgs),
+    /// Principal coordinates analysis (classical MDS) of a distance matrix
+    Pcoa(PcoaArgs),
+    /// Community detection on a graph with Louvain or Leiden
+    Community(CommunityArgs),
+    /// Consensus communities across graphs (e.g. different k) and seeds
+    Consensus(ConsensusArgs),
+    /// Compare partitions (labels or communities) with ARI, NMI, purity and V-measure
+    Compare(CompareArgs),
     /// Label propagation to predict node labels on a graph
     Label(LabelArgs),
     /// Stratified label-based dereplication of input sequences 
     Derep(DerepArgs),
     /// Stratified k-fold cross-validation for prediction
     Xval(CrossValidationArgs),
+    /// Leave-one-out evaluation of label propagation on a labelled graph
+    Eval(EvalArgs),
+    /// Grid search over k, centrality and vote weights of label propagation
+    Tune(TuneArgs),
     #[cfg(feature = "plot")]
     /// Plot a graph using the Netview plotting library
-    Plot(PlotArgs)
+    Plot(PlotArgs),
+    #[cfg(feature = "columnar")]
+    /// Export graph tables and matrices to Parquet or Arrow
+    Export(ExportArgs),
+    #[cfg(feature = "sqlite")]
+    /// Export a netview run to a SQLite database
+    Sqlite(SqliteArgs)
 }
 
 
@@ -48,12 +74,17 @@ pub struct PredictArgs {
     /// Database labels, in order of database genomes (.csv)
     #[clap(long, short = 'l', required = true)]
     pub labels: PathBuf,
+    #[clap(flatten)]
+    pub label_columns: LabelColumnArgs,
     /// Output directory of working data and results
     #[clap(long, short = 'o', default_value="netview")]
     pub outdir: PathBuf,
     /// K parameter for mutual nearest neighbor algorithm
-    #[clap(long = "mknn", short = 'k', num_args(0..), default_value="20")]
-    pub k: usize,
+    /// 
+    /// Defaults to 'graph.k' of the configuration (--json, --toml)
+    /// or 20 without configuration.
+    #[clap(long = "mknn", short = 'k')]
+    pub k: Option<usize>,
     /// Propagate all labels across the graph topology
     #[clap(long, short = 'a')]
     pub all: bool,
@@ -77,6 +108,13 @@ pub struct PredictArgs {
     /// in sparse distance matrices where there is no similarity at all (d >= 100.0)
     #[clap(long, short='e')]
     pub edge_threshold: Option<f64>,
+    /// Write the per-node label vote breakdown of the query nodes
+    /// 
+    /// Lists every labelled neighbor with its distance, AF, centrality 
+    /// and vote weight, as well as the summed votes per label, in 
+    /// {basename}.predict.votes.{tsv,jsonl} next to the predictions.
+    #[clap(long, short = 'v')]
+    pub votes: Option<VoteReportFormat>,
     /// Netview configuration as JSON file (.json)
     #[clap(long)]
     pub json: Option<PathBuf>,
@@ -85,13 +123,171 @@ pub struct PredictArgs {
     pub toml: Option<PathBuf>,
 }
 
+#[derive(Debug, Args)]
+pub struct TreeArgs {
+    /// Distance matrix for tree construction (square or lower triangular)
+    /// 
+    /// Compressed matrices (.gz, .bz2, .xz) are read transparently,
+    /// use '-' to read from stdin and '--output -' to write to stdout.
+    #[clap(long, short = 'd', required = true)]
+    pub dist: PathBuf,
+    /// Node identifier file for tip names, otherwise tips are named by index
+    #[clap(long, short = 'i', required = false)]
+    pub ids: Option<PathBuf>,
+    /// Labels in order of the matrix rows to annotate on the tips (.csv)
+    #[clap(long, short = 'l', required = false)]
+    pub labels: Option<PathBuf>,
+    /// Tree construction method
+    #[clap(long, short = 'm', default_value="nj")]
+    pub method: TreeMethod,
+    /// Tree output file (.nwk)
+    #[clap(long, short = 'o', default_value="tree.nwk")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct PcoaArgs {
+    /// Distance matrix for ordination (square or lower triangular)
+    /// 
+    /// Compressed matrices (.gz, .bz2, .xz) are read transparently
+    /// and '-' reads from stdin.
+    #[clap(long, short = 'd', required = true)]
+    pub dist: PathBuf,
+    /// Node identifier file for sample names, otherwise samples are named by index
+    #[clap(long, short = 'i', required = false)]
+    pub ids: Option<PathBuf>,
+    /// Number of principal coordinates to compute
+    #[clap(long, short = 'm', default_value="2")]
+    pub dimensions: usize,
+    /// Maximum number of iterations of the eigensolver
+    #[clap(long, default_value="1000")]
+    pub max_iterations: usize,
+    /// Convergence tolerance of the eigensolver (residual relative to the largest eigenvalue)
+    #[clap(long, default_value="1e-8")]
+    pub tolerance: f64,
+    /// Per-sample coordinates output file (.tsv)
+    #[clap(long, short = 'o', default_value="pcoa.tsv")]
+    pub output: PathBuf,
+    /// Eigenvalues and explained variance output file (.tsv)
+    #[clap(long, short = 'v', required = false)]
+    pub variance: Option<PathBuf>,
+    /// Graph (.json) computed from the same matrix to add the first two coordinates as node layout
+    #[clap(long, short = 'g', required = false)]
+    pub graph: Option<PathBuf>,
+    /// Graph output file (.json) with node layout, otherwise '--graph' is updated in place
+    #[clap(long, required = false, requires = "graph")]
+    pub graph_output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct CommunityArgs {
+    /// Graph for community detection (.json)
+    #[clap(long, short = 'g', required = true)]
+    pub graph: PathBuf,
+    /// Community detection method
+    #[clap(long, short = 'm', default_value="leiden")]
+    pub method: CommunityMethod,
+    /// Resolution of the modularity, larger values give smaller communities
+    #[clap(long, short = 'r', default_value="1.0")]
+    pub resolution: f64,
+    /// Seed for reproducible community detection
+    #[clap(long, short = 's', default_value="42")]
+    pub seed: u64,
+    /// Edge distances are fractions (0 - 1) instead of percent (0 - 100)
+    #[clap(long)]
+    pub fraction: bool,
+    /// Length of random walks for walktrap
+    #[clap(long, default_value="4")]
+    pub steps: usize,
+    /// Cut the walktrap dendrogram into this number of communities instead of maximum modularity
+    #[clap(long, required = false)]
+    pub communities: Option<usize>,
+    /// Walktrap merge dendrogram output file (.tsv)
+    #[clap(long, required = false)]
+    pub dendrogram: Option<PathBuf>,
+    /// Walktrap merge dendrogram output file with node identifier tips (.nwk)
+    #[clap(long, required = false)]
+    pub dendrogram_newick: Option<PathBuf>,
+    /// Community membership output file (.tsv)
+    #[clap(long, short = 'o', default_value="communities.tsv")]
+    pub output: PathBuf,
+    /// Graph output file with community membership on nodes (.json)
+    #[clap(long, short = 'j', required = false)]
+    pub graph_output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConsensusArgs {
+    /// Graphs of the same nodes for consensus communities (.json)
+    #[clap(long, short = 'g', num_args(1..), required = true)]
+    pub graphs: Vec<PathBuf>,
+    /// Community detection method for each run
+    #[clap(long, short = 'm', default_value="leiden")]
+    pub method: CommunityMethod,
+    /// Resolution of the modularity for each run and the consensus network
+    #[clap(long, short = 'r', default_value="1.0")]
+    pub resolution: f64,
+    /// Number of runs with different seeds on each graph
+    #[clap(long, short = 'n', default_value="10")]
+    pub runs: u64,
+    /// Seed of the first run, subsequent runs increment the seed
+    #[clap(long, short = 's', default_value="42")]
+    pub seed: u64,
+    /// Minimum co-assignment of node pairs in the consensus network
+    #[clap(long, short = 't', default_value="0.5")]
+    pub threshold: f64,
+    /// Nodes with lower stability are flagged as unstable
+    #[clap(long, default_value="0.75")]
+    pub min_stability: f64,
+    /// Edge distances are fractions (0 - 1) instead of percent (0 - 100)
+    #[clap(long)]
+    pub fraction: bool,
+    /// Consensus membership and stability output file (.tsv)
+    #[clap(long, short = 'o', default_value="consensus.tsv")]
+    pub output: PathBuf,
+    /// Sparse co-assignment matrix output file (.tsv)
+    #[clap(long, required = false)]
+    pub coassignment: Option<PathBuf>,
+    /// First graph with consensus community membership on nodes (.json)
+    #[clap(long, short = 'j', required = false)]
+    pub graph_output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    /// Reference partition e.g. known labels (.json graph, .csv or .tsv table with header)
+    #[clap(long, short = 'r', required = true)]
+    pub reference: PathBuf,
+    /// Reference column: 'label' or 'community' for graphs, column name for tables
+    #[clap(long, required = false)]
+    pub reference_column: Option<String>,
+    /// Predicted partition e.g. communities or propagated labels (.json graph, .csv or .tsv table with header)
+    #[clap(long, short = 'p', required = true)]
+    pub predicted: PathBuf,
+    /// Predicted column: 'label' or 'community' for graphs, column name for tables
+    #[clap(long, required = false)]
+    pub predicted_column: Option<String>,
+    /// Comparison metrics output file (.tsv or .json)
+    #[clap(long, short = 'o', default_value="comparison.tsv")]
+    pub output: PathBuf,
+    /// Contingency table output file (.tsv)
+    #[clap(long, short = 'c', required = false)]
+    pub contingency: Option<PathBuf>,
+}
+
 #[derive(Debug, Args)]
 pub struct GraphArgs {
     /// Distance matrix for graph computation (square)
+    /// 
+    /// Compressed matrices (.gz, .bz2, .xz) are read transparently,
+    /// use '-' to read from stdin and '--output -' to write to stdout.
     #[clap(long, short = 'd', required = true)]
     pub dist: PathBuf,
     /// K parameter for mutual nearest neighbor algorithm
-    #[clap(long = "mknn", short = 'k', num_args(0..), default_value="Vec::from([20])")]
+    /// 
+    /// Not read from configuration files, pass 'graph.k' of a
+    /// configuration (e.g. from 'netview tune') explicitly.
+    #[clap(long = "mknn", short = 'k', num_args(0..), default_value="20")]
     pub k: Vec<usize>,
     /// Alignment fraction matrix for populating edge labels from 'skani'
     #[clap(long, short = 'a', required = false)]
@@ -111,6 +307,36 @@ pub struct GraphArgs {
     /// Output format for graph
     #[clap(long, short = 'f', default_value="json")]
     pub format: GraphFormat,
+    /// If output is an edge list, use node identifiers instead of node indices
+    #[clap(long)]
+    pub edge_ids: bool,
+    /// If output is an edge list, edge attribute columns to include
+    #[clap(long, num_args(0..))]
+    pub edge_attributes: Vec<EdgeAttribute>,
+    /// If output is a Matrix Market file, edge values of the sparse adjacency matrix
+    #[clap(long, default_value="distance")]
+    pub mtx_weight: SparseWeight,
+    /// Write a spectral embedding of the graph to this file (.tsv)
+    #[clap(long, required = false)]
+    pub spectral: Option<PathBuf>,
+    /// Number of spectral embedding dimensions
+    #[clap(long, default_value="2")]
+    pub spectral_dimensions: usize,
+    /// Edge weights for the spectral embedding, unweighted if not set
+    #[clap(long, required = false)]
+    pub spectral_weight: Option<SparseWeight>,
+    /// Maximum number of iterations of the spectral embedding eigensolver
+    #[clap(long, default_value="1000")]
+    pub spectral_max_iterations: usize,
+    /// Convergence tolerance of the spectral embedding eigensolver (residual relative to the largest eigenvalue)
+    #[clap(long, default_value="1e-8")]
+    pub spectral_tolerance: f64,
+    /// If output is DOT, add node tooltips with identifier, label and confidence
+    #[clap(long)]
+    pub dot_tooltip: bool,
+    /// If output is DOT, group nodes into cluster subgraphs
+    #[clap(long)]
+    pub dot_cluster: Option<DotCluster>,
     /// Threads for distance abstraction computation
     #[clap(long, short = 't')]
     pub threads: Option<usize>,
@@ -137,6 +363,13 @@ pub struct LabelArgs {
     /// Label file in order of node indices
     #[clap(long, short = 'l', required = true)]
     pub labels: PathBuf,
+    #[clap(flatten)]
+    pub label_columns: LabelColumnArgs,
+    /// Node metadata table with 'id' column to add as typed node attributes (.csv or .tsv)
+    /// 
+    /// Other columns of the label file are always added as node attributes.
+    #[clap(long)]
+    pub metadata: Option<PathBuf>,
     /// Centrality metric for nodes used in label propagation
     #[clap(long, short = 'c', default_value="betweenness")]
     pub centrality: NodeCentrality,
@@ -152,15 +385,97 @@ pub struct LabelArgs {
     /// Propagate labels for query nodes only
     #[clap(long, short = 'q', num_args(0..))]
     pub query: Option<Vec<String>>,
+    /// Propagate labels for nodes with matching attributes only (e.g. 'country=AU', 'year>=2019')
+    /// 
+    /// Operators are =, !=, <, <=, > and >=, numeric attributes are compared as numbers.
+    /// Repeated filters must all match and combine with --query and --unlabelled.
+    #[clap(long, num_args(1..))]
+    pub filter: Vec<AttributeFilter>,
+    /// Update labels after each iteration or immediately in random node order
+    #[clap(long, default_value="synchronous")]
+    pub update: PropagationUpdate,
+    /// Seed for the random node order of asynchronous updates
+    #[clap(long, default_value="42")]
+    pub seed: u64,
+    /// Hierarchical labels with this rank separator (e.g. ';' for taxonomic lineages)
+    /// 
+    /// Labels are assigned rank by rank and truncated at the first rank where 
+    /// the best taxon has less than --min-support of the total vote.
+    #[clap(long)]
+    pub hierarchy: Option<String>,
+    /// Minimum vote share to assign a rank of hierarchical labels
+    #[clap(long, default_value="0.5")]
+    pub min_support: f64,
+    /// Label spreading with probabilities over labels instead of label votes
+    /// 
+    /// Spreads labels over the similarity-weighted graph (Zhou et al. 2004),
+    /// the most probable label is assigned with its probability as confidence.
+    #[clap(long, short = 's')]
+    pub spreading: bool,
+    /// Weight of neighbor information against the initial labels in label spreading
+    #[clap(long, default_value="0.9")]
+    pub alpha: f64,
+    /// Labelled nodes may change their label in label spreading (soft clamping)
+    #[clap(long)]
+    pub soft: bool,
+    /// Label probabilities from label spreading with one column per label (.csv)
+    #[clap(long)]
+    pub output_probabilities: Option<PathBuf>,
+    /// Assign the novel label to targets with a lower label confidence 
+    #[clap(long)]
+    pub min_confidence: Option<f64>,
+    /// Assign the novel label to targets with a more distant nearest labelled neighbor
+    #[clap(long)]
+    pub max_distance: Option<f64>,
+    /// Assign the novel label to targets with a lower AF to the nearest labelled neighbor
+    #[clap(long)]
+    pub min_af: Option<f64>,
+    /// Label of targets for which label prediction abstains
+    #[clap(long, default_value="novel")]
+    pub novel_label: String,
     /// Propagated labels file in order of node indices
     #[clap(long, short = 'o', default_value="label.prop.csv")]
     pub output_labels: PathBuf,
     /// Netview graph with propagated labels in JSON format 
     #[clap(long, short = 'f', default_value="netview.prop.json")]
     pub output_graph: PathBuf,
+    /// Netview graph with propagated labels and node attributes in DOT format
+    #[clap(long)]
+    pub output_dot: Option<PathBuf>,
+    /// If output DOT is written, add node tooltips with identifier, label, confidence and attributes
+    #[clap(long)]
+    pub dot_tooltip: bool,
+    /// If output DOT is written, fill nodes by the value of this node attribute instead of the label
+    #[clap(long)]
+    pub dot_color: Option<String>,
+    /// Per-node label vote breakdown of the target nodes (.tsv or .jsonl)
+    #[clap(long)]
+    pub output_votes: Option<PathBuf>,
 }
 
 
+#[derive(Debug, Args)]
+pub struct LabelColumnArgs {
+    /// Delimiter of the label file, detected from the extension or header by default
+    #[clap(long, default_value="auto")]
+    pub delimiter: LabelDelimiter,
+    /// Identifier column of the label file
+    #[clap(long, default_value="id")]
+    pub id_column: String,
+    /// Label columns of the label file, labels are predicted for each column separately
+    #[clap(long, num_args(1..), default_value="label")]
+    pub label_column: Vec<String>,
+}
+impl LabelColumnArgs {
+    pub fn columns(&self) -> LabelColumns {
+        LabelColumns {
+            delimiter: self.delimiter,
+            id: self.id_column.clone(),
+            labels: self.label_column.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct CrossValidationArgs {
     /// Genomes for cross-validation dataset (.fasta)
@@ -169,6 +484,8 @@ pub struct CrossValidationArgs {
     /// Label file in order of genomes (.csv)
     #[clap(long, short = 'l', required = true)]
     pub labels: PathBuf,
+    #[clap(flatten)]
+    pub label_columns: LabelColumnArgs,
     /// Number of cross-validation folds
     #[clap(long, short = 'k', default_value="5")]
     pub k_folds: usize,
@@ -183,6 +500,117 @@ pub struct CrossValidationArgs {
     pub outdir: PathBuf,
 }
 
+#[derive(Debug, Args)]
+pub struct EvalArgs {
+    /// Netview graph in JSON format
+    #[clap(long, short = 'g', required = true)]
+    pub graph: PathBuf,
+    /// Label file with known labels, labels of the graph are evaluated by default
+    #[clap(long, short = 'l')]
+    pub labels: Option<PathBuf>,
+    #[clap(flatten)]
+    pub label_columns: LabelColumnArgs,
+    /// Netview configuration with label settings as JSON file (.json)
+    #[clap(long)]
+    pub json: Option<PathBuf>,
+    /// Netview configuration with label settings as TOML file (.toml)
+    #[clap(long)]
+    pub toml: Option<PathBuf>,
+    /// Evaluate a stratified sample of labelled nodes per label
+    #[clap(long, short = 'n')]
+    pub max_per_label: Option<usize>,
+    /// Seed for the stratified sample of labelled nodes
+    #[clap(long, default_value="42")]
+    pub seed: u64,
+    /// Evaluation metrics output file (.tsv or .json)
+    #[clap(long, short = 'o', default_value="evaluation.tsv")]
+    pub output: PathBuf,
+    /// Confusion matrix output file (.tsv)
+    #[clap(long, short = 'c')]
+    pub confusion: Option<PathBuf>,
+    /// Per-class precision, recall and F1 output file (.tsv)
+    #[clap(long)]
+    pub classes: Option<PathBuf>,
+    /// Predicted labels of the evaluated nodes output file (.tsv)
+    #[clap(long)]
+    pub predictions: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct TuneArgs {
+    /// Distance matrix for graph computation (square)
+    #[clap(long, short = 'd', required = true)]
+    pub dist: PathBuf,
+    /// Alignment fraction matrix for populating edge labels from 'skani'
+    #[clap(long, short = 'a')]
+    pub afrac: Option<PathBuf>,
+    /// Node identifier file in order of the distance matrix
+    #[clap(long, short = 'i', required = true)]
+    pub ids: PathBuf,
+    /// Label file with known labels, the first label column is evaluated
+    #[clap(long, short = 'l', required = true)]
+    pub labels: PathBuf,
+    #[clap(flatten)]
+    pub label_columns: LabelColumnArgs,
+    /// Output directory of 'xval' with folds, leave-one-out evaluation by default
+    /// 
+    /// Training labels of each fold are propagated to its test nodes 
+    /// on the graph of all genomes in the distance matrix.
+    #[clap(long)]
+    pub folds: Option<PathBuf>,
+    /// Base configuration with other label settings as JSON file (.json)
+    #[clap(long)]
+    pub json: Option<PathBuf>,
+    /// Base configuration with other label settings as TOML file (.toml)
+    #[clap(long)]
+    pub toml: Option<PathBuf>,
+    /// K parameters for mutual nearest neighbor graphs [default: 'graph.k' of the base configuration]
+    #[clap(long = "mknn", short = 'k', num_args(1..))]
+    pub k: Option<Vec<usize>>,
+    /// Centrality metrics for nodes used in label propagation [default: base configuration]
+    #[clap(long, short = 'c', num_args(1..))]
+    pub centrality_metric: Option<Vec<NodeCentrality>>,
+    /// Use neighbor centrality in vote weight determination [default: base configuration]
+    #[clap(long, num_args(1..))]
+    pub neighbor_centrality: Option<Vec<bool>>,
+    /// Vote weights of node centrality [default: base configuration]
+    #[clap(long, num_args(1..))]
+    pub centrality_weight: Option<Vec<f64>>,
+    /// Vote weights of similarity (1 - distance) [default: base configuration]
+    #[clap(long, num_args(1..))]
+    pub distance_weight: Option<Vec<f64>>,
+    /// Vote weights of alignment fraction [default: base configuration]
+    #[clap(long, num_args(1..))]
+    pub af_weight: Option<Vec<f64>>,
+    /// Vote weights of ANI [default: base configuration]
+    #[clap(long, num_args(1..))]
+    pub ani_weight: Option<Vec<f64>>,
+    /// Vote weights of AAI [default: base configuration]
+    #[clap(long, num_args(1..))]
+    pub aai_weight: Option<Vec<f64>>,
+    /// Metric to rank parameter combinations
+    #[clap(long, short = 'm', default_value="macro-f1")]
+    pub metric: TuneMetric,
+    /// Evaluate a stratified sample of labelled nodes per label in leave-one-out evaluation
+    #[clap(long, short = 'n')]
+    pub max_per_label: Option<usize>,
+    /// Seed for the stratified sample of labelled nodes
+    #[clap(long, default_value="42")]
+    pub seed: u64,
+    /// Ranked parameter combinations with metrics (.tsv)
+    #[clap(long, short = 'o', default_value="tune.tsv")]
+    pub output: PathBuf,
+    /// Netview configuration with the best parameter combination (.toml)
+    #[clap(long, default_value="netview.tune.toml")]
+    pub output_config: PathBuf,
+    /// Threads for distance abstraction computation
+    #[clap(long, short = 't')]
+    pub threads: Option<usize>,
+    /// Chunk size for distance abstraction computation
+    #[clap(long)]
+    pub chunk_size: Option<usize>,
+}
+
 #[derive(Debug, Args)]
 pub struct DerepArgs {
     /// Genomes for replication by label (.fasta)
@@ -191,6 +619,8 @@ pub struct DerepArgs {
     /// Label file in order of genomes (.csv)
     #[clap(long, short = 'l', required = true)]
     pub labels: PathBuf,
+    #[clap(flatten)]
+    pub label_columns: LabelColumnArgs,
     /// Exclude these labels, all unlabelled are excluded by default
     #[clap(long, short = 'e', required = false, num_args(0..), default_value="Vec::new()")]
     pub exclude: Vec<String>,
@@ -261,6 +691,55 @@ pub struct PlotArgs {
 
 }
 
+#[cfg(feature = "columnar")]
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Netview graph in JSON format, exported as node and edge tables
+    #[clap(long, short = 'g')]
+    pub graph: Option<PathBuf>,
+    /// Distance matrix, exported as long-form table
+    #[clap(long, short = 'd')]
+    pub dist: Option<PathBuf>,
+    /// Node identifier file for the distance matrix
+    #[clap(long, short = 'i')]
+    pub ids: Option<PathBuf>,
+    /// Include the diagonal of the distance matrix in the long-form table
+    #[clap(long)]
+    pub diagonal: bool,
+    /// Basename of output files ({output}.nodes, {output}.edges, {output}.matrix)
+    #[clap(long, short = 'o', default_value="netview")]
+    pub output: PathBuf,
+    /// Output format for tables
+    #[clap(long, short = 'f', default_value="parquet")]
+    pub format: ColumnarFormat,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Args)]
+pub struct SqliteArgs {
+    /// Netview graph in JSON format
+    #[clap(long, short = 'g', required = true)]
+    pub graph: PathBuf,
+    /// Distance matrix in order of graph nodes
+    #[clap(long, short = 'd')]
+    pub dist: Option<PathBuf>,
+    /// Alignment fraction matrix in order of graph nodes
+    #[clap(long, short = 'a')]
+    pub afrac: Option<PathBuf>,
+    /// Pairs of samples written to the distance table
+    #[clap(long, short = 'p', default_value="mknn")]
+    pub pairs: DistancePairs,
+    /// Netview configuration of the run as JSON file (.json)
+    #[clap(long)]
+    pub json: Option<PathBuf>,
+    /// Netview configuration of the run as TOML file (.toml)
+    #[clap(long)]
+    pub toml: Option<PathBuf>,
+    /// Output SQLite database
+    #[clap(long, short = 'o', default_value="netview.sqlite")]
+    pub output: PathBuf,
+}
+
 pub fn get_styles() -> clap::builder::Styles {
 	clap::builder::Styles::styled()
 		.header(
@@ -0,0 +1,261 @@
+// Grid search over graph and label propagation parameters
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::centrality::{node_centrality, NodeCentrality};
+use crate::config::{LabelConfig, NetviewConfig};
+use crate::error::NetviewError;
+use crate::evaluation::{evaluate_folds, leave_one_out, Evaluation};
+use crate::label::{label_nodes, VoteWeights};
+use crate::mknn::node_id_or_index;
+use crate::netview::NetviewGraph;
+use crate::utils::get_file_writer;
+use crate::validation::Fold;
+
+/// Values of each parameter, all combinations are evaluated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuneGrid {
+    pub k: Vec<usize>,
+    pub centrality_metric: Vec<NodeCentrality>,
+    pub neighbor_centrality_vote: Vec<bool>,
+    pub centrality: Vec<f64>,     // Vote weight components, see `VoteWeights`
+    pub weight: Vec<f64>,
+    pub af: Vec<f64>,
+    pub ani: Vec<f64>,
+    pub aai: Vec<f64>,
+}
+impl Default for TuneGrid {
+    fn default() -> Self {
+        Self::from_config(&NetviewConfig::default())
+    }
+}
+impl TuneGrid {
+    // Grid of the single parameter combination of a configuration
+    pub fn from_config(config: &NetviewConfig) -> Self {
+        let (label, weights) = (&config.label, &config.label.vote_weights);
+        Self {
+            k: vec![config.graph.k],
+            centrality_metric: vec![label.centrality_metric.clone()],
+            neighbor_centrality_vote: vec![label.neighbor_centrality_vote],
+            centrality: vec![weights.centrality],
+            weight: vec![weights.weight],
+            af: vec![weights.af],
+            ani: vec![weights.ani],
+            aai: vec![weights.aai],
+        }
+    }
+    // Label configurations of all combinations of the label parameters, other settings from the base configuration
+    pub fn label_configs(&self, base: &LabelConfig) -> Vec<LabelConfig> {
+        let mut configs = Vec::new();
+        for centrality_metric in &self.centrality_metric {
+            for &neighbor_centrality_vote in &self.neighbor_centrality_vote {
+                for &centrality in &self.centrality {
+                    for &weight in &self.weight {
+                        for &af in &self.af {
+                            for &ani in &self.ani {
+                                for &aai in &self.aai {
+                                    configs.push(LabelConfig {
+                                        centrality_metric: centrality_metric.clone(),
+                                        neighbor_centrality_vote,
+                                        vote_weights: VoteWeights { centrality, weight, af, ani, aai },
+                                        ..base.clone()
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        configs
+    }
+    // Number of parameter combinations
+    pub fn size(&self) -> usize {
+        self.k.len() * self.centrality_metric.len() * self.neighbor_centrality_vote.len()
+            * self.centrality.len() * self.weight.len() * self.af.len() * self.ani.len() * self.aai.len()
+    }
+}
+
+/// Validation of each parameter combination
+#[derive(Debug, Clone)]
+pub enum TuneValidation {
+    LeaveOneOut { max_per_label: Option<usize>, seed: u64 },
+    Folds(Vec<Fold>),
+}
+
+/// Metric to rank parameter combinations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum TuneMetric {
+    Accuracy,
+    #[default]
+    MacroF1,
+}
+impl std::fmt::Display for TuneMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            TuneMetric::Accuracy => "accuracy",
+            TuneMetric::MacroF1 => "macro-f1",
+        };
+        write!(f, "{}", output)
+    }
+}
+impl TuneMetric {
+    pub fn value(&self, evaluation: &Evaluation) -> f64 {
+        match self {
+            TuneMetric::Accuracy => evaluation.accuracy,
+            TuneMetric::MacroF1 => evaluation.macro_f1,
+        }
+    }
+}
+
+/// Evaluation of a parameter combination
+#[derive(Debug, Clone)]
+pub struct TuneResult {
+    pub k: usize,
+    pub label: LabelConfig,
+    pub evaluation: Evaluation,
+}
+
+// Label the nodes of a graph by identifier, other nodes are unlabelled
+pub fn label_nodes_by_id(graph: &mut NetviewGraph, labels: &HashMap<String, String>) -> Result<(), NetviewError> {
+    let node_labels = graph.node_weights().map(|node| labels.get(&node_id_or_index(node)).cloned()).collect();
+    label_nodes(graph, node_labels)
+}
+
+/// Evaluates all parameter combinations of the grid on the labelled graphs of each k
+/// and returns the results ranked by the metric (ties by accuracy).
+///
+/// With leave-one-out validation, the labels of the graphs are evaluated. With
+/// cross-validation folds, the graphs are labelled with the training labels of each fold.
+pub fn tune_label_propagation(
+    graphs: &[(usize, NetviewGraph)],
+    grid: &TuneGrid,
+    base: &LabelConfig,
+    validation: &TuneValidation,
+    metric: &TuneMetric
+) -> Result<Vec<TuneResult>, NetviewError> {
+
+    let label_configs = grid.label_configs(base);
+    log::info!("Evaluating {} parameter combinations ({} graphs)", label_configs.len() * graphs.len(), graphs.len());
+
+    let mut results = Vec::new();
+    for (k, graph) in graphs {
+        // Node centrality depends only on the graph topology, computed once per metric
+        let centralities: HashMap<&NodeCentrality, HashMap<usize, f64>> = grid.centrality_metric.par_iter()
+            .map(|metric| (metric, node_centrality(graph, metric)))
+            .collect();
+
+        let evaluations = label_configs.par_iter().map(|config| {
+            let centrality = &centralities[&config.centrality_metric];
+            match validation {
+                TuneValidation::LeaveOneOut { max_per_label, seed } => leave_one_out(graph, config, centrality, *max_per_label, *seed),
+                TuneValidation::Folds(folds) => evaluate_folds(graph, config, centrality, folds),
+            }
+        }).collect::<Result<Vec<_>, NetviewError>>()?;
+
+        results.extend(label_configs.iter().zip(evaluations).map(|(config, evaluation)| {
+            TuneResult { k: *k, label: config.clone(), evaluation }
+        }));
+    }
+
+    results.sort_by(|a, b| {
+        metric.value(&b.evaluation).total_cmp(&metric.value(&a.evaluation))
+            .then(b.evaluation.accuracy.total_cmp(&a.evaluation.accuracy))
+    });
+    Ok(results)
+}
+
+/// Writes the ranked parameter combinations and their metrics as TSV.
+pub fn write_tune_results(results: &[TuneResult], path: &Path) -> Result<(), NetviewError> {
+    let mut writer = get_file_writer(path)?;
+    writeln!(
+        writer,
+        "rank\tk\tcentrality_metric\tneighbor_centrality_vote\tcentrality\tweight\taf\tani\taai\t\
+        nodes\tunpredicted\taccuracy\tmacro_precision\tmacro_recall\tmacro_f1"
+    )?;
+    for (rank, result) in results.iter().enumerate() {
+        let (label, weights, evaluation) = (&result.label, &result.label.vote_weights, &result.evaluation);
+        let centrality_metric = serde_json::to_value(&label.centrality_metric)?;
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            rank + 1, result.k, centrality_metric.as_str().unwrap_or_default(), label.neighbor_centrality_vote,
+            weights.centrality, weights.weight, weights.af, weights.ani, weights.aai,
+            evaluation.nodes, evaluation.unpredicted, evaluation.accuracy,
+            evaluation.macro_precision, evaluation.macro_recall, evaluation.macro_f1
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mknn::mknn_graphs;
+
+    #[test]
+    fn grid_search_ranks_parameter_combinations() {
+        // Two clusters of four labelled genomes (A, B) and an unlabelled genome distant to both
+        let n = 9;
+        let distance: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| {
+            if i == j {
+                0.0
+            } else if (i < 4) == (j < 4) && i < 8 && j < 8 {
+                1.0 + (i + j) as f64 * 0.01
+            } else {
+                50.0
+            }
+        }).collect()).collect();
+        let ids: Vec<String> = (0..n).map(|i| format!("s{i}")).collect();
+        let labels: HashMap<String, String> = ids.iter().take(8)
+            .map(|id| (id.clone(), if id.as_str() < "s4" { "A".to_string() } else { "B".to_string() }))
+            .collect();
+
+        let mut graphs = mknn_graphs(&distance, None, Some(ids), &[2, 3], None, None, None).unwrap();
+        for (_, graph) in graphs.iter_mut() {
+            label_nodes_by_id(graph, &labels).unwrap();
+        }
+
+        let grid = TuneGrid {
+            centrality_metric: vec![NodeCentrality::Degree, NodeCentrality::Closeness],
+            weight: vec![1.0, 2.0],
+            ..Default::default()
+        };
+        assert_eq!(grid.size(), 4);
+
+        // Single combination of a configuration
+        let mut config = NetviewConfig::default();
+        config.graph.k = 3;
+        config.label.centrality_metric = NodeCentrality::Closeness;
+        let base = TuneGrid::from_config(&config);
+        assert_eq!((base.k.clone(), base.size()), (vec![3], 1));
+        assert!(matches!(base.label_configs(&config.label)[0].centrality_metric, NodeCentrality::Closeness));
+        let validation = TuneValidation::LeaveOneOut { max_per_label: None, seed: 42 };
+        let results = tune_label_propagation(&graphs, &grid, &LabelConfig::default(), &validation, &TuneMetric::MacroF1).unwrap();
+
+        assert_eq!(results.len(), 8);
+        assert!(results.windows(2).all(|pair| pair[0].evaluation.macro_f1 >= pair[1].evaluation.macro_f1));
+        assert!((results[0].evaluation.accuracy - 1.0).abs() < 1e-12);
+
+        // Folds with the same labels are evaluated on the test nodes only
+        let folds = vec![Fold {
+            train: labels.iter().filter(|(id, _)| !["s0", "s4"].contains(&id.as_str())).map(|(id, label)| (id.clone(), label.clone())).collect(),
+            test: labels.iter().filter(|(id, _)| ["s0", "s4"].contains(&id.as_str())).map(|(id, label)| (id.clone(), label.clone())).collect(),
+        }];
+        let results = tune_label_propagation(&graphs[..1], &TuneGrid::default(), &LabelConfig::default(), &TuneValidation::Folds(folds), &TuneMetric::Accuracy).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].evaluation.nodes, 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tune.tsv");
+        write_tune_results(&results, &path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.lines().nth(1).unwrap().starts_with("1\t2\tDegree\tfalse\t1\t2\t1\t0\t0\t2\t"));
+    }
+}
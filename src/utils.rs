@@ -1,8 +1,9 @@
-use std::{ffi::OsStr, fs::File, io::{BufReader, BufWriter, Read, Write}, path::{Path, PathBuf}};
+use std::{ffi::OsStr, fs::File, io::{BufReader, BufWriter, Cursor, Read, Write}, path::{Path, PathBuf}};
 use csv::{Reader, ReaderBuilder, Writer, WriterBuilder};
 use needletail::{parse_fastx_file, parser::LineEnding};
 use niffler::{get_reader, get_writer};
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use crate::error::NetviewError;
 
 
@@ -40,10 +41,74 @@ impl CompressionExt for niffler::compression::Format {
     }
 }
 
+/// Computes the SHA-256 checksum of a file as lowercase hex string
+pub fn sha256_checksum(file: &Path) -> Result<String, NetviewError> {
+    let mut reader = BufReader::new(File::open(file)?);
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Whether the file name ends in one of the extensions (e.g. `.tsv`), case-insensitive
+/// and without a compression suffix (`.gz`, `.bz2`, `.xz`)
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let name = [".gz", ".bz2", ".xz"].iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(&name);
+    extensions.iter().any(|extension| name.ends_with(extension))
+}
+
+/// Whether the path refers to stdin or stdout (`-`)
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Opens a file for reading, or stdin if the path is `-`.
+///
+/// Compressed input (gz, bz2, xz, zst) is detected from the magic bytes
+/// and decompressed transparently. Inputs shorter than the magic bytes
+/// are read as uncompressed.
+pub fn get_file_reader(file: &Path) -> Result<Box<dyn Read>, NetviewError> {
+
+    let mut input: Box<dyn Read> = if is_stdio(file) {
+        Box::new(BufReader::new(std::io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(file)?))
+    };
+
+    let mut magic = Vec::with_capacity(5);
+    input.by_ref().take(5).read_to_end(&mut magic)?;
+
+    let too_short = magic.len() < 5;
+    let stream: Box<dyn Read> = Box::new(Cursor::new(magic).chain(input));
+
+    if too_short {
+        return Ok(stream)
+    }
+    let (reader, _format) = get_reader(stream)?;
+    Ok(reader)
+}
+
+/// Creates a file for writing, or writes to stdout if the path is `-`.
+///
+/// Output is compressed if the file extension is `.gz`, `.bz2` or `.xz`,
+/// output to stdout is not compressed.
+pub fn get_file_writer(file: &Path) -> Result<Box<dyn Write>, NetviewError> {
+
+    if is_stdio(file) {
+        return Ok(Box::new(BufWriter::new(std::io::stdout())))
+    }
+
+    let buf_writer = BufWriter::new(File::create(file)?);
+    let writer = get_writer(Box::new(buf_writer), niffler::Format::from_path(file), niffler::compression::Level::Nine)?;
+
+    Ok(writer)
+}
+
 pub fn get_tsv_reader(file: &Path, flexible: bool) -> Result<Reader<Box<dyn Read>>, NetviewError> {
 
-    let buf_reader = BufReader::new(File::open(&file)?);
-    let (reader, _format) = get_reader(Box::new(buf_reader))?;
+    let reader = get_file_reader(file)?;
 
     let csv_reader = ReaderBuilder::new()
         .delimiter(b'\t')
@@ -57,8 +122,7 @@ pub fn get_tsv_writer(
     file: &Path,
 ) -> Result<Writer<Box<dyn Write>>, NetviewError> {
     
-    let buf_writer = BufWriter::new(File::create(&file)?);
-    let writer = get_writer(Box::new(buf_writer), niffler::Format::from_path(file), niffler::compression::Level::Nine)?;
+    let writer = get_file_writer(file)?;
 
     let csv_writer = WriterBuilder::new()
         .delimiter(b'\t')
@@ -1,14 +1,13 @@
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::BufWriter;
 use needletail::parser::LineEnding;
 use rand::seq::SliceRandom;
-use csv::WriterBuilder;
 use needletail::parse_fastx_file;
 
 use crate::error::NetviewError;
-use crate::label::{read_labels_from_file, Label};
+use crate::label::{read_label_table, Label, LabelColumns, LabelDelimiter, LabelTable};
 use crate::utils::write_fasta;
 
 
@@ -34,7 +33,8 @@ fn load_fasta_sequences(fasta: &PathBuf) -> Result<HashMap<String, Vec<u8>>, Net
 }
 
 pub struct CrossFoldValidation {
-    labels: Vec<Label>,                       // Vector of all labels
+    table: LabelTable,                        // Identifier and label columns of the label file
+    labels: Vec<Label>,                       // Vector of all labels (combined label columns)
     seqs: HashMap<String, Vec<u8>>,           // Path to the input FASTA file
     k_folds: usize,                           // Number of folds for cross-validation
     max_samples_per_label: Option<usize>,     // Maximum number of samples per label (optional)
@@ -44,6 +44,7 @@ pub struct CrossFoldValidation {
 impl CrossFoldValidation {
     pub fn new(
         labels: &PathBuf, 
+        label_columns: &LabelColumns,
         fasta: &PathBuf, 
         k_folds: usize, 
         max_samples_per_label: Option<usize>,  // Add max_samples_per_label here
@@ -53,10 +54,13 @@ impl CrossFoldValidation {
             fs::create_dir_all(&outdir)?;
         }
 
-        let labels = read_labels_from_file(labels, false)?;
+        // Folds are stratified by the combination of all label columns
+        let table = read_label_table(labels, label_columns)?;
+        let labels = table.strata();
         let seqs = load_fasta_sequences(fasta)?;
 
         Ok(Self {
+            table,
             labels,
             seqs,
             k_folds,
@@ -161,26 +165,77 @@ impl CrossFoldValidation {
             }
         }
 
-        // Write training labels
-        let train_labels_path = fold_dir.join("train_labels.csv");
-        let mut train_wtr = WriterBuilder::new().from_path(train_labels_path)?;
-        for label in &self.labels {
-            if train_ids.contains(&label.id) {
-                train_wtr.serialize(label)?;
-            }
-        }
-        train_wtr.flush()?;
-
-        // Write test labels
-        let test_labels_path = fold_dir.join("test_labels.csv");
-        let mut test_wtr = WriterBuilder::new().from_path(test_labels_path)?;
-        for label in &self.labels {
-            if test_ids.contains(&label.id) {
-                test_wtr.serialize(label)?;
-            }
-        }
-        test_wtr.flush()?;
+        // Write training and test labels with all label columns
+        let ids_in = |ids: &Vec<String>| -> Vec<String> {
+            self.table.ids.iter().filter(|id| ids.contains(id)).cloned().collect()
+        };
+        self.table.write(fold_dir.join("train_labels.csv"), Some(&ids_in(train_ids)), false)?;
+        self.table.write(fold_dir.join("test_labels.csv"), Some(&ids_in(test_ids)), false)?;
 
         Ok(())
     }
 }
+
+/// Known labels of the training and test nodes of a cross-validation fold
+#[derive(Debug, Clone, Default)]
+pub struct Fold {
+    pub train: HashMap<String, String>,
+    pub test: HashMap<String, String>,
+}
+
+// Labelled identifiers of the first label column of a fold label file, which 
+// is always written as CSV regardless of the delimiter of the input label file
+fn read_fold_labels(path: &Path, label_columns: &LabelColumns) -> Result<HashMap<String, String>, NetviewError> {
+    let columns = LabelColumns { delimiter: LabelDelimiter::Comma, ..label_columns.clone() };
+    let table = read_label_table(path, &columns)?;
+    Ok(table.labels(0).into_iter()
+        .filter_map(|label| label.label.map(|value| (label.id, value)))
+        .collect())
+}
+
+/// Reads the training and test labels of existing cross-validation folds 
+/// (`fold_{i}/train_labels.csv` and `fold_{i}/test_labels.csv` in the output 
+/// directory of `xval`), in order of the fold index.
+pub fn read_folds(outdir: &Path, label_columns: &LabelColumns) -> Result<Vec<Fold>, NetviewError> {
+    let mut fold_dirs: Vec<(usize, PathBuf)> = fs::read_dir(outdir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let index = name.strip_prefix("fold_")?.parse().ok()?;
+            entry.path().is_dir().then(|| (index, entry.path()))
+        })
+        .collect();
+    fold_dirs.sort();
+
+    if fold_dirs.is_empty() {
+        return Err(NetviewError::EvaluationError(format!("no cross-validation folds in {}", outdir.display())))
+    }
+
+    fold_dirs.iter().map(|(_, dir)| {
+        Ok(Fold {
+            train: read_fold_labels(&dir.join("train_labels.csv"), label_columns)?,
+            test: read_fold_labels(&dir.join("test_labels.csv"), label_columns)?,
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_are_read_as_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let fold_dir = dir.path().join("fold_0");
+        fs::create_dir(&fold_dir).unwrap();
+        fs::write(fold_dir.join("train_labels.csv"), "id,label\na,A\nb,\n").unwrap();
+        fs::write(fold_dir.join("test_labels.csv"), "id,label\nc,B\n").unwrap();
+
+        // Delimiter of the input label file (e.g. TSV) does not apply to fold files
+        let columns = LabelColumns { delimiter: LabelDelimiter::Tab, ..Default::default() };
+        let folds = read_folds(dir.path(), &columns).unwrap();
+        assert_eq!(folds.len(), 1);
+        assert_eq!(folds[0].train, HashMap::from([("a".to_string(), "A".to_string())]));
+        assert_eq!(folds[0].test["c"], "B");
+    }
+}
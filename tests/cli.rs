@@ -0,0 +1,48 @@
+// Command-line workflows of the netview binary
+
+use std::path::Path;
+use std::process::Command;
+
+fn netview(dir: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_netview"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn label_writes_dot_filled_by_attribute() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // Two clusters of three samples, the unlabelled sample of each cluster is predicted
+    let n = 6;
+    let distance: Vec<String> = (0..n).map(|i| (0..n).map(|j| {
+        if i == j { "0.0" } else if (i < 3) == (j < 3) { "1.0" } else { "50.0" }.to_string()
+    }).collect::<Vec<_>>().join("\t")).collect();
+    std::fs::write(dir.path().join("dist.tsv"), distance.join("\n") + "\n").unwrap();
+    std::fs::write(dir.path().join("ids.txt"), "s0\ns1\ns2\ns3\ns4\ns5\n").unwrap();
+    std::fs::write(dir.path().join("labels.csv"), "id,label,country\n\
+        s0,A,AU\ns1,A,PNG\ns2,,AU\ns3,B,AU\ns4,B,PNG\ns5,,\n").unwrap();
+
+    netview(dir.path(), &["graph", "-d", "dist.tsv", "-i", "ids.txt", "-k", "2", "-o", "graph.json"]);
+    netview(dir.path(), &[
+        "label", "-g", "graph.json", "-l", "labels.csv", "-o", "labels.prop.csv", "-f", "graph.prop.json",
+        "--output-dot", "graph.prop.dot", "--dot-color", "country", "--dot-tooltip"
+    ]);
+
+    let dot = std::fs::read_to_string(dir.path().join("graph.prop.dot")).unwrap();
+    let fill = |id: &str| -> String {
+        let line = dot.lines().find(|line| line.trim_start().starts_with(&format!("\"{id}\" [label="))).unwrap();
+        line.split("fillcolor=").nth(1).unwrap().split('"').nth(1).unwrap().to_string()
+    };
+
+    // Samples of the same country share a fill color across labels, samples without a country differ
+    assert_eq!(fill("s0"), fill("s3"));
+    assert_eq!(fill("s1"), fill("s4"));
+    assert_ne!(fill("s0"), fill("s1"));
+    assert_ne!(fill("s5"), fill("s0"));
+    assert!(dot.contains("country: PNG"));
+    assert!(dot.contains("label: B"));
+}